@@ -0,0 +1,53 @@
+//! Loads the sample data in `fixtures/` into the Firestore emulator.
+//!
+//! ```sh
+//! APP_ENVIRONMENT=local cargo run --bin seed [fixtures-dir]
+//! ```
+
+use std::path::PathBuf;
+
+use color_eyre::eyre::{self, eyre, Result};
+use tapped_api_rs::{
+    configuration::get_configuration,
+    data::fixtures::Fixtures,
+    startup::connect_firestore,
+    tracing::{get_subscriber, init_subscriber},
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let subscriber = get_subscriber("tapped-seed".into(), "info".into(), std::io::stdout);
+    init_subscriber(subscriber);
+
+    let settings = get_configuration()?;
+    // Never write fixtures into a real project.
+    if settings.firestore.emulator_host.is_none() {
+        eyre::bail!(
+            "refusing to seed without firestore.emulator_host, run with APP_ENVIRONMENT=local"
+        );
+    }
+
+    let directory = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("fixtures"));
+    let fixtures = Fixtures::load(&directory).map_err(|e| eyre!("{e:#}"))?;
+
+    let db = connect_firestore(&settings.firestore).await?;
+    fixtures
+        .seed_firestore(&db)
+        .await
+        .map_err(|e| eyre!("Failed to seed Firestore: {e:#}"))?;
+
+    tracing::info!(
+        "seeded {} users, {} bookings, {} reviews and {} API keys",
+        fixtures.users.len(),
+        fixtures.bookings.len(),
+        fixtures.reviews.len(),
+        fixtures.api_keys.len(),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,407 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::eyre::{self, Result, WrapErr};
+use serde::Deserialize;
+
+use crate::environment::Environment;
+
+/// Everything the API reads at startup. Loaded from `configuration/base.yaml`,
+/// then `configuration/{APP_ENVIRONMENT}.yaml`, then `APP_`-prefixed
+/// environment variables, e.g. `APP_ALGOLIA__USERS_INDEX=stage_users`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub firestore: FirestoreSettings,
+    pub search: SearchSettings,
+    pub algolia: AlgoliaSettings,
+    pub cors: CorsSettings,
+    pub http: HttpSettings,
+    pub rate_limit: RateLimitSettings,
+    pub cache: CacheSettings,
+    pub similarity: SimilaritySettings,
+    pub mail: MailSettings,
+    pub exports: ExportSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    /// How long in-flight requests get to finish after a shutdown signal.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl ServerSettings {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FirestoreSettings {
+    pub project_id: String,
+    /// A service account key file. Without one the default Google
+    /// credentials of the environment are used.
+    pub credentials_path: Option<PathBuf>,
+    /// `host:port` of a Firestore emulator. When set, no credentials are
+    /// used at all.
+    pub emulator_host: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackend {
+    Algolia,
+    /// Scans the Firestore `users` collection. Only suitable for small,
+    /// local data sets.
+    Firestore,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchSettings {
+    pub backend: SearchBackend,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlgoliaSettings {
+    pub users_index: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorsSettings {
+    /// Origins allowed to call the API from a browser. `*` allows any.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    pub max_age_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpSettings {
+    /// Whether responses are gzip or brotli compressed when the client
+    /// accepts it.
+    pub compression: bool,
+    /// Responses smaller than this are sent uncompressed.
+    pub compression_min_bytes: u16,
+    /// Requests still without a response after this long get a 408.
+    pub request_timeout_secs: u64,
+    /// Requests handled at once by an instance. Any more get a 503.
+    pub concurrency_limit: usize,
+    pub body_limit_bytes: usize,
+}
+
+impl HttpSettings {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitSettings {
+    /// Sustained requests per second allowed for each API key.
+    pub requests_per_second: u32,
+    /// Requests allowed above the sustained rate in a short burst.
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheSettings {
+    pub performer_ttl_secs: u64,
+    pub search_ttl_secs: u64,
+}
+
+impl CacheSettings {
+    pub fn performer_ttl(&self) -> Duration {
+        Duration::from_secs(self.performer_ttl_secs)
+    }
+
+    pub fn search_ttl(&self) -> Duration {
+        Duration::from_secs(self.search_ttl_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Sendgrid,
+    /// Logs emails instead of sending them.
+    Log,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailSettings {
+    pub backend: MailBackend,
+    /// The sender of every email, e.g. profile claim tokens.
+    pub from: String,
+    /// Only needed by the `sendgrid` backend. Set it with
+    /// `APP_MAIL__SENDGRID_API_KEY` rather than in a file.
+    pub sendgrid_api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportBackend {
+    /// Keeps files in a local directory. Only for a single instance.
+    Local,
+    /// Keeps files in a Cloud Storage bucket every instance can read.
+    Gcs,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportSettings {
+    pub backend: ExportBackend,
+    /// Where the `local` backend writes files. Defaults to the temp directory.
+    pub directory: Option<PathBuf>,
+    /// Only needed by the `gcs` backend.
+    pub bucket: Option<String>,
+    /// How long finished exports are kept before they are pruned.
+    pub retention_secs: u64,
+}
+
+impl ExportSettings {
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.retention_secs)
+    }
+}
+
+/// How much each factor counts towards `/performer/:id/similar`. Weights are
+/// relative to each other; they needn't add up to 1.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimilaritySettings {
+    pub genres: f64,
+    pub label: f64,
+    pub category: f64,
+    pub audience: f64,
+    pub location: f64,
+}
+
+impl SimilaritySettings {
+    fn weights(&self) -> [f64; 5] {
+        [
+            self.genres,
+            self.label,
+            self.category,
+            self.audience,
+            self.location,
+        ]
+    }
+
+    pub fn total(&self) -> f64 {
+        self.weights().iter().sum()
+    }
+}
+
+impl Settings {
+    /// Catches misconfiguration before anything is started.
+    pub fn validate(&self) -> Result<()> {
+        if self.server.host.trim().is_empty() {
+            eyre::bail!("server.host must not be empty");
+        }
+        if self.firestore.project_id.trim().is_empty() {
+            eyre::bail!("firestore.project_id must not be empty");
+        }
+        if self
+            .firestore
+            .emulator_host
+            .as_ref()
+            .is_some_and(|host| host.trim().is_empty())
+        {
+            eyre::bail!("firestore.emulator_host must not be empty when set");
+        }
+        if self.algolia.users_index.trim().is_empty() {
+            eyre::bail!("algolia.users_index must not be empty");
+        }
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" {
+                reqwest::Url::parse(origin).wrap_err_with(|| {
+                    format!("cors.allowed_origins has an invalid origin `{origin}`")
+                })?;
+            }
+        }
+        if self.http.request_timeout_secs == 0 {
+            eyre::bail!("http.request_timeout_secs must be positive");
+        }
+        if self.http.concurrency_limit == 0 {
+            eyre::bail!("http.concurrency_limit must be positive");
+        }
+        if self.http.body_limit_bytes == 0 {
+            eyre::bail!("http.body_limit_bytes must be positive");
+        }
+        if self.rate_limit.requests_per_second == 0 {
+            eyre::bail!("rate_limit.requests_per_second must be positive");
+        }
+        if self.rate_limit.burst < self.rate_limit.requests_per_second {
+            eyre::bail!("rate_limit.burst must be at least rate_limit.requests_per_second");
+        }
+        let similarity = self.similarity.weights();
+        if similarity
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            eyre::bail!("similarity weights must be non-negative numbers");
+        }
+        if self.similarity.total() <= 0.0 {
+            eyre::bail!("at least one similarity weight must be positive");
+        }
+        if !self.mail.from.contains('@') {
+            eyre::bail!("mail.from must be an email address");
+        }
+        if self.mail.backend == MailBackend::Sendgrid
+            && self
+                .mail
+                .sendgrid_api_key
+                .as_ref()
+                .is_none_or(|key| key.trim().is_empty())
+        {
+            eyre::bail!("mail.sendgrid_api_key must be set for the sendgrid backend");
+        }
+        if self.exports.backend == ExportBackend::Gcs
+            && self
+                .exports
+                .bucket
+                .as_ref()
+                .is_none_or(|bucket| bucket.trim().is_empty())
+        {
+            eyre::bail!("exports.bucket must be set for the gcs backend");
+        }
+
+        Ok(())
+    }
+}
+
+/// The checked-in settings of `environment`: `base.yaml` and then its own
+/// file, without anything from the process environment.
+fn checked_in(
+    configuration_directory: &Path,
+    environment: Environment,
+) -> config::ConfigBuilder<config::builder::DefaultState> {
+    config::Config::builder()
+        .add_source(config::File::from(
+            configuration_directory.join("base.yaml"),
+        ))
+        .add_source(config::File::from(
+            configuration_directory.join(format!("{}.yaml", environment.as_str())),
+        ))
+}
+
+/// Parses and validates built settings.
+fn parse(config: config::Config) -> Result<Settings> {
+    let settings: Settings = config
+        .try_deserialize()
+        .wrap_err("Failed to parse configuration")?;
+    settings.validate().wrap_err("Invalid configuration")?;
+
+    Ok(settings)
+}
+
+pub fn get_configuration() -> Result<Settings> {
+    let configuration_directory = std::env::current_dir()
+        .wrap_err("Failed to determine the current directory")?
+        .join("configuration");
+
+    let environment: Environment = std::env::var("APP_ENVIRONMENT")
+        .unwrap_or_else(|_| "stage".into())
+        .try_into()?;
+
+    let settings = checked_in(&configuration_directory, environment)
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__")
+                .try_parsing(true)
+                .list_separator(",")
+                .with_list_parse_key("cors.allowed_origins"),
+        )
+        // Cloud Run tells us which port to listen on with `PORT`, and
+        // existing deployments set `PROJECT_ID`.
+        .set_override_option("server.port", std::env::var("PORT").ok())?
+        .set_override_option("firestore.project_id", std::env::var("PROJECT_ID").ok())?
+        .build()
+        .wrap_err("Failed to load configuration")?;
+
+    parse(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings `environment` runs with, given the `secrets` its
+    /// deployment sets in the environment.
+    fn settings(environment: Environment, secrets: &[(&str, &str)]) -> Result<Settings> {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("configuration");
+        let mut builder = checked_in(&directory, environment);
+        for (key, value) in secrets {
+            builder = builder.set_override(*key, *value)?;
+        }
+
+        parse(builder.build()?)
+    }
+
+    #[test]
+    fn checked_in_configuration_is_valid() {
+        for (environment, secrets) in [
+            (Environment::Local, &[][..]),
+            (Environment::Stage, &[]),
+            (
+                Environment::Production,
+                &[
+                    ("mail.sendgrid_api_key", "key"),
+                    ("exports.bucket", "bucket"),
+                ],
+            ),
+        ] {
+            let settings = settings(environment, secrets)
+                .unwrap_or_else(|error| panic!("{}: {error:?}", environment.as_str()));
+
+            assert_eq!("prod_users", settings.algolia.users_index);
+            assert!(settings.rate_limit.burst >= settings.rate_limit.requests_per_second);
+        }
+    }
+
+    #[test]
+    fn production_needs_its_secrets() {
+        assert!(settings(Environment::Production, &[]).is_err());
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let valid = settings(Environment::Stage, &[]).unwrap();
+
+        let mut settings = valid.clone();
+        settings.cors.allowed_origins = vec!["not an origin".into()];
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.rate_limit.burst = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.http.concurrency_limit = 0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.similarity.genres = -1.0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.mail.backend = MailBackend::Sendgrid;
+        settings.mail.sendgrid_api_key = None;
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid.clone();
+        settings.exports.backend = ExportBackend::Gcs;
+        settings.exports.bucket = None;
+        assert!(settings.validate().is_err());
+
+        let mut settings = valid;
+        settings.firestore.project_id = " ".into();
+        assert!(settings.validate().is_err());
+    }
+}
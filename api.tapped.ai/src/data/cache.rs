@@ -0,0 +1,471 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+
+use crate::{
+    configuration::CacheSettings,
+    data::{
+        database::Database,
+        search::{Search, UserSearchOptions},
+    },
+    domain::models::{
+        api_key::ApiKey,
+        booking::Booking,
+        calendar_feed::CalendarFeed,
+        claim::ProfileClaim,
+        event::Event,
+        export_job::ExportJob,
+        review::Review,
+        user::UserModel,
+        webhook::{Webhook, WebhookDelivery, WebhookEventType},
+    },
+    metrics::Metrics,
+};
+
+const MAX_CACHED_USERS: u64 = 10_000;
+const MAX_CACHED_SEARCHES: u64 = 1_000;
+
+/// Serves repeated user lookups from memory for `cache.performer_ttl_secs`.
+/// Everything else goes straight to the wrapped `Database`.
+#[derive(Clone)]
+pub struct CachedDatabase {
+    inner: Arc<dyn Database>,
+    users_by_id: Cache<String, UserModel>,
+    users_by_username: Cache<String, UserModel>,
+    metrics: Metrics,
+}
+
+impl CachedDatabase {
+    pub fn new(inner: Arc<dyn Database>, settings: &CacheSettings, metrics: Metrics) -> Self {
+        let users = || {
+            Cache::builder()
+                .max_capacity(MAX_CACHED_USERS)
+                .time_to_live(settings.performer_ttl())
+                .build()
+        };
+
+        Self {
+            inner,
+            users_by_id: users(),
+            users_by_username: users(),
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl Database for CachedDatabase {
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
+    }
+
+    async fn get_api_key(&self, api_key: &str) -> Result<ApiKey> {
+        self.inner.get_api_key(api_key).await
+    }
+
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.inner.create_api_key(api_key).await
+    }
+
+    async fn get_api_key_by_id(&self, id: &str) -> Result<ApiKey> {
+        self.inner.get_api_key_by_id(id).await
+    }
+
+    async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        self.inner.get_api_keys_by_user_id(user_id).await
+    }
+
+    async fn update_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.inner.update_api_key(api_key).await
+    }
+
+    async fn get_user_by_id(&self, id: &str) -> Result<UserModel> {
+        if let Some(user) = self.users_by_id.get(id).await {
+            self.metrics.record_cache_lookup("user", true);
+            return Ok(user);
+        }
+        self.metrics.record_cache_lookup("user", false);
+
+        let user = self.inner.get_user_by_id(id).await?;
+        self.users_by_id.insert(id.to_string(), user.clone()).await;
+
+        Ok(user)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<UserModel> {
+        if let Some(user) = self.users_by_username.get(username).await {
+            self.metrics.record_cache_lookup("user", true);
+            return Ok(user);
+        }
+        self.metrics.record_cache_lookup("user", false);
+
+        let user = self.inner.get_user_by_username(username).await?;
+        self.users_by_username
+            .insert(username.to_string(), user.clone())
+            .await;
+
+        Ok(user)
+    }
+
+    /// Not cached: the caches only hold users the soft-delete policy lets
+    /// through.
+    async fn get_user_by_id_including_deleted(&self, id: &str) -> Result<UserModel> {
+        self.inner.get_user_by_id_including_deleted(id).await
+    }
+
+    async fn get_user_by_username_including_deleted(&self, username: &str) -> Result<UserModel> {
+        self.inner
+            .get_user_by_username_including_deleted(username)
+            .await
+    }
+
+    async fn get_users_by_ids(&self, ids: &[String]) -> Result<Vec<UserModel>> {
+        self.inner.get_users_by_ids(ids).await
+    }
+
+    /// Drops the user from both caches, so the next read sees the claim.
+    async fn claim_user(&self, id: &str, claimant_id: &str) -> Result<UserModel> {
+        let user = self.inner.claim_user(id, claimant_id).await?;
+        self.users_by_id.invalidate(id).await;
+        self.users_by_username.invalidate(&user.username).await;
+
+        Ok(user)
+    }
+
+    async fn create_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.inner.create_profile_claim(claim).await
+    }
+
+    async fn get_profile_claim_by_id(&self, id: &str) -> Result<ProfileClaim> {
+        self.inner.get_profile_claim_by_id(id).await
+    }
+
+    async fn get_profile_claims_by_user_id(&self, user_id: &str) -> Result<Vec<ProfileClaim>> {
+        self.inner.get_profile_claims_by_user_id(user_id).await
+    }
+
+    async fn update_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.inner.update_profile_claim(claim).await
+    }
+
+    async fn create_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.inner.create_calendar_feed(feed).await
+    }
+
+    async fn get_calendar_feed_by_id(&self, id: &str) -> Result<CalendarFeed> {
+        self.inner.get_calendar_feed_by_id(id).await
+    }
+
+    async fn get_calendar_feed_by_token_hash(&self, token_hash: &str) -> Result<CalendarFeed> {
+        self.inner.get_calendar_feed_by_token_hash(token_hash).await
+    }
+
+    async fn get_calendar_feeds_by_performer_id(
+        &self,
+        performer_id: &str,
+    ) -> Result<Vec<CalendarFeed>> {
+        self.inner
+            .get_calendar_feeds_by_performer_id(performer_id)
+            .await
+    }
+
+    async fn update_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.inner.update_calendar_feed(feed).await
+    }
+
+    async fn create_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.inner.create_export_job(job).await
+    }
+
+    async fn get_export_job_by_id(&self, id: &str) -> Result<ExportJob> {
+        self.inner.get_export_job_by_id(id).await
+    }
+
+    async fn update_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.inner.update_export_job(job).await
+    }
+
+    async fn get_export_jobs_created_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<ExportJob>> {
+        self.inner.get_export_jobs_created_before(cutoff).await
+    }
+
+    async fn delete_export_job(&self, id: &str) -> Result<()> {
+        self.inner.delete_export_job(id).await
+    }
+
+    async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.inner.get_bookings_by_performer_id(performer_id).await
+    }
+
+    async fn get_open_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.inner
+            .get_open_bookings_by_performer_id(performer_id)
+            .await
+    }
+
+    async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>> {
+        self.inner.get_bookings_by_booker_id(booker_id).await
+    }
+
+    async fn get_bookings_by_performer_ids(
+        &self,
+        performer_ids: &[String],
+    ) -> Result<Vec<Booking>> {
+        self.inner
+            .get_bookings_by_performer_ids(performer_ids)
+            .await
+    }
+
+    async fn get_bookings_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Booking>> {
+        self.inner.get_bookings_by_booker_ids(booker_ids).await
+    }
+
+    async fn get_booking_by_id(&self, id: &str) -> Result<Booking> {
+        self.inner.get_booking_by_id(id).await
+    }
+
+    async fn create_booking(&self, booking: &Booking) -> Result<()> {
+        self.inner.create_booking(booking).await
+    }
+
+    async fn get_bookings_by_event_id(&self, event_id: &str) -> Result<Vec<Booking>> {
+        self.inner.get_bookings_by_event_id(event_id).await
+    }
+
+    async fn get_event_by_id(&self, id: &str) -> Result<Event> {
+        self.inner.get_event_by_id(id).await
+    }
+
+    async fn get_events_by_start_time(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.inner.get_events_by_start_time(from, to).await
+    }
+
+    async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>> {
+        self.inner.get_reviews_by_performer_id(performer_id).await
+    }
+
+    async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>> {
+        self.inner.get_reviews_by_booker_id(booker_id).await
+    }
+
+    async fn get_reviews_by_performer_ids(&self, performer_ids: &[String]) -> Result<Vec<Review>> {
+        self.inner.get_reviews_by_performer_ids(performer_ids).await
+    }
+
+    async fn get_reviews_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Review>> {
+        self.inner.get_reviews_by_booker_ids(booker_ids).await
+    }
+
+    async fn get_reviews_by_booking_id(&self, booking_id: &str) -> Result<Vec<Review>> {
+        self.inner.get_reviews_by_booking_id(booking_id).await
+    }
+
+    async fn create_review(&self, review: &Review) -> Result<()> {
+        self.inner.create_review(review).await
+    }
+
+    async fn create_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.inner.create_webhook(webhook).await
+    }
+
+    async fn get_webhook_by_id(&self, id: &str) -> Result<Webhook> {
+        self.inner.get_webhook_by_id(id).await
+    }
+
+    async fn get_webhooks_by_owner_id(&self, owner_id: &str) -> Result<Vec<Webhook>> {
+        self.inner.get_webhooks_by_owner_id(owner_id).await
+    }
+
+    async fn get_webhooks_by_event_type(
+        &self,
+        event_type: WebhookEventType,
+    ) -> Result<Vec<Webhook>> {
+        self.inner.get_webhooks_by_event_type(event_type).await
+    }
+
+    async fn update_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.inner.update_webhook(webhook).await
+    }
+
+    async fn delete_webhook(&self, id: &str) -> Result<()> {
+        self.inner.delete_webhook(id).await
+    }
+
+    async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        self.inner.create_webhook_delivery(delivery).await
+    }
+
+    async fn upsert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.inner.upsert_webhook_delivery(delivery).await
+    }
+
+    async fn claim_webhook_delivery(
+        &self,
+        stalled: &WebhookDelivery,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebhookDelivery>> {
+        self.inner.claim_webhook_delivery(stalled, now).await
+    }
+
+    async fn get_webhook_delivery_by_id(&self, id: &str) -> Result<WebhookDelivery> {
+        self.inner.get_webhook_delivery_by_id(id).await
+    }
+
+    async fn get_webhook_deliveries_by_webhook_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>> {
+        self.inner
+            .get_webhook_deliveries_by_webhook_id(webhook_id)
+            .await
+    }
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        self.inner.get_pending_webhook_deliveries().await
+    }
+}
+
+/// Serves repeated searches from memory for `cache.search_ttl_secs`.
+#[derive(Clone)]
+pub struct CachedSearch {
+    inner: Arc<dyn Search>,
+    results: Cache<String, Vec<UserModel>>,
+    metrics: Metrics,
+}
+
+impl CachedSearch {
+    pub fn new(inner: Arc<dyn Search>, settings: &CacheSettings, metrics: Metrics) -> Self {
+        Self {
+            inner,
+            results: Cache::builder()
+                .max_capacity(MAX_CACHED_SEARCHES)
+                .time_to_live(settings.search_ttl())
+                .build(),
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl Search for CachedSearch {
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
+    }
+
+    async fn search_users(
+        &self,
+        query: String,
+        options: UserSearchOptions,
+    ) -> Result<Vec<UserModel>> {
+        let key = format!("{query}\u{0}{options:?}");
+        if let Some(users) = self.results.get(&key).await {
+            self.metrics.record_cache_lookup("search", true);
+            return Ok(users);
+        }
+        self.metrics.record_cache_lookup("search", false);
+
+        let users = self.inner.search_users(query, options).await?;
+        self.results.insert(key, users.clone()).await;
+
+        Ok(users)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{memory::InMemoryDatabase, search::InMemorySearch};
+
+    fn settings() -> CacheSettings {
+        CacheSettings {
+            performer_ttl_secs: 60,
+            search_ttl_secs: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_user_lookups_hit_the_cache() {
+        let database = InMemoryDatabase::new();
+        database.insert_user(UserModel::fixture("performer", serde_json::json!({})));
+        let metrics = Metrics::new();
+        let cached = CachedDatabase::new(Arc::new(database.clone()), &settings(), metrics.clone());
+
+        cached.get_user_by_id("performer").await.unwrap();
+        cached.get_user_by_id("performer").await.unwrap();
+        assert!(cached.get_user_by_id("missing").await.is_err());
+
+        assert_eq!(vec!["get_user_by_id", "get_user_by_id"], database.calls());
+        let rendered = metrics.render();
+        assert!(rendered.contains(r#"cache_lookups_total{cache="user",result="hit"} 1"#));
+        assert!(rendered.contains(r#"cache_lookups_total{cache="user",result="miss"} 2"#));
+    }
+
+    #[tokio::test]
+    async fn claiming_a_user_drops_it_from_the_caches() {
+        let database = InMemoryDatabase::new();
+        database.insert_user(
+            serde_json::from_value(serde_json::json!({
+                "id": "performer",
+                "email": "",
+                "username": "performer",
+                "deleted": false,
+                "unclaimed": true,
+            }))
+            .unwrap(),
+        );
+        let cached = CachedDatabase::new(Arc::new(database.clone()), &settings(), Metrics::new());
+
+        assert!(!cached
+            .get_user_by_id("performer")
+            .await
+            .unwrap()
+            .is_verified());
+        assert!(!cached
+            .get_user_by_username("performer")
+            .await
+            .unwrap()
+            .is_verified());
+        cached.claim_user("performer", "owner").await.unwrap();
+
+        let by_id = cached.get_user_by_id("performer").await.unwrap();
+        assert!(by_id.is_verified());
+        assert_eq!(Some("owner"), by_id.claimed_by());
+        assert!(cached
+            .get_user_by_username("performer")
+            .await
+            .unwrap()
+            .is_verified());
+    }
+
+    #[tokio::test]
+    async fn searches_are_cached_per_query_and_options() {
+        let database = InMemoryDatabase::new();
+        database.insert_user(UserModel::fixture("performer", serde_json::json!({})));
+        let metrics = Metrics::new();
+        let cached = CachedSearch::new(
+            Arc::new(InMemorySearch::new(database.clone())),
+            &settings(),
+            metrics.clone(),
+        );
+
+        let search = |query: &str| cached.search_users(query.into(), UserSearchOptions::default());
+        assert_eq!(1, search("perf").await.unwrap().len());
+        database.insert_user(UserModel::fixture("performer-two", serde_json::json!({})));
+        assert_eq!(1, search("perf").await.unwrap().len());
+        assert_eq!(2, search("performer").await.unwrap().len());
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(r#"cache_lookups_total{cache="search",result="hit"} 1"#));
+        assert!(rendered.contains(r#"cache_lookups_total{cache="search",result="miss"} 2"#));
+    }
+}
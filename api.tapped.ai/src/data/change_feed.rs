@@ -0,0 +1,388 @@
+use crate::domain::models::{
+    booking::{Booking, BookingStatus, GuardedBooking},
+    review::{GuardedReview, Review},
+};
+use anyhow::Result;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use firestore::{
+    timestamp_utils::from_timestamp, FirestoreDb, FirestoreListenEvent, FirestoreListener,
+    FirestoreListenerTarget, FirestoreListenerTargetParams, FirestoreListenerTargetResumeType,
+    FirestoreMemListenStateStorage, FirestoreQueryParams, FirestoreTargetType,
+};
+use futures::{stream::BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+/// How many past changes are kept around for `Last-Event-ID` resumes.
+pub const DEFAULT_REPLAY_CAPACITY: usize = 1024;
+
+const BOOKINGS_TARGET: u32 = 1;
+const REVIEWS_TARGET: u32 = 2;
+
+/// A single change to a booking, as seen by subscribers of the feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingChange {
+    /// Used as the SSE event id. Changes read from Firestore are numbered by
+    /// the document's update time in microseconds, so every instance gives a
+    /// change the same id and a stream can resume on any instance that still
+    /// buffers it. Changes committed in the same write share their id.
+    pub id: u64,
+    pub status: BookingStatus,
+    pub timestamp: DateTime<Utc>,
+    pub booking: GuardedBooking,
+}
+
+impl BookingChange {
+    pub fn event_type(&self) -> &'static str {
+        match self.status {
+            BookingStatus::Pending => "booking.pending",
+            BookingStatus::Confirmed => "booking.confirmed",
+            BookingStatus::Canceled => "booking.canceled",
+        }
+    }
+}
+
+#[async_trait]
+pub trait ChangeFeed: Send + Sync {
+    /// Records a new version of `booking` and fans it out to live subscribers.
+    async fn publish(&self, booking: &Booking) -> Result<BookingChange>;
+
+    /// Streams every change after `last_event_id` that is still buffered,
+    /// followed by live changes. `None` only streams live changes.
+    async fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> Result<BoxStream<'static, BookingChange>>;
+
+    /// Announces a newly created review to live subscribers.
+    async fn publish_review(&self, review: &Review) -> Result<()>;
+
+    /// Streams reviews created from now on.
+    async fn subscribe_reviews(&self) -> Result<BoxStream<'static, GuardedReview>>;
+
+    /// Stops following changes, on shutdown.
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct ChangeLog {
+    last_id: u64,
+    capacity: usize,
+    buffer: VecDeque<BookingChange>,
+    sender: broadcast::Sender<BookingChange>,
+}
+
+/// A change feed that only lives in process memory. Changes are replayable
+/// for as long as they stay in the bounded buffer, which holds at least one.
+#[derive(Clone)]
+pub struct InMemoryChangeFeed {
+    log: Arc<Mutex<ChangeLog>>,
+    reviews: broadcast::Sender<GuardedReview>,
+}
+
+impl InMemoryChangeFeed {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        let (reviews, _) = broadcast::channel(capacity);
+
+        Self {
+            log: Arc::new(Mutex::new(ChangeLog {
+                last_id: 0,
+                capacity,
+                buffer: VecDeque::with_capacity(capacity),
+                sender,
+            })),
+            reviews,
+        }
+    }
+
+    /// Records a change made at `changed_at`, numbered by that time and
+    /// nothing else, so every instance reading it gives it the same id.
+    pub fn publish_at(&self, booking: &Booking, changed_at: DateTime<Utc>) -> BookingChange {
+        let id = u64::try_from(changed_at.timestamp_micros()).unwrap_or_default();
+
+        self.append(booking, Some(id), changed_at)
+    }
+
+    /// Appends the change as `id`, or the next id after the latest.
+    fn append(
+        &self,
+        booking: &Booking,
+        id: Option<u64>,
+        timestamp: DateTime<Utc>,
+    ) -> BookingChange {
+        let mut log = self.log.lock().unwrap();
+
+        let change = BookingChange {
+            id: id.unwrap_or(log.last_id + 1),
+            status: booking.status,
+            timestamp,
+            booking: booking.to_guarded(),
+        };
+        log.last_id = log.last_id.max(change.id);
+
+        if log.buffer.len() == log.capacity {
+            log.buffer.pop_front();
+        }
+        log.buffer.push_back(change.clone());
+
+        // Having no live subscribers is not an error.
+        let _ = log.sender.send(change.clone());
+
+        change
+    }
+}
+
+impl Default for InMemoryChangeFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl ChangeFeed for InMemoryChangeFeed {
+    async fn publish(&self, booking: &Booking) -> Result<BookingChange> {
+        Ok(self.append(booking, None, Utc::now()))
+    }
+
+    async fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> Result<BoxStream<'static, BookingChange>> {
+        // Subscribing and snapshotting the buffer under the same lock as
+        // `publish` guarantees no change is missed or sent twice.
+        let log = self.log.lock().unwrap();
+        let receiver = log.sender.subscribe();
+        let replay: Vec<BookingChange> = match last_event_id {
+            Some(last_event_id) => log
+                .buffer
+                .iter()
+                .filter(|change| change.id > last_event_id)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        drop(log);
+
+        let live = BroadcastStream::new(receiver).filter_map(|change| async move {
+            match change {
+                Ok(change) => Some(change),
+                Err(error) => {
+                    tracing::warn!("booking change subscriber lagged: {error}");
+                    None
+                }
+            }
+        });
+
+        Ok(futures::stream::iter(replay).chain(live).boxed())
+    }
+
+    async fn publish_review(&self, review: &Review) -> Result<()> {
+        if !review.is_published() {
+            return Ok(());
+        }
+
+        // Having no live subscribers is not an error.
+        let _ = self.reviews.send(review.to_guarded());
+
+        Ok(())
+    }
+
+    async fn subscribe_reviews(&self) -> Result<BoxStream<'static, GuardedReview>> {
+        let reviews =
+            BroadcastStream::new(self.reviews.subscribe()).filter_map(|review| async move {
+                match review {
+                    Ok(review) => Some(review),
+                    Err(error) => {
+                        tracing::warn!("review subscriber lagged: {error}");
+                        None
+                    }
+                }
+            });
+
+        Ok(reviews.boxed())
+    }
+}
+
+/// A change feed fed by a Firestore listener on the `bookings` and `reviews`
+/// collections.
+#[derive(Clone)]
+pub struct FirestoreChangeFeed {
+    feed: InMemoryChangeFeed,
+    listener:
+        Arc<tokio::sync::Mutex<FirestoreListener<FirestoreDb, FirestoreMemListenStateStorage>>>,
+}
+
+impl FirestoreChangeFeed {
+    pub async fn start(db: &FirestoreDb, capacity: usize) -> Result<Self> {
+        let feed = InMemoryChangeFeed::new(capacity);
+
+        let mut listener = db
+            .create_listener(FirestoreMemListenStateStorage::new())
+            .await?;
+
+        // Only changes made after startup are of interest, not the initial
+        // snapshot of every document in the collections.
+        let started_at = Utc::now();
+        for (target, collection) in [(BOOKINGS_TARGET, "bookings"), (REVIEWS_TARGET, "reviews")] {
+            listener.add_target(
+                FirestoreListenerTargetParams::new(
+                    FirestoreListenerTarget::new(target),
+                    FirestoreTargetType::Query(FirestoreQueryParams::new(collection.into())),
+                    HashMap::new(),
+                )
+                .with_resume_type(FirestoreListenerTargetResumeType::ReadTime(started_at)),
+            )?;
+        }
+
+        // New reviews wait for moderation, and are announced when they are
+        // approved instead. Reviews still pending when the process restarts
+        // are not announced.
+        let awaiting_moderation = Arc::new(Mutex::new(HashSet::<String>::new()));
+
+        let publisher = feed.clone();
+        listener
+            .start(move |event| {
+                let publisher = publisher.clone();
+                let awaiting_moderation = awaiting_moderation.clone();
+                async move {
+                    let FirestoreListenEvent::DocumentChange(change) = event else {
+                        return Ok(());
+                    };
+                    let Some(doc) = change.document else {
+                        return Ok(());
+                    };
+
+                    if change.target_ids.contains(&(BOOKINGS_TARGET as i32)) {
+                        let booking = FirestoreDb::deserialize_doc_to::<Booking>(&doc)?;
+                        let changed_at = match doc.update_time {
+                            Some(update_time) => from_timestamp(update_time)?,
+                            None => Utc::now(),
+                        };
+                        publisher.publish_at(&booking, changed_at);
+                    } else if change.target_ids.contains(&(REVIEWS_TARGET as i32)) {
+                        let review = FirestoreDb::deserialize_doc_to::<Review>(&doc)?;
+                        let created = doc.create_time == doc.update_time;
+                        // Other edits to existing reviews are not announced.
+                        let announce = {
+                            let mut awaiting = awaiting_moderation.lock().unwrap();
+                            if review.is_published() {
+                                awaiting.remove(&review.id) || created
+                            } else {
+                                if created {
+                                    awaiting.insert(review.id.clone());
+                                }
+                                false
+                            }
+                        };
+
+                        if announce {
+                            publisher.publish_review(&review).await?;
+                        }
+                    }
+
+                    Ok(())
+                }
+            })
+            .await?;
+
+        Ok(Self {
+            feed,
+            listener: Arc::new(tokio::sync::Mutex::new(listener)),
+        })
+    }
+}
+
+#[async_trait]
+impl ChangeFeed for FirestoreChangeFeed {
+    async fn publish(&self, booking: &Booking) -> Result<BookingChange> {
+        self.feed.publish(booking).await
+    }
+
+    async fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> Result<BoxStream<'static, BookingChange>> {
+        self.feed.subscribe(last_event_id).await
+    }
+
+    async fn publish_review(&self, review: &Review) -> Result<()> {
+        self.feed.publish_review(review).await
+    }
+
+    async fn subscribe_reviews(&self) -> Result<BoxStream<'static, GuardedReview>> {
+        self.feed.subscribe_reviews().await
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        self.listener.lock().await.shutdown().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booking(id: &str) -> Booking {
+        Booking {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn a_zero_capacity_still_buffers_the_latest_change() {
+        let feed = InMemoryChangeFeed::new(0);
+
+        for id in ["booking-1", "booking-2", "booking-3"] {
+            feed.publish(&booking(id)).await.unwrap();
+        }
+
+        let log = feed.log.lock().unwrap();
+        assert_eq!(1, log.buffer.len());
+        assert_eq!(3, log.buffer[0].id);
+    }
+
+    #[test]
+    fn changes_are_numbered_by_when_they_were_made() {
+        let feed = InMemoryChangeFeed::default();
+        let changed_at = DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap();
+
+        let first = feed.publish_at(&booking("booking-1"), changed_at);
+        let same_time = feed.publish_at(&booking("booking-2"), changed_at);
+        let earlier = feed.publish_at(
+            &booking("booking-3"),
+            changed_at - chrono::Duration::seconds(1),
+        );
+
+        assert_eq!(1_700_000_000_000_000, first.id);
+        assert_eq!(first.id, same_time.id);
+        assert_eq!(first.id - 1_000_000, earlier.id);
+    }
+
+    #[tokio::test]
+    async fn published_changes_are_numbered_after_the_latest() {
+        let feed = InMemoryChangeFeed::default();
+        let changed_at = DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap();
+
+        feed.publish_at(&booking("booking-1"), changed_at);
+        feed.publish_at(
+            &booking("booking-2"),
+            changed_at - chrono::Duration::seconds(1),
+        );
+        let published = feed.publish(&booking("booking-3")).await.unwrap();
+
+        assert_eq!(1_700_000_000_000_001, published.id);
+    }
+}
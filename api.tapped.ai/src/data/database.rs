@@ -1,19 +1,197 @@
-use crate::domain::models::{api_key::ApiKey, booking::Booking, review::Review, user::UserModel};
+use crate::{
+    data::soft_delete,
+    domain::models::{
+        api_key::ApiKey,
+        booking::Booking,
+        calendar_feed::CalendarFeed,
+        claim::ProfileClaim,
+        event::Event,
+        export_job::ExportJob,
+        review::Review,
+        user::UserModel,
+        webhook::{Webhook, WebhookDelivery, WebhookEventType},
+    },
+};
 use anyhow::Result;
 use axum::async_trait;
-use firestore::{struct_path::path, FirestoreDb, FirestoreResult};
-use futures::{stream::BoxStream, TryStreamExt};
+use chrono::{DateTime, Utc};
+use firestore::{
+    errors::FirestoreError, struct_path::path, FirestoreConsistencySelector, FirestoreDb,
+    FirestoreQueryDirection, FirestoreResult, FirestoreTimestamp,
+};
+use futures::{future, stream::BoxStream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::fmt;
 use tracing::instrument;
 
+/// The booking fields holding the performer, who the booking was requested
+/// of, and the booker, who requested it.
+const BOOKING_PERFORMER_FIELD: &str = "requesteeId";
+const BOOKING_BOOKER_FIELD: &str = "requesterId";
+/// Firestore takes at most this many values in an `in` filter.
+const MAX_IN_VALUES: usize = 30;
+
+/// Returned by `claim_user` when the profile was claimed by someone else
+/// first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAlreadyClaimed {
+    pub id: String,
+}
+
+impl fmt::Display for UserAlreadyClaimed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user {} has already been claimed", self.id)
+    }
+}
+
+impl std::error::Error for UserAlreadyClaimed {}
+
+/// Returned by single user reads when there is no such user, so callers can
+/// tell a missing user from a read that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserNotFound;
+
+impl fmt::Display for UserNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user not found")
+    }
+}
+
+impl std::error::Error for UserNotFound {}
+
+/// Whether a user read failed because there is no such user.
+pub fn is_user_not_found(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<UserNotFound>().is_some()
+}
+
+/// Claims the user if it's still visible and unclaimed. Every `claim_user`
+/// applies it to the freshly read user, in the same transaction as the write.
+pub fn claim(user: UserModel, claimant_id: &str) -> Result<UserModel> {
+    let mut user = soft_delete::visible(user)?;
+    if !user.is_unclaimed() {
+        return Err(UserAlreadyClaimed { id: user.id }.into());
+    }
+    user.claim(claimant_id);
+
+    Ok(user)
+}
+
+/// Whether a delivery is still as it was when it was found stalled. Every
+/// `claim_webhook_delivery` checks it in the same transaction as the write.
+pub fn is_unchanged(delivery: &WebhookDelivery, stalled: &WebhookDelivery) -> bool {
+    delivery.status == stalled.status
+        && delivery.attempts == stalled.attempts
+        && delivery.next_attempt_at == stalled.next_attempt_at
+}
+
 #[async_trait]
 pub trait Database: Send + Sync {
-    async fn get_user_from_api_key(&self, api_key: &str) -> Result<String>;
+    /// Checks that the database can be reached.
+    async fn ping(&self) -> Result<()>;
+    async fn get_api_key(&self, api_key: &str) -> Result<ApiKey>;
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<()>;
+    async fn get_api_key_by_id(&self, id: &str) -> Result<ApiKey>;
+    async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>>;
+    async fn update_api_key(&self, api_key: &ApiKey) -> Result<()>;
+    /// Fails with `UserDeleted` for soft-deleted users.
     async fn get_user_by_id(&self, id: &str) -> Result<UserModel>;
+    /// Fails with `UserDeleted` for soft-deleted users.
     async fn get_user_by_username(&self, username: &str) -> Result<UserModel>;
+    /// Like `get_user_by_id`, but returns soft-deleted users too. Only for
+    /// admins.
+    async fn get_user_by_id_including_deleted(&self, id: &str) -> Result<UserModel>;
+    /// Like `get_user_by_username`, but returns soft-deleted users too. Only
+    /// for admins.
+    async fn get_user_by_username_including_deleted(&self, username: &str) -> Result<UserModel>;
+    /// Looks up many users in one round trip. Missing and soft-deleted ids
+    /// are skipped.
+    async fn get_users_by_ids(&self, ids: &[String]) -> Result<Vec<UserModel>>;
+    /// Links the profile to `claimant_id` and marks it claimed and
+    /// verified. Returns the updated user. The unclaimed check and the write
+    /// are atomic: fails with `UserAlreadyClaimed` if someone else won, and
+    /// with `UserDeleted` for soft-deleted users.
+    async fn claim_user(&self, id: &str, claimant_id: &str) -> Result<UserModel>;
+    async fn create_profile_claim(&self, claim: &ProfileClaim) -> Result<()>;
+    async fn get_profile_claim_by_id(&self, id: &str) -> Result<ProfileClaim>;
+    async fn get_profile_claims_by_user_id(&self, user_id: &str) -> Result<Vec<ProfileClaim>>;
+    async fn update_profile_claim(&self, claim: &ProfileClaim) -> Result<()>;
+    async fn create_calendar_feed(&self, feed: &CalendarFeed) -> Result<()>;
+    async fn get_calendar_feed_by_id(&self, id: &str) -> Result<CalendarFeed>;
+    async fn get_calendar_feed_by_token_hash(&self, token_hash: &str) -> Result<CalendarFeed>;
+    async fn get_calendar_feeds_by_performer_id(
+        &self,
+        performer_id: &str,
+    ) -> Result<Vec<CalendarFeed>>;
+    async fn update_calendar_feed(&self, feed: &CalendarFeed) -> Result<()>;
+    async fn create_export_job(&self, job: &ExportJob) -> Result<()>;
+    async fn get_export_job_by_id(&self, id: &str) -> Result<ExportJob>;
+    async fn update_export_job(&self, job: &ExportJob) -> Result<()>;
+    /// Jobs started before `cutoff`, whatever their status.
+    async fn get_export_jobs_created_before(&self, cutoff: DateTime<Utc>)
+        -> Result<Vec<ExportJob>>;
+    async fn delete_export_job(&self, id: &str) -> Result<()>;
     async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>>;
+    /// Confirmed and pending bookings of the performer, i.e. the ones that
+    /// take up their time.
+    async fn get_open_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>>;
     async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>>;
+    /// Like `get_bookings_by_performer_id`, for many performers at once.
+    async fn get_bookings_by_performer_ids(&self, performer_ids: &[String])
+        -> Result<Vec<Booking>>;
+    /// Like `get_bookings_by_booker_id`, for many bookers at once.
+    async fn get_bookings_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Booking>>;
+    async fn get_booking_by_id(&self, id: &str) -> Result<Booking>;
+    async fn create_booking(&self, booking: &Booking) -> Result<()>;
+    /// Confirmed bookings whose `reference_event_id` is the event.
+    async fn get_bookings_by_event_id(&self, event_id: &str) -> Result<Vec<Booking>>;
+    async fn get_event_by_id(&self, id: &str) -> Result<Event>;
+    /// Events starting from `from` up to, but not including, `to`, soonest
+    /// first.
+    async fn get_events_by_start_time(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>>;
+    /// Published reviews of the performer.
     async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>>;
+    /// Published reviews of the booker.
     async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>>;
+    /// Like `get_reviews_by_performer_id`, for many performers at once.
+    async fn get_reviews_by_performer_ids(&self, performer_ids: &[String]) -> Result<Vec<Review>>;
+    /// Like `get_reviews_by_booker_id`, for many bookers at once.
+    async fn get_reviews_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Review>>;
+    /// Every review of the booking, whatever its moderation status.
+    async fn get_reviews_by_booking_id(&self, booking_id: &str) -> Result<Vec<Review>>;
+    async fn create_review(&self, review: &Review) -> Result<()>;
+    async fn create_webhook(&self, webhook: &Webhook) -> Result<()>;
+    async fn get_webhook_by_id(&self, id: &str) -> Result<Webhook>;
+    async fn get_webhooks_by_owner_id(&self, owner_id: &str) -> Result<Vec<Webhook>>;
+    async fn get_webhooks_by_event_type(
+        &self,
+        event_type: WebhookEventType,
+    ) -> Result<Vec<Webhook>>;
+    async fn update_webhook(&self, webhook: &Webhook) -> Result<()>;
+    async fn delete_webhook(&self, id: &str) -> Result<()>;
+    /// Records a new delivery unless one with its id already exists, e.g.
+    /// made by another instance for the same event. Returns whether it was
+    /// recorded.
+    async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<bool>;
+    async fn upsert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()>;
+    /// Takes over a stalled delivery by setting its `next_attempt_at` to
+    /// `now`, which leases it for another `max_delay`. Returns the claimed
+    /// delivery, or `None` if it changed since `stalled` was read, e.g.
+    /// because another instance claimed it first.
+    async fn claim_webhook_delivery(
+        &self,
+        stalled: &WebhookDelivery,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebhookDelivery>>;
+    async fn get_webhook_delivery_by_id(&self, id: &str) -> Result<WebhookDelivery>;
+    async fn get_webhook_deliveries_by_webhook_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>>;
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>>;
 }
 
 #[derive(Debug, Clone)]
@@ -25,15 +203,119 @@ impl Firestore {
     pub fn new(db: FirestoreDb) -> Self {
         Self { db }
     }
+
+    /// Moves a key stored under the key itself to a document under its
+    /// hash, giving it an id if it had none.
+    async fn upgrade_api_key(&self, legacy: ApiKey) -> Result<ApiKey> {
+        let api_key = legacy.clone().hashed();
+
+        let _: ApiKey = self
+            .db
+            .fluent()
+            .update()
+            .in_col("apiKeys")
+            .document_id(&api_key.key_hash)
+            .object(&api_key)
+            .execute()
+            .await?;
+        self.db
+            .fluent()
+            .delete()
+            .from("apiKeys")
+            .document_id(&legacy.key)
+            .execute()
+            .await?;
+        tracing::info!("moved legacy API key '{}' under its hash", api_key.id);
+
+        Ok(ApiKey {
+            key: String::new(),
+            ..api_key
+        })
+    }
+
+    /// Upgrades the legacy keys among `api_keys`, so every key can be
+    /// managed by its id.
+    async fn upgrade_api_keys(&self, api_keys: Vec<ApiKey>) -> Result<Vec<ApiKey>> {
+        let mut upgraded = Vec::with_capacity(api_keys.len());
+        for api_key in api_keys {
+            upgraded.push(if api_key.is_legacy() && !api_key.key.is_empty() {
+                self.upgrade_api_key(api_key).await?
+            } else {
+                api_key
+            });
+        }
+
+        Ok(upgraded)
+    }
+
+    /// Documents whose `field` is one of `values` and whose `other` field
+    /// equals its value, in as few queries as Firestore allows.
+    async fn select_in<T>(
+        &self,
+        collection: &str,
+        field: &str,
+        values: &[String],
+        (other, equals): (&str, &str),
+    ) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let chunks = future::try_join_all(values.chunks(MAX_IN_VALUES).map(|chunk| async move {
+            let object_stream: BoxStream<FirestoreResult<T>> = self
+                .db
+                .fluent()
+                .select()
+                .from(collection)
+                .filter(|q| q.for_all([q.field(field).is_in(chunk), q.field(other).eq(equals)]))
+                .obj()
+                .stream_query_with_errors()
+                .await?;
+
+            object_stream.try_collect::<Vec<T>>().await
+        }))
+        .await?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
 }
 
 #[async_trait]
 impl Database for Firestore {
     #[instrument]
-    async fn get_user_from_api_key(&self, api_key: &str) -> Result<String> {
-        tracing::info!("getting user from Firestore by API key: '{}'", api_key);
+    async fn ping(&self) -> Result<()> {
+        // A point read of a document that doesn't exist is the cheapest
+        // round trip Firestore offers.
+        let _: Option<ApiKey> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("apiKeys")
+            .obj()
+            .one("healthcheck")
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(api_key))]
+    async fn get_api_key(&self, api_key: &str) -> Result<ApiKey> {
+        tracing::info!("getting API key from Firestore");
 
         let doc: Option<ApiKey> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("apiKeys")
+            .obj()
+            .one(&ApiKey::hash(api_key))
+            .await?;
+        if let Some(doc) = doc {
+            tracing::info!("API key found: '{}'", doc.id);
+            return Ok(doc);
+        }
+
+        // Keys stored before they were hashed live under the key itself.
+        let legacy: Option<ApiKey> = self
             .db
             .fluent()
             .select()
@@ -42,14 +324,107 @@ impl Database for Firestore {
             .one(api_key)
             .await?;
 
-        match doc {
-            Some(api_key) => Ok(api_key.user_id),
+        match legacy {
+            Some(legacy) => {
+                self.upgrade_api_key(ApiKey {
+                    key: api_key.to_string(),
+                    ..legacy
+                })
+                .await
+            }
+            None => Err(anyhow::anyhow!("api key not found")),
+        }
+    }
+
+    #[instrument(skip(api_key))]
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        tracing::info!("creating API key in Firestore: '{}'", api_key.id);
+
+        let _: ApiKey = self
+            .db
+            .fluent()
+            .insert()
+            .into("apiKeys")
+            .document_id(&api_key.key_hash)
+            .object(api_key)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_api_key_by_id(&self, id: &str) -> Result<ApiKey> {
+        tracing::info!("getting API key by id from Firestore: '{}'", id);
+
+        let object_stream: BoxStream<FirestoreResult<ApiKey>> = self
+            .db
+            .fluent()
+            .select()
+            .from("apiKeys")
+            .filter(|q| q.field(path!(ApiKey::id)).eq(id))
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<ApiKey> = object_stream.try_collect().await?;
+
+        match self.upgrade_api_keys(as_vec).await?.into_iter().nth(0) {
             None => Err(anyhow::anyhow!("api key not found")),
+            Some(api_key) => Ok(api_key),
         }
     }
 
     #[instrument]
+    async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        tracing::info!("getting API keys by user id from Firestore: '{}'", user_id);
+
+        let object_stream: BoxStream<FirestoreResult<ApiKey>> = self
+            .db
+            .fluent()
+            .select()
+            .from("apiKeys")
+            .filter(|q| q.field(path!(ApiKey::user_id)).eq(user_id))
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<ApiKey> = object_stream.try_collect().await?;
+        tracing::info!("API keys found: {:?}", as_vec.len());
+
+        self.upgrade_api_keys(as_vec).await
+    }
+
+    #[instrument(skip(api_key))]
+    async fn update_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        tracing::info!("updating API key in Firestore: '{}'", api_key.id);
+
+        let _: ApiKey = self
+            .db
+            .fluent()
+            .update()
+            .in_col("apiKeys")
+            .document_id(&api_key.key_hash)
+            .object(api_key)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
     async fn get_user_by_id(&self, id: &str) -> Result<UserModel> {
+        soft_delete::visible(self.get_user_by_id_including_deleted(id).await?)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<UserModel> {
+        soft_delete::visible(
+            self.get_user_by_username_including_deleted(username)
+                .await?,
+        )
+    }
+
+    #[instrument]
+    async fn get_user_by_id_including_deleted(&self, id: &str) -> Result<UserModel> {
         tracing::info!("getting user by id from Firestore: {}", id);
 
         let doc: Option<UserModel> = self
@@ -64,12 +439,12 @@ impl Database for Firestore {
         tracing::info!("user found: {:?}", doc);
         match doc {
             Some(user) => Ok(user),
-            None => Err(anyhow::anyhow!("user not found")),
+            None => Err(UserNotFound.into()),
         }
     }
 
     #[instrument]
-    async fn get_user_by_username(&self, username: &str) -> Result<UserModel> {
+    async fn get_user_by_username_including_deleted(&self, username: &str) -> Result<UserModel> {
         tracing::info!("getting user by username from Firestore: '{}'", username);
 
         let object_stream: BoxStream<FirestoreResult<UserModel>> = self
@@ -86,128 +461,1023 @@ impl Database for Firestore {
         tracing::info!("users found: {:?}", as_vec.len());
 
         match as_vec.into_iter().nth(0) {
-            None => Err(anyhow::anyhow!("user not found")),
+            None => Err(UserNotFound.into()),
             Some(user) => Ok(user),
         }
     }
 
     #[instrument]
-    async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+    async fn get_users_by_ids(&self, ids: &[String]) -> Result<Vec<UserModel>> {
+        tracing::info!("getting {} users by id from Firestore", ids.len());
+
+        let object_stream: BoxStream<(String, Option<UserModel>)> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("users")
+            .obj()
+            .batch(ids)
+            .await?;
+
+        let as_vec: Vec<UserModel> = object_stream
+            .filter_map(|(_, user)| futures::future::ready(user))
+            .collect()
+            .await;
+        tracing::info!("users found: {:?}", as_vec.len());
+
+        Ok(soft_delete::without_deleted(as_vec))
+    }
+
+    #[instrument]
+    async fn claim_user(&self, id: &str, claimant_id: &str) -> Result<UserModel> {
+        tracing::info!("claiming user in Firestore: '{}' for '{}'", id, claimant_id);
+
+        // Reads in the transaction lock the user until it commits, so two
+        // verified claims can't both see it unclaimed.
+        let mut transaction = self.db.begin_transaction().await?;
+        let db =
+            self.db
+                .clone_with_consistency_selector(FirestoreConsistencySelector::Transaction(
+                    transaction.transaction_id().clone(),
+                ));
+        let doc: Option<UserModel> = db.fluent().select().by_id_in("users").obj().one(id).await?;
+
+        let claimed = doc
+            .ok_or_else(|| UserNotFound.into())
+            .and_then(|user| claim(user, claimant_id));
+        let user = match claimed {
+            Ok(user) => user,
+            Err(error) => {
+                transaction.rollback().await?;
+                return Err(error);
+            }
+        };
+
+        self.db
+            .fluent()
+            .update()
+            .fields(["unclaimed", "verified", "claimedBy"])
+            .in_col("users")
+            .document_id(id)
+            .object(&user)
+            .add_to_transaction(&mut transaction)?;
+        transaction.commit().await?;
+
+        Ok(user)
+    }
+
+    #[instrument(skip(claim))]
+    async fn create_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        tracing::info!("creating profile claim in Firestore: '{}'", claim.id);
+
+        let _: ProfileClaim = self
+            .db
+            .fluent()
+            .insert()
+            .into("profileClaims")
+            .document_id(&claim.id)
+            .object(claim)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_profile_claim_by_id(&self, id: &str) -> Result<ProfileClaim> {
+        tracing::info!("getting profile claim by id from Firestore: '{}'", id);
+
+        let doc: Option<ProfileClaim> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("profileClaims")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(claim) => Ok(claim),
+            None => Err(anyhow::anyhow!("profile claim not found")),
+        }
+    }
+
+    #[instrument]
+    async fn get_profile_claims_by_user_id(&self, user_id: &str) -> Result<Vec<ProfileClaim>> {
         tracing::info!(
-            "getting bookings by performer id from Firestore: '{}'",
-            performer_id
+            "getting profile claims by user id from Firestore: '{}'",
+            user_id
         );
 
-        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+        let object_stream: BoxStream<FirestoreResult<ProfileClaim>> = self
             .db
             .fluent()
             .select()
-            .from("bookings")
-            .filter(|q| {
-                q.for_all([
-                    // q.field(path!(Booking::requestee_id)).eq(performer_id),
-                    q.field("requesterId").eq(performer_id),
-                    // q.field(path!(Booking::status)).eq(BookingStatus::Confirmed),
-                    q.field("status").eq("confirmed"),
-                ])
-            })
+            .from("profileClaims")
+            .filter(|q| q.field(path!(ProfileClaim::user_id)).eq(user_id))
             .obj()
             .stream_query_with_errors()
             .await?;
 
-        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
-        tracing::info!("bookings found: {:?}", as_vec.len());
+        let as_vec: Vec<ProfileClaim> = object_stream.try_collect().await?;
+        tracing::info!("profile claims found: {:?}", as_vec.len());
 
         Ok(as_vec)
     }
 
+    #[instrument(skip(claim))]
+    async fn update_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        tracing::info!("updating profile claim in Firestore: '{}'", claim.id);
+
+        let _: ProfileClaim = self
+            .db
+            .fluent()
+            .update()
+            .in_col("profileClaims")
+            .document_id(&claim.id)
+            .object(claim)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(feed))]
+    async fn create_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        tracing::info!("creating calendar feed in Firestore: '{}'", feed.id);
+
+        let _: CalendarFeed = self
+            .db
+            .fluent()
+            .insert()
+            .into("calendarFeeds")
+            .document_id(&feed.id)
+            .object(feed)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
     #[instrument]
-    async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>> {
-        tracing::info!(
-            "getting bookings by booker id from Firestore: '{}'",
-            booker_id
-        );
+    async fn get_calendar_feed_by_id(&self, id: &str) -> Result<CalendarFeed> {
+        tracing::info!("getting calendar feed by id from Firestore: '{}'", id);
 
-        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+        let doc: Option<CalendarFeed> = self
             .db
             .fluent()
             .select()
-            .from("bookings")
-            .filter(|q| {
-                q.for_all([
-                    // q.field(path!(Booking::requester_id)).eq(performer_id),
-                    q.field("requesterId").eq(booker_id),
-                    // q.field(path!(Booking::status)).eq(BookingStatus::Confirmed),
-                    q.field("status").eq("confirmed"),
-                ])
-            })
+            .by_id_in("calendarFeeds")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(feed) => Ok(feed),
+            None => Err(anyhow::anyhow!("calendar feed not found")),
+        }
+    }
+
+    #[instrument(skip(token_hash))]
+    async fn get_calendar_feed_by_token_hash(&self, token_hash: &str) -> Result<CalendarFeed> {
+        tracing::info!("getting calendar feed by token from Firestore");
+
+        let object_stream: BoxStream<FirestoreResult<CalendarFeed>> = self
+            .db
+            .fluent()
+            .select()
+            .from("calendarFeeds")
+            .filter(|q| q.field(path!(CalendarFeed::token_hash)).eq(token_hash))
+            .limit(1)
             .obj()
             .stream_query_with_errors()
             .await?;
 
-        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
-        tracing::info!("bookings found: {:?}", as_vec.len());
+        let as_vec: Vec<CalendarFeed> = object_stream.try_collect().await?;
 
-        Ok(as_vec)
+        as_vec
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("calendar feed not found"))
     }
 
     #[instrument]
-    async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>> {
+    async fn get_calendar_feeds_by_performer_id(
+        &self,
+        performer_id: &str,
+    ) -> Result<Vec<CalendarFeed>> {
         tracing::info!(
-            "getting reviews by performer id from Firestore: '{}'",
+            "getting calendar feeds by performer id from Firestore: '{}'",
             performer_id
         );
 
-        let object_stream: BoxStream<FirestoreResult<Review>> = self
+        let object_stream: BoxStream<FirestoreResult<CalendarFeed>> = self
             .db
             .fluent()
             .select()
-            .from("reviews")
-            .filter(|q| {
-                q.for_all([
-                    // q.field(path!(Review::performer_id)).eq(performer_id),
-                    q.field("performerId").eq(performer_id),
-                    // q.field(path!(Review::review_type)).eq(ReviewType::Performer),
-                    q.field("type").eq("performer"),
-                ])
-            })
+            .from("calendarFeeds")
+            .filter(|q| q.field(path!(CalendarFeed::performer_id)).eq(performer_id))
             .obj()
             .stream_query_with_errors()
             .await?;
 
-        let as_vec: Vec<Review> = object_stream.try_collect().await?;
-        tracing::info!("reviews found: {:?}", as_vec.len());
+        let as_vec: Vec<CalendarFeed> = object_stream.try_collect().await?;
+        tracing::info!("calendar feeds found: {:?}", as_vec.len());
 
         Ok(as_vec)
     }
 
+    #[instrument(skip(feed))]
+    async fn update_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        tracing::info!("updating calendar feed in Firestore: '{}'", feed.id);
+
+        let _: CalendarFeed = self
+            .db
+            .fluent()
+            .update()
+            .in_col("calendarFeeds")
+            .document_id(&feed.id)
+            .object(feed)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(job))]
+    async fn create_export_job(&self, job: &ExportJob) -> Result<()> {
+        tracing::info!("creating export job in Firestore: '{}'", job.id);
+
+        let _: ExportJob = self
+            .db
+            .fluent()
+            .insert()
+            .into("exportJobs")
+            .document_id(&job.id)
+            .object(job)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
     #[instrument]
-    async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>> {
-        tracing::info!(
-            "getting reviews by booker id from Firestore: '{}'",
-            booker_id
-        );
+    async fn get_export_job_by_id(&self, id: &str) -> Result<ExportJob> {
+        tracing::info!("getting export job by id from Firestore: '{}'", id);
 
-        let object_stream: BoxStream<FirestoreResult<Review>> = self
+        let doc: Option<ExportJob> = self
             .db
             .fluent()
             .select()
-            .from("reviews")
-            .filter(|q| {
-                q.for_all([
-                    // q.field(path!(Review::booker_id)).eq(booker_id),
-                    q.field("bookerId").eq(booker_id),
-                    // q.field(path!(Review::review_type)).eq(ReviewType::Booker),
-                    q.field("type").eq("booker"),
-                ])
-            })
+            .by_id_in("exportJobs")
             .obj()
-            .stream_query_with_errors()
+            .one(id)
             .await?;
 
-        let as_vec: Vec<Review> = object_stream.try_collect().await?;
-        tracing::info!("reviews found: {:?}", as_vec.len());
+        match doc {
+            Some(job) => Ok(job),
+            None => Err(anyhow::anyhow!("export job not found")),
+        }
+    }
 
-        Ok(as_vec)
+    #[instrument(skip(job))]
+    async fn update_export_job(&self, job: &ExportJob) -> Result<()> {
+        tracing::info!("updating export job in Firestore: '{}'", job.id);
+
+        let _: ExportJob = self
+            .db
+            .fluent()
+            .update()
+            .in_col("exportJobs")
+            .document_id(&job.id)
+            .object(job)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_export_jobs_created_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<ExportJob>> {
+        tracing::info!(
+            "getting export jobs created before {} from Firestore",
+            cutoff
+        );
+
+        let object_stream: BoxStream<FirestoreResult<ExportJob>> = self
+            .db
+            .fluent()
+            .select()
+            .from("exportJobs")
+            .filter(|q| {
+                q.field(path!(ExportJob::timestamp))
+                    .less_than(FirestoreTimestamp(cutoff))
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<ExportJob> = object_stream.try_collect().await?;
+        tracing::info!("old export jobs found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn delete_export_job(&self, id: &str) -> Result<()> {
+        tracing::info!("deleting export job from Firestore: '{}'", id);
+
+        self.db
+            .fluent()
+            .delete()
+            .from("exportJobs")
+            .document_id(id)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting bookings by performer id from Firestore: '{}'",
+            performer_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+            .db
+            .fluent()
+            .select()
+            .from("bookings")
+            .filter(|q| {
+                q.for_all([
+                    // q.field(path!(Booking::requestee_id)).eq(performer_id),
+                    q.field(BOOKING_PERFORMER_FIELD).eq(performer_id),
+                    // q.field(path!(Booking::status)).eq(BookingStatus::Confirmed),
+                    q.field("status").eq("confirmed"),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_open_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting open bookings by performer id from Firestore: '{}'",
+            performer_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+            .db
+            .fluent()
+            .select()
+            .from("bookings")
+            .filter(|q| {
+                q.for_all([
+                    q.field(BOOKING_PERFORMER_FIELD).eq(performer_id),
+                    q.field("status").is_in(["confirmed", "pending"]),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting bookings by booker id from Firestore: '{}'",
+            booker_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+            .db
+            .fluent()
+            .select()
+            .from("bookings")
+            .filter(|q| {
+                q.for_all([
+                    // q.field(path!(Booking::requester_id)).eq(performer_id),
+                    q.field(BOOKING_BOOKER_FIELD).eq(booker_id),
+                    // q.field(path!(Booking::status)).eq(BookingStatus::Confirmed),
+                    q.field("status").eq("confirmed"),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_bookings_by_performer_ids(
+        &self,
+        performer_ids: &[String],
+    ) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting bookings of {} performers from Firestore",
+            performer_ids.len()
+        );
+
+        let as_vec: Vec<Booking> = self
+            .select_in(
+                "bookings",
+                BOOKING_PERFORMER_FIELD,
+                performer_ids,
+                ("status", "confirmed"),
+            )
+            .await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_bookings_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting bookings of {} bookers from Firestore",
+            booker_ids.len()
+        );
+
+        let as_vec: Vec<Booking> = self
+            .select_in(
+                "bookings",
+                BOOKING_BOOKER_FIELD,
+                booker_ids,
+                ("status", "confirmed"),
+            )
+            .await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_booking_by_id(&self, id: &str) -> Result<Booking> {
+        tracing::info!("getting booking by id from Firestore: '{}'", id);
+
+        let doc: Option<Booking> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("bookings")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(booking) => Ok(booking),
+            None => Err(anyhow::anyhow!("booking not found")),
+        }
+    }
+
+    #[instrument(skip(booking))]
+    async fn create_booking(&self, booking: &Booking) -> Result<()> {
+        tracing::info!("creating booking in Firestore: '{}'", booking.id);
+
+        let _: Booking = self
+            .db
+            .fluent()
+            .insert()
+            .into("bookings")
+            .document_id(&booking.id)
+            .object(booking)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_bookings_by_event_id(&self, event_id: &str) -> Result<Vec<Booking>> {
+        tracing::info!(
+            "getting bookings by event id from Firestore: '{}'",
+            event_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Booking>> = self
+            .db
+            .fluent()
+            .select()
+            .from("bookings")
+            .filter(|q| {
+                q.for_all([
+                    q.field("referenceEventId").eq(event_id),
+                    q.field("status").eq("confirmed"),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Booking> = object_stream.try_collect().await?;
+        tracing::info!("bookings found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_event_by_id(&self, id: &str) -> Result<Event> {
+        tracing::info!("getting event by id from Firestore: '{}'", id);
+
+        let doc: Option<Event> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("events")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(event) => Ok(event),
+            None => Err(anyhow::anyhow!("event not found")),
+        }
+    }
+
+    #[instrument]
+    async fn get_events_by_start_time(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        tracing::info!("getting events from Firestore between {} and {}", from, to);
+
+        let object_stream: BoxStream<FirestoreResult<Event>> = self
+            .db
+            .fluent()
+            .select()
+            .from("events")
+            .filter(|q| {
+                q.for_all([
+                    q.field(path!(Event::start_time))
+                        .greater_than_or_equal(FirestoreTimestamp(from)),
+                    q.field(path!(Event::start_time))
+                        .less_than(FirestoreTimestamp(to)),
+                ])
+            })
+            .order_by([(path!(Event::start_time), FirestoreQueryDirection::Ascending)])
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Event> = object_stream.try_collect().await?;
+        tracing::info!("events found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>> {
+        tracing::info!(
+            "getting reviews by performer id from Firestore: '{}'",
+            performer_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Review>> = self
+            .db
+            .fluent()
+            .select()
+            .from("reviews")
+            .filter(|q| {
+                q.for_all([
+                    // q.field(path!(Review::performer_id)).eq(performer_id),
+                    q.field("performerId").eq(performer_id),
+                    // q.field(path!(Review::review_type)).eq(ReviewType::Performer),
+                    q.field("type").eq("performer"),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Review> = object_stream.try_collect().await?;
+        tracing::info!("reviews found: {:?}", as_vec.len());
+
+        // Reviews from before moderation have no status to filter on, so
+        // unpublished ones are dropped here rather than in the query.
+        Ok(as_vec.into_iter().filter(Review::is_published).collect())
+    }
+
+    #[instrument]
+    async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>> {
+        tracing::info!(
+            "getting reviews by booker id from Firestore: '{}'",
+            booker_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Review>> = self
+            .db
+            .fluent()
+            .select()
+            .from("reviews")
+            .filter(|q| {
+                q.for_all([
+                    // q.field(path!(Review::booker_id)).eq(booker_id),
+                    q.field("bookerId").eq(booker_id),
+                    // q.field(path!(Review::review_type)).eq(ReviewType::Booker),
+                    q.field("type").eq("booker"),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Review> = object_stream.try_collect().await?;
+        tracing::info!("reviews found: {:?}", as_vec.len());
+
+        // Reviews from before moderation have no status to filter on, so
+        // unpublished ones are dropped here rather than in the query.
+        Ok(as_vec.into_iter().filter(Review::is_published).collect())
+    }
+
+    #[instrument]
+    async fn get_reviews_by_performer_ids(&self, performer_ids: &[String]) -> Result<Vec<Review>> {
+        tracing::info!(
+            "getting reviews of {} performers from Firestore",
+            performer_ids.len()
+        );
+
+        let as_vec: Vec<Review> = self
+            .select_in(
+                "reviews",
+                "performerId",
+                performer_ids,
+                ("type", "performer"),
+            )
+            .await?;
+        tracing::info!("reviews found: {:?}", as_vec.len());
+
+        Ok(as_vec.into_iter().filter(Review::is_published).collect())
+    }
+
+    #[instrument]
+    async fn get_reviews_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Review>> {
+        tracing::info!(
+            "getting reviews of {} bookers from Firestore",
+            booker_ids.len()
+        );
+
+        let as_vec: Vec<Review> = self
+            .select_in("reviews", "bookerId", booker_ids, ("type", "booker"))
+            .await?;
+        tracing::info!("reviews found: {:?}", as_vec.len());
+
+        Ok(as_vec.into_iter().filter(Review::is_published).collect())
+    }
+
+    #[instrument]
+    async fn get_reviews_by_booking_id(&self, booking_id: &str) -> Result<Vec<Review>> {
+        tracing::info!(
+            "getting reviews by booking id from Firestore: '{}'",
+            booking_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Review>> = self
+            .db
+            .fluent()
+            .select()
+            .from("reviews")
+            .filter(|q| q.field(path!(Review::booking_id)).eq(booking_id))
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Review> = object_stream.try_collect().await?;
+        tracing::info!("reviews found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument(skip(review))]
+    async fn create_review(&self, review: &Review) -> Result<()> {
+        tracing::info!("creating review in Firestore: '{}'", review.id);
+
+        let _: Review = self
+            .db
+            .fluent()
+            .insert()
+            .into("reviews")
+            .document_id(&review.id)
+            .object(review)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(webhook))]
+    async fn create_webhook(&self, webhook: &Webhook) -> Result<()> {
+        tracing::info!("creating webhook in Firestore: '{}'", webhook.id);
+
+        let _: Webhook = self
+            .db
+            .fluent()
+            .insert()
+            .into("webhooks")
+            .document_id(&webhook.id)
+            .object(webhook)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn get_webhook_by_id(&self, id: &str) -> Result<Webhook> {
+        tracing::info!("getting webhook by id from Firestore: '{}'", id);
+
+        let doc: Option<Webhook> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("webhooks")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(webhook) => Ok(webhook),
+            None => Err(anyhow::anyhow!("webhook not found")),
+        }
+    }
+
+    #[instrument]
+    async fn get_webhooks_by_owner_id(&self, owner_id: &str) -> Result<Vec<Webhook>> {
+        tracing::info!(
+            "getting webhooks by owner id from Firestore: '{}'",
+            owner_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Webhook>> = self
+            .db
+            .fluent()
+            .select()
+            .from("webhooks")
+            .filter(|q| q.field(path!(Webhook::owner_id)).eq(owner_id))
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Webhook> = object_stream.try_collect().await?;
+        tracing::info!("webhooks found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_webhooks_by_event_type(
+        &self,
+        event_type: WebhookEventType,
+    ) -> Result<Vec<Webhook>> {
+        tracing::info!(
+            "getting webhooks by event type from Firestore: '{}'",
+            event_type.as_str()
+        );
+
+        let object_stream: BoxStream<FirestoreResult<Webhook>> = self
+            .db
+            .fluent()
+            .select()
+            .from("webhooks")
+            .filter(|q| {
+                q.for_all([
+                    q.field(path!(Webhook::event_types))
+                        .array_contains(event_type.as_str()),
+                    q.field(path!(Webhook::active)).eq(true),
+                ])
+            })
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<Webhook> = object_stream.try_collect().await?;
+        tracing::info!("webhooks found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument(skip(webhook))]
+    async fn update_webhook(&self, webhook: &Webhook) -> Result<()> {
+        tracing::info!("updating webhook in Firestore: '{}'", webhook.id);
+
+        let _: Webhook = self
+            .db
+            .fluent()
+            .update()
+            .in_col("webhooks")
+            .document_id(&webhook.id)
+            .object(webhook)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    async fn delete_webhook(&self, id: &str) -> Result<()> {
+        tracing::info!("deleting webhook from Firestore: '{}'", id);
+
+        self.db
+            .fluent()
+            .delete()
+            .from("webhooks")
+            .document_id(id)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(delivery))]
+    async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        tracing::info!("creating webhook delivery in Firestore: '{}'", delivery.id);
+
+        // Inserting fails if the document exists, so only one instance
+        // records and sends each delivery.
+        let inserted: FirestoreResult<WebhookDelivery> = self
+            .db
+            .fluent()
+            .insert()
+            .into("webhookDeliveries")
+            .document_id(&delivery.id)
+            .object(delivery)
+            .execute()
+            .await;
+
+        match inserted {
+            Ok(_) => Ok(true),
+            Err(FirestoreError::DataConflictError(_)) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    #[instrument(skip(delivery))]
+    async fn upsert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        tracing::info!("saving webhook delivery in Firestore: '{}'", delivery.id);
+
+        let _: WebhookDelivery = self
+            .db
+            .fluent()
+            .update()
+            .in_col("webhookDeliveries")
+            .document_id(&delivery.id)
+            .object(delivery)
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(stalled))]
+    async fn claim_webhook_delivery(
+        &self,
+        stalled: &WebhookDelivery,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebhookDelivery>> {
+        tracing::info!("claiming webhook delivery in Firestore: '{}'", stalled.id);
+
+        // Reads in the transaction lock the delivery until it commits, so
+        // only one instance sees it unchanged and resumes it.
+        let mut transaction = self.db.begin_transaction().await?;
+        let db =
+            self.db
+                .clone_with_consistency_selector(FirestoreConsistencySelector::Transaction(
+                    transaction.transaction_id().clone(),
+                ));
+        let doc: Option<WebhookDelivery> = db
+            .fluent()
+            .select()
+            .by_id_in("webhookDeliveries")
+            .obj()
+            .one(&stalled.id)
+            .await?;
+
+        let Some(mut delivery) = doc.filter(|doc| is_unchanged(doc, stalled)) else {
+            transaction.rollback().await?;
+            return Ok(None);
+        };
+        delivery.next_attempt_at = Some(now);
+
+        self.db
+            .fluent()
+            .update()
+            .fields([path!(WebhookDelivery::next_attempt_at)])
+            .in_col("webhookDeliveries")
+            .document_id(&delivery.id)
+            .object(&delivery)
+            .add_to_transaction(&mut transaction)?;
+        transaction.commit().await?;
+
+        Ok(Some(delivery))
+    }
+
+    #[instrument]
+    async fn get_webhook_delivery_by_id(&self, id: &str) -> Result<WebhookDelivery> {
+        tracing::info!("getting webhook delivery by id from Firestore: '{}'", id);
+
+        let doc: Option<WebhookDelivery> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("webhookDeliveries")
+            .obj()
+            .one(id)
+            .await?;
+
+        match doc {
+            Some(delivery) => Ok(delivery),
+            None => Err(anyhow::anyhow!("webhook delivery not found")),
+        }
+    }
+
+    #[instrument]
+    async fn get_webhook_deliveries_by_webhook_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>> {
+        tracing::info!(
+            "getting webhook deliveries by webhook id from Firestore: '{}'",
+            webhook_id
+        );
+
+        let object_stream: BoxStream<FirestoreResult<WebhookDelivery>> = self
+            .db
+            .fluent()
+            .select()
+            .from("webhookDeliveries")
+            .filter(|q| q.field(path!(WebhookDelivery::webhook_id)).eq(webhook_id))
+            .order_by([(
+                path!(WebhookDelivery::timestamp),
+                FirestoreQueryDirection::Descending,
+            )])
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<WebhookDelivery> = object_stream.try_collect().await?;
+        tracing::info!("webhook deliveries found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+
+    #[instrument]
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        tracing::info!("getting pending webhook deliveries from Firestore");
+
+        let object_stream: BoxStream<FirestoreResult<WebhookDelivery>> = self
+            .db
+            .fluent()
+            .select()
+            .from("webhookDeliveries")
+            .filter(|q| q.field(path!(WebhookDelivery::status)).eq("pending"))
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let as_vec: Vec<WebhookDelivery> = object_stream.try_collect().await?;
+        tracing::info!("pending webhook deliveries found: {:?}", as_vec.len());
+
+        Ok(as_vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A performer's bookings used to be queried by the booker's field, so
+    // they came back empty or held the bookings the performer had made.
+    #[test]
+    fn booking_queries_filter_on_the_right_party() {
+        let booking = Booking {
+            requester_id: Some("booker".into()),
+            requestee_id: "performer".into(),
+            ..Default::default()
+        };
+        let stored = serde_json::to_value(&booking).unwrap();
+
+        assert_eq!("performer", stored[BOOKING_PERFORMER_FIELD]);
+        assert_eq!("booker", stored[BOOKING_BOOKER_FIELD]);
     }
 }
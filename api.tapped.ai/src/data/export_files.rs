@@ -0,0 +1,238 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{async_trait, body::Bytes};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use gcloud_sdk::{
+    google::storage::v2::{
+        storage_client::StorageClient,
+        write_object_request::{Data, FirstMessage},
+        ChecksummedData, DeleteObjectRequest, Object, ReadObjectRequest, WriteObjectRequest,
+        WriteObjectSpec,
+    },
+    tonic::{metadata::MetadataValue, Request},
+    GoogleApi, GoogleAuthMiddleware,
+};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::ReaderStream;
+
+use crate::configuration::{ExportBackend, ExportSettings};
+
+const CLOUD_STORAGE_URL: &str = "https://storage.googleapis.com";
+const CLOUD_STORAGE_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+
+/// Cloud Storage takes at most 2 MiB of data per upload message.
+const UPLOAD_CHUNK_BYTES: usize = 2 * 1024 * 1024;
+
+/// Where background exports are written, and read back from when they are
+/// downloaded.
+#[async_trait]
+pub trait ExportFiles: Send + Sync {
+    async fn write(&self, name: &str, chunks: BoxStream<'static, Result<Bytes>>) -> Result<()>;
+    async fn read(&self, name: &str) -> Result<BoxStream<'static, Result<Bytes>>>;
+    async fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// The storage picked by `exports.backend`.
+pub async fn from_settings(settings: &ExportSettings) -> Result<Arc<dyn ExportFiles>> {
+    Ok(match settings.backend {
+        ExportBackend::Gcs => {
+            let bucket = settings
+                .bucket
+                .clone()
+                .context("exports.bucket is required for the gcs backend")?;
+
+            Arc::new(CloudStorage::new(bucket).await?)
+        }
+        ExportBackend::Local => Arc::new(LocalExportFiles::new(
+            settings
+                .directory
+                .clone()
+                .unwrap_or_else(|| std::env::temp_dir().join("tapped-exports")),
+        )),
+    })
+}
+
+/// Files in a local directory, so only the instance that wrote an export
+/// can serve it.
+pub struct LocalExportFiles {
+    directory: PathBuf,
+}
+
+impl LocalExportFiles {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+#[async_trait]
+impl ExportFiles for LocalExportFiles {
+    async fn write(&self, name: &str, mut chunks: BoxStream<'static, Result<Bytes>>) -> Result<()> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+        let file = tokio::fs::File::create(self.directory.join(name)).await?;
+        let mut file = tokio::io::BufWriter::new(file);
+
+        while let Some(chunk) = chunks.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    async fn read(&self, name: &str) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let file = tokio::fs::File::open(self.directory.join(name)).await?;
+
+        Ok(ReaderStream::new(file).map_err(anyhow::Error::from).boxed())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        tokio::fs::remove_file(self.directory.join(name)).await?;
+
+        Ok(())
+    }
+}
+
+/// Objects in a Cloud Storage bucket, shared by every instance.
+pub struct CloudStorage {
+    client: GoogleApi<StorageClient<GoogleAuthMiddleware>>,
+    bucket: String,
+}
+
+impl CloudStorage {
+    pub async fn new(bucket: String) -> Result<Self> {
+        let client = GoogleApi::from_function_with_scopes(
+            StorageClient::new,
+            CLOUD_STORAGE_URL,
+            None,
+            vec![CLOUD_STORAGE_SCOPE.to_string()],
+        )
+        .await
+        .context("Failed to connect to Cloud Storage")?;
+
+        Ok(Self { client, bucket })
+    }
+
+    fn bucket_path(&self) -> String {
+        format!("projects/_/buckets/{}", self.bucket)
+    }
+
+    /// Cloud Storage routes requests by bucket, from a header rather than
+    /// the message.
+    fn request<T>(&self, message: T) -> Result<Request<T>> {
+        let mut request = Request::new(message);
+        request.metadata_mut().insert(
+            "x-goog-request-params",
+            MetadataValue::try_from(format!("bucket={}", self.bucket_path()))?,
+        );
+
+        Ok(request)
+    }
+
+    /// Sends `chunks` as upload messages of at most `UPLOAD_CHUNK_BYTES`.
+    /// Returning early without the final message abandons the upload.
+    async fn send_chunks(
+        &self,
+        name: &str,
+        mut chunks: BoxStream<'static, Result<Bytes>>,
+        messages: mpsc::Sender<WriteObjectRequest>,
+    ) -> Result<()> {
+        let mut spec = Some(WriteObjectSpec {
+            resource: Some(Object {
+                name: name.to_string(),
+                bucket: self.bucket_path(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let mut offset = 0;
+        let mut buffer = Vec::new();
+
+        loop {
+            let chunk = chunks.next().await.transpose()?;
+            let finished = chunk.is_none();
+            if let Some(chunk) = chunk {
+                buffer.extend_from_slice(&chunk);
+            }
+
+            while buffer.len() >= UPLOAD_CHUNK_BYTES || finished {
+                let content: Vec<u8> = buffer
+                    .drain(..buffer.len().min(UPLOAD_CHUNK_BYTES))
+                    .collect();
+                let last = finished && buffer.is_empty();
+                let length = content.len() as i64;
+
+                let message = WriteObjectRequest {
+                    write_offset: offset,
+                    first_message: spec.take().map(FirstMessage::WriteObjectSpec),
+                    data: Some(Data::ChecksummedData(ChecksummedData {
+                        content,
+                        crc32c: None,
+                    })),
+                    finish_write: last,
+                    ..Default::default()
+                };
+                // The upload only stops listening once it has failed, and
+                // reports why itself.
+                if messages.send(message).await.is_err() || last {
+                    return Ok(());
+                }
+                offset += length;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ExportFiles for CloudStorage {
+    async fn write(&self, name: &str, chunks: BoxStream<'static, Result<Bytes>>) -> Result<()> {
+        let (messages, receiver) = mpsc::channel(1);
+        let request = self.request(ReceiverStream::new(receiver))?;
+
+        let mut client = self.client.get();
+        let (upload, sent) = tokio::join!(
+            client.write_object(request),
+            self.send_chunks(name, chunks, messages)
+        );
+        // A failed export also fails the upload, so its error is the one
+        // worth reporting.
+        sent?;
+        upload?;
+
+        Ok(())
+    }
+
+    async fn read(&self, name: &str) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let request = self.request(ReadObjectRequest {
+            bucket: self.bucket_path(),
+            object: name.to_string(),
+            ..Default::default()
+        })?;
+        let response = self.client.get().read_object(request).await?;
+
+        Ok(response
+            .into_inner()
+            .map_ok(|message| {
+                Bytes::from(
+                    message
+                        .checksummed_data
+                        .map(|data| data.content)
+                        .unwrap_or_default(),
+                )
+            })
+            .map_err(anyhow::Error::from)
+            .boxed())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        let request = self.request(DeleteObjectRequest {
+            bucket: self.bucket_path(),
+            object: name.to_string(),
+            ..Default::default()
+        })?;
+        self.client.get().delete_object(request).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use firestore::FirestoreDb;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::domain::models::{
+    api_key::ApiKey, booking::Booking, event::Event, review::Review, user::UserModel,
+};
+
+use super::memory::InMemoryDatabase;
+
+/// Sample data for local development, read from a directory holding
+/// `users.json`, `bookings.json`, `events.json`, `reviews.json` and
+/// `apiKeys.json`. A missing file is treated as empty.
+#[derive(Debug, Default)]
+pub struct Fixtures {
+    pub users: Vec<UserModel>,
+    pub bookings: Vec<Booking>,
+    pub events: Vec<Event>,
+    pub reviews: Vec<Review>,
+    pub api_keys: Vec<ApiKey>,
+}
+
+impl Fixtures {
+    pub fn load(directory: &Path) -> Result<Self> {
+        Ok(Self {
+            users: read(&directory.join("users.json"))?,
+            bookings: read(&directory.join("bookings.json"))?,
+            events: read(&directory.join("events.json"))?,
+            reviews: read(&directory.join("reviews.json"))?,
+            api_keys: read(&directory.join("apiKeys.json"))?,
+        })
+    }
+
+    /// Writes every fixture to Firestore, replacing documents with the same id.
+    pub async fn seed_firestore(&self, db: &FirestoreDb) -> Result<()> {
+        for user in &self.users {
+            upsert(db, "users", &user.id, user).await?;
+        }
+        for booking in &self.bookings {
+            upsert(db, "bookings", &booking.id, booking).await?;
+        }
+        for event in &self.events {
+            upsert(db, "events", &event.id, event).await?;
+        }
+        for review in &self.reviews {
+            upsert(db, "reviews", &review.id, review).await?;
+        }
+        for api_key in &self.api_keys {
+            let api_key = api_key.clone().hashed();
+            upsert(db, "apiKeys", &api_key.key_hash, &api_key).await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn seed_memory(&self, db: &InMemoryDatabase) {
+        self.users.iter().cloned().for_each(|u| db.insert_user(u));
+        self.bookings
+            .iter()
+            .cloned()
+            .for_each(|b| db.insert_booking(b));
+        self.events.iter().cloned().for_each(|e| db.insert_event(e));
+        self.reviews
+            .iter()
+            .cloned()
+            .for_each(|r| db.insert_review(r));
+        self.api_keys
+            .iter()
+            .cloned()
+            .for_each(|k| db.insert_api_key(k));
+    }
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    serde_json::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+async fn upsert<T>(db: &FirestoreDb, collection: &str, id: &str, object: &T) -> Result<()>
+where
+    T: Serialize + DeserializeOwned + Send + Sync,
+{
+    tracing::info!("seeding {collection}/{id}");
+
+    let _: T = db
+        .fluent()
+        .update()
+        .in_col(collection)
+        .document_id(id)
+        .object(object)
+        .execute()
+        .await?;
+
+    Ok(())
+}
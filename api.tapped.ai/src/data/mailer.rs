@@ -0,0 +1,118 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use axum::async_trait;
+use serde_json::json;
+
+use crate::configuration::{MailBackend, MailSettings};
+
+const SENDGRID_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+/// A plain text email to one recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<()>;
+}
+
+/// The mailer picked by `mail.backend`.
+pub fn from_settings(settings: &MailSettings) -> Result<Arc<dyn Mailer>> {
+    Ok(match settings.backend {
+        MailBackend::Sendgrid => Arc::new(SendGrid::new(settings)?),
+        MailBackend::Log => Arc::new(LogMailer),
+    })
+}
+
+/// Sends through SendGrid's v3 mail API.
+pub struct SendGrid {
+    client: reqwest::Client,
+    api_key: String,
+    from: String,
+}
+
+impl SendGrid {
+    pub fn new(settings: &MailSettings) -> Result<Self> {
+        let api_key = settings
+            .sendgrid_api_key
+            .clone()
+            .context("mail.sendgrid_api_key is required for the sendgrid backend")?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            api_key,
+            from: settings.from.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SendGrid {
+    async fn send(&self, email: &Email) -> Result<()> {
+        tracing::info!("sending '{}' through SendGrid", email.subject);
+
+        self.client
+            .post(SENDGRID_URL)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "personalizations": [{ "to": [{ "email": email.to }] }],
+                "from": { "email": self.from },
+                "subject": email.subject,
+                "content": [{ "type": "text/plain", "value": email.body }],
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Writes emails to the log instead of sending them. Only for environments
+/// where whoever reads the logs may read the emails too.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        tracing::info!(
+            "not sending '{}' to {}:\n{}",
+            email.subject,
+            email.to,
+            email.body
+        );
+
+        Ok(())
+    }
+}
+
+/// Keeps every email in process memory, for tests.
+#[derive(Clone, Default)]
+pub struct InMemoryMailer {
+    sent: Arc<RwLock<Vec<Email>>>,
+}
+
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.read().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        self.sent.write().unwrap().push(email.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,883 @@
+use crate::{
+    data::soft_delete,
+    domain::models::{
+        api_key::ApiKey,
+        booking::{Booking, BookingStatus},
+        calendar_feed::CalendarFeed,
+        claim::ProfileClaim,
+        event::Event,
+        export_job::ExportJob,
+        review::{Review, ReviewType},
+        user::UserModel,
+        webhook::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEventType},
+    },
+};
+use anyhow::Result;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use super::database::{self, Database, UserNotFound};
+
+/// A `Database` held entirely in memory. Used by the test suite so the API
+/// can be exercised without a Firestore project.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDatabase {
+    api_keys: Arc<RwLock<HashMap<String, ApiKey>>>,
+    users: Arc<RwLock<HashMap<String, UserModel>>>,
+    bookings: Arc<RwLock<HashMap<String, Booking>>>,
+    calendar_feeds: Arc<RwLock<HashMap<String, CalendarFeed>>>,
+    export_jobs: Arc<RwLock<HashMap<String, ExportJob>>>,
+    events: Arc<RwLock<HashMap<String, Event>>>,
+    profile_claims: Arc<RwLock<HashMap<String, ProfileClaim>>>,
+    reviews: Arc<RwLock<HashMap<String, Review>>>,
+    webhooks: Arc<RwLock<HashMap<String, Webhook>>>,
+    webhook_deliveries: Arc<RwLock<HashMap<String, WebhookDelivery>>>,
+    calls: Arc<RwLock<Vec<&'static str>>>,
+    unreachable: Arc<RwLock<bool>>,
+    latency: Arc<RwLock<Duration>>,
+}
+
+impl InMemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The names of the `Database` methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.calls.read().unwrap().clone()
+    }
+
+    /// Forgets the calls recorded so far.
+    pub fn clear_calls(&self) {
+        self.calls.write().unwrap().clear();
+    }
+
+    /// Every stored user, ordered by id.
+    pub fn users(&self) -> Vec<UserModel> {
+        let mut users: Vec<_> = self.users.read().unwrap().values().cloned().collect();
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+
+        users
+    }
+
+    /// Every stored booking, ordered by id.
+    pub fn bookings(&self) -> Vec<Booking> {
+        let mut bookings: Vec<_> = self.bookings.read().unwrap().values().cloned().collect();
+        bookings.sort_by(|a, b| a.id.cmp(&b.id));
+
+        bookings
+    }
+
+    /// Makes `ping` fail, as if the database had gone away.
+    pub fn set_unreachable(&self, unreachable: bool) {
+        *self.unreachable.write().unwrap() = unreachable;
+    }
+
+    /// Delays every call by `latency`, as if the database were slow.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.write().unwrap() = latency;
+    }
+
+    fn find_user_by_id(&self, id: &str) -> Result<UserModel> {
+        self.users
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| UserNotFound.into())
+    }
+
+    fn find_user_by_username(&self, username: &str) -> Result<UserModel> {
+        self.users
+            .read()
+            .unwrap()
+            .values()
+            .find(|user| user.username == username)
+            .cloned()
+            .ok_or_else(|| UserNotFound.into())
+    }
+
+    async fn record(&self, method: &'static str) {
+        self.calls.write().unwrap().push(method);
+
+        let latency = *self.latency.read().unwrap();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    /// Stores the key as it is stored now, under its hash.
+    pub fn insert_api_key(&self, api_key: ApiKey) {
+        let api_key = stored(api_key.hashed());
+        self.api_keys
+            .write()
+            .unwrap()
+            .insert(api_key.key_hash.clone(), api_key);
+    }
+
+    /// Stores the key as it was before keys were hashed, under the key
+    /// itself.
+    pub fn insert_legacy_api_key(&self, api_key: ApiKey) {
+        self.api_keys
+            .write()
+            .unwrap()
+            .insert(api_key.key.clone(), api_key);
+    }
+
+    /// Moves legacy keys under their hash, as `Firestore` does when it
+    /// comes across them.
+    fn upgrade_api_keys(&self, api_keys: Vec<ApiKey>) -> Vec<ApiKey> {
+        let mut stored_keys = self.api_keys.write().unwrap();
+
+        api_keys
+            .into_iter()
+            .map(|api_key| {
+                if !api_key.is_legacy() {
+                    return api_key;
+                }
+
+                stored_keys.remove(&api_key.key);
+                let api_key = stored(api_key.hashed());
+                stored_keys.insert(api_key.key_hash.clone(), api_key.clone());
+
+                api_key
+            })
+            .collect()
+    }
+
+    pub fn insert_user(&self, user: UserModel) {
+        self.users.write().unwrap().insert(user.id.clone(), user);
+    }
+
+    pub fn insert_booking(&self, booking: Booking) {
+        self.bookings
+            .write()
+            .unwrap()
+            .insert(booking.id.clone(), booking);
+    }
+
+    pub fn insert_event(&self, event: Event) {
+        self.events.write().unwrap().insert(event.id.clone(), event);
+    }
+
+    pub fn insert_review(&self, review: Review) {
+        self.reviews
+            .write()
+            .unwrap()
+            .insert(review.id.clone(), review);
+    }
+}
+
+#[async_trait]
+impl Database for InMemoryDatabase {
+    async fn ping(&self) -> Result<()> {
+        self.record("ping").await;
+
+        if *self.unreachable.read().unwrap() {
+            anyhow::bail!("database is unreachable");
+        }
+
+        Ok(())
+    }
+
+    async fn get_api_key(&self, api_key: &str) -> Result<ApiKey> {
+        self.record("get_api_key").await;
+
+        let found = {
+            let api_keys = self.api_keys.read().unwrap();
+            api_keys
+                .get(&ApiKey::hash(api_key))
+                .or_else(|| api_keys.get(api_key))
+                .cloned()
+        };
+
+        found
+            .map(|api_key| self.upgrade_api_keys(vec![api_key]).remove(0))
+            .ok_or_else(|| anyhow::anyhow!("api key not found"))
+    }
+
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.record("create_api_key").await;
+
+        let mut api_keys = self.api_keys.write().unwrap();
+        if api_keys.contains_key(&api_key.key_hash) {
+            return Err(anyhow::anyhow!("api key already exists"));
+        }
+        api_keys.insert(api_key.key_hash.clone(), stored(api_key.clone()));
+
+        Ok(())
+    }
+
+    async fn get_api_key_by_id(&self, id: &str) -> Result<ApiKey> {
+        self.record("get_api_key_by_id").await;
+
+        let found: Vec<ApiKey> = self
+            .api_keys
+            .read()
+            .unwrap()
+            .values()
+            .filter(|api_key| api_key.id == id)
+            .take(1)
+            .cloned()
+            .collect();
+
+        self.upgrade_api_keys(found)
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("api key not found"))
+    }
+
+    async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        self.record("get_api_keys_by_user_id").await;
+
+        let found = self
+            .api_keys
+            .read()
+            .unwrap()
+            .values()
+            .filter(|api_key| api_key.user_id == user_id)
+            .cloned()
+            .collect();
+
+        Ok(self.upgrade_api_keys(found))
+    }
+
+    async fn update_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.record("update_api_key").await;
+
+        self.api_keys
+            .write()
+            .unwrap()
+            .insert(api_key.key_hash.clone(), stored(api_key.clone()));
+
+        Ok(())
+    }
+
+    async fn get_user_by_id(&self, id: &str) -> Result<UserModel> {
+        self.record("get_user_by_id").await;
+
+        soft_delete::visible(self.find_user_by_id(id)?)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<UserModel> {
+        self.record("get_user_by_username").await;
+
+        soft_delete::visible(self.find_user_by_username(username)?)
+    }
+
+    async fn get_user_by_id_including_deleted(&self, id: &str) -> Result<UserModel> {
+        self.record("get_user_by_id_including_deleted").await;
+
+        self.find_user_by_id(id)
+    }
+
+    async fn get_user_by_username_including_deleted(&self, username: &str) -> Result<UserModel> {
+        self.record("get_user_by_username_including_deleted").await;
+
+        self.find_user_by_username(username)
+    }
+
+    async fn get_users_by_ids(&self, ids: &[String]) -> Result<Vec<UserModel>> {
+        self.record("get_users_by_ids").await;
+
+        let users = self.users.read().unwrap();
+
+        Ok(soft_delete::without_deleted(
+            ids.iter().filter_map(|id| users.get(id).cloned()).collect(),
+        ))
+    }
+
+    async fn claim_user(&self, id: &str, claimant_id: &str) -> Result<UserModel> {
+        self.record("claim_user").await;
+
+        let mut users = self.users.write().unwrap();
+        let user = users.get(id).cloned().ok_or(UserNotFound)?;
+        let user = database::claim(user, claimant_id)?;
+        users.insert(id.to_string(), user.clone());
+
+        Ok(user)
+    }
+
+    async fn create_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.record("create_profile_claim").await;
+
+        let mut claims = self.profile_claims.write().unwrap();
+        if claims.contains_key(&claim.id) {
+            return Err(anyhow::anyhow!("profile claim already exists"));
+        }
+        claims.insert(claim.id.clone(), claim.clone());
+
+        Ok(())
+    }
+
+    async fn get_profile_claim_by_id(&self, id: &str) -> Result<ProfileClaim> {
+        self.record("get_profile_claim_by_id").await;
+
+        self.profile_claims
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("profile claim not found"))
+    }
+
+    async fn get_profile_claims_by_user_id(&self, user_id: &str) -> Result<Vec<ProfileClaim>> {
+        self.record("get_profile_claims_by_user_id").await;
+
+        Ok(self
+            .profile_claims
+            .read()
+            .unwrap()
+            .values()
+            .filter(|claim| claim.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.record("update_profile_claim").await;
+
+        self.profile_claims
+            .write()
+            .unwrap()
+            .insert(claim.id.clone(), claim.clone());
+
+        Ok(())
+    }
+
+    async fn create_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.record("create_calendar_feed").await;
+
+        let mut feeds = self.calendar_feeds.write().unwrap();
+        if feeds.contains_key(&feed.id) {
+            return Err(anyhow::anyhow!("calendar feed already exists"));
+        }
+        feeds.insert(feed.id.clone(), feed.clone());
+
+        Ok(())
+    }
+
+    async fn get_calendar_feed_by_id(&self, id: &str) -> Result<CalendarFeed> {
+        self.record("get_calendar_feed_by_id").await;
+
+        self.calendar_feeds
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("calendar feed not found"))
+    }
+
+    async fn get_calendar_feed_by_token_hash(&self, token_hash: &str) -> Result<CalendarFeed> {
+        self.record("get_calendar_feed_by_token_hash").await;
+
+        self.calendar_feeds
+            .read()
+            .unwrap()
+            .values()
+            .find(|feed| feed.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("calendar feed not found"))
+    }
+
+    async fn get_calendar_feeds_by_performer_id(
+        &self,
+        performer_id: &str,
+    ) -> Result<Vec<CalendarFeed>> {
+        self.record("get_calendar_feeds_by_performer_id").await;
+
+        Ok(self
+            .calendar_feeds
+            .read()
+            .unwrap()
+            .values()
+            .filter(|feed| feed.performer_id == performer_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.record("update_calendar_feed").await;
+
+        self.calendar_feeds
+            .write()
+            .unwrap()
+            .insert(feed.id.clone(), feed.clone());
+
+        Ok(())
+    }
+
+    async fn create_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.record("create_export_job").await;
+
+        let mut jobs = self.export_jobs.write().unwrap();
+        if jobs.contains_key(&job.id) {
+            return Err(anyhow::anyhow!("export job already exists"));
+        }
+        jobs.insert(job.id.clone(), job.clone());
+
+        Ok(())
+    }
+
+    async fn get_export_job_by_id(&self, id: &str) -> Result<ExportJob> {
+        self.record("get_export_job_by_id").await;
+
+        self.export_jobs
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("export job not found"))
+    }
+
+    async fn update_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.record("update_export_job").await;
+
+        self.export_jobs
+            .write()
+            .unwrap()
+            .insert(job.id.clone(), job.clone());
+
+        Ok(())
+    }
+
+    async fn get_export_jobs_created_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<ExportJob>> {
+        self.record("get_export_jobs_created_before").await;
+
+        Ok(self
+            .export_jobs
+            .read()
+            .unwrap()
+            .values()
+            .filter(|job| job.timestamp < cutoff)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_export_job(&self, id: &str) -> Result<()> {
+        self.record("delete_export_job").await;
+
+        self.export_jobs.write().unwrap().remove(id);
+
+        Ok(())
+    }
+
+    async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.record("get_bookings_by_performer_id").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                booking.requestee_id == performer_id && booking.status == BookingStatus::Confirmed
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_open_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.record("get_open_bookings_by_performer_id").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                booking.requestee_id == performer_id && booking.status != BookingStatus::Canceled
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>> {
+        self.record("get_bookings_by_booker_id").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                booking.requester_id.as_deref() == Some(booker_id)
+                    && booking.status == BookingStatus::Confirmed
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_bookings_by_performer_ids(
+        &self,
+        performer_ids: &[String],
+    ) -> Result<Vec<Booking>> {
+        self.record("get_bookings_by_performer_ids").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                performer_ids.contains(&booking.requestee_id)
+                    && booking.status == BookingStatus::Confirmed
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_bookings_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Booking>> {
+        self.record("get_bookings_by_booker_ids").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                booking
+                    .requester_id
+                    .as_ref()
+                    .is_some_and(|id| booker_ids.contains(id))
+                    && booking.status == BookingStatus::Confirmed
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_booking_by_id(&self, id: &str) -> Result<Booking> {
+        self.record("get_booking_by_id").await;
+
+        self.bookings
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("booking not found"))
+    }
+
+    async fn create_booking(&self, booking: &Booking) -> Result<()> {
+        self.record("create_booking").await;
+
+        let mut bookings = self.bookings.write().unwrap();
+        if bookings.contains_key(&booking.id) {
+            return Err(anyhow::anyhow!("booking already exists"));
+        }
+        bookings.insert(booking.id.clone(), booking.clone());
+
+        Ok(())
+    }
+
+    async fn get_bookings_by_event_id(&self, event_id: &str) -> Result<Vec<Booking>> {
+        self.record("get_bookings_by_event_id").await;
+
+        Ok(self
+            .bookings
+            .read()
+            .unwrap()
+            .values()
+            .filter(|booking| {
+                booking.reference_event_id.as_deref() == Some(event_id)
+                    && booking.status == BookingStatus::Confirmed
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_event_by_id(&self, id: &str) -> Result<Event> {
+        self.record("get_event_by_id").await;
+
+        self.events
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("event not found"))
+    }
+
+    async fn get_events_by_start_time(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.record("get_events_by_start_time").await;
+
+        let mut events: Vec<Event> = self
+            .events
+            .read()
+            .unwrap()
+            .values()
+            .filter(|event| event.start_time >= from && event.start_time < to)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.start_time);
+
+        Ok(events)
+    }
+
+    async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>> {
+        self.record("get_reviews_by_performer_id").await;
+
+        Ok(self
+            .reviews
+            .read()
+            .unwrap()
+            .values()
+            .filter(|review| {
+                review.performer_id == performer_id
+                    && review.review_type == ReviewType::Performer
+                    && review.is_published()
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>> {
+        self.record("get_reviews_by_booker_id").await;
+
+        Ok(self
+            .reviews
+            .read()
+            .unwrap()
+            .values()
+            .filter(|review| {
+                review.booker_id == booker_id
+                    && review.review_type == ReviewType::Booker
+                    && review.is_published()
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_reviews_by_performer_ids(&self, performer_ids: &[String]) -> Result<Vec<Review>> {
+        self.record("get_reviews_by_performer_ids").await;
+
+        Ok(self
+            .reviews
+            .read()
+            .unwrap()
+            .values()
+            .filter(|review| {
+                performer_ids.contains(&review.performer_id)
+                    && review.review_type == ReviewType::Performer
+                    && review.is_published()
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_reviews_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Review>> {
+        self.record("get_reviews_by_booker_ids").await;
+
+        Ok(self
+            .reviews
+            .read()
+            .unwrap()
+            .values()
+            .filter(|review| {
+                booker_ids.contains(&review.booker_id)
+                    && review.review_type == ReviewType::Booker
+                    && review.is_published()
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_reviews_by_booking_id(&self, booking_id: &str) -> Result<Vec<Review>> {
+        self.record("get_reviews_by_booking_id").await;
+
+        Ok(self
+            .reviews
+            .read()
+            .unwrap()
+            .values()
+            .filter(|review| review.booking_id == booking_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn create_review(&self, review: &Review) -> Result<()> {
+        self.record("create_review").await;
+
+        let mut reviews = self.reviews.write().unwrap();
+        if reviews.contains_key(&review.id) {
+            return Err(anyhow::anyhow!("review already exists"));
+        }
+        reviews.insert(review.id.clone(), review.clone());
+
+        Ok(())
+    }
+
+    async fn create_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.record("create_webhook").await;
+
+        let mut webhooks = self.webhooks.write().unwrap();
+        if webhooks.contains_key(&webhook.id) {
+            return Err(anyhow::anyhow!("webhook already exists"));
+        }
+        webhooks.insert(webhook.id.clone(), webhook.clone());
+
+        Ok(())
+    }
+
+    async fn get_webhook_by_id(&self, id: &str) -> Result<Webhook> {
+        self.record("get_webhook_by_id").await;
+
+        self.webhooks
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("webhook not found"))
+    }
+
+    async fn get_webhooks_by_owner_id(&self, owner_id: &str) -> Result<Vec<Webhook>> {
+        self.record("get_webhooks_by_owner_id").await;
+
+        Ok(self
+            .webhooks
+            .read()
+            .unwrap()
+            .values()
+            .filter(|webhook| webhook.owner_id == owner_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_webhooks_by_event_type(
+        &self,
+        event_type: WebhookEventType,
+    ) -> Result<Vec<Webhook>> {
+        self.record("get_webhooks_by_event_type").await;
+
+        Ok(self
+            .webhooks
+            .read()
+            .unwrap()
+            .values()
+            .filter(|webhook| webhook.active && webhook.event_types.contains(&event_type))
+            .cloned()
+            .collect())
+    }
+
+    async fn update_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.record("update_webhook").await;
+
+        self.webhooks
+            .write()
+            .unwrap()
+            .insert(webhook.id.clone(), webhook.clone());
+
+        Ok(())
+    }
+
+    async fn delete_webhook(&self, id: &str) -> Result<()> {
+        self.record("delete_webhook").await;
+
+        self.webhooks.write().unwrap().remove(id);
+
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        self.record("create_webhook_delivery").await;
+
+        let mut deliveries = self.webhook_deliveries.write().unwrap();
+        if deliveries.contains_key(&delivery.id) {
+            return Ok(false);
+        }
+        deliveries.insert(delivery.id.clone(), delivery.clone());
+
+        Ok(true)
+    }
+
+    async fn upsert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.record("upsert_webhook_delivery").await;
+
+        self.webhook_deliveries
+            .write()
+            .unwrap()
+            .insert(delivery.id.clone(), delivery.clone());
+
+        Ok(())
+    }
+
+    async fn claim_webhook_delivery(
+        &self,
+        stalled: &WebhookDelivery,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebhookDelivery>> {
+        self.record("claim_webhook_delivery").await;
+
+        let mut deliveries = self.webhook_deliveries.write().unwrap();
+        let Some(delivery) = deliveries
+            .get_mut(&stalled.id)
+            .filter(|delivery| database::is_unchanged(delivery, stalled))
+        else {
+            return Ok(None);
+        };
+        delivery.next_attempt_at = Some(now);
+
+        Ok(Some(delivery.clone()))
+    }
+
+    async fn get_webhook_delivery_by_id(&self, id: &str) -> Result<WebhookDelivery> {
+        self.record("get_webhook_delivery_by_id").await;
+
+        self.webhook_deliveries
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("webhook delivery not found"))
+    }
+
+    async fn get_webhook_deliveries_by_webhook_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>> {
+        self.record("get_webhook_deliveries_by_webhook_id").await;
+
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .webhook_deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|delivery| delivery.webhook_id == webhook_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|delivery| std::cmp::Reverse(delivery.timestamp));
+
+        Ok(deliveries)
+    }
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        self.record("get_pending_webhook_deliveries").await;
+
+        Ok(self
+            .webhook_deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|delivery| delivery.status == WebhookDeliveryStatus::Pending)
+            .cloned()
+            .collect())
+    }
+}
+
+/// What `Firestore` keeps of a key: everything but the key itself.
+fn stored(api_key: ApiKey) -> ApiKey {
+    ApiKey {
+        key: String::new(),
+        ..api_key
+    }
+}
@@ -0,0 +1,486 @@
+use std::{future::Future, sync::Arc, time::Instant};
+
+use anyhow::Result;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    data::{
+        database::Database,
+        search::{Search, UserSearchOptions},
+    },
+    domain::models::{
+        api_key::ApiKey,
+        booking::Booking,
+        calendar_feed::CalendarFeed,
+        claim::ProfileClaim,
+        event::Event,
+        export_job::ExportJob,
+        review::Review,
+        user::UserModel,
+        webhook::{Webhook, WebhookDelivery, WebhookEventType},
+    },
+    metrics::Metrics,
+};
+
+/// Times every call to the wrapped `Database` and counts it by method and
+/// outcome.
+#[derive(Clone)]
+pub struct MeteredDatabase {
+    inner: Arc<dyn Database>,
+    metrics: Metrics,
+}
+
+impl MeteredDatabase {
+    pub fn new(inner: Arc<dyn Database>, metrics: Metrics) -> Self {
+        Self { inner, metrics }
+    }
+
+    async fn observe<T>(
+        &self,
+        method: &'static str,
+        call: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = call.await;
+        self.metrics
+            .observe_database(method, result.is_ok(), started.elapsed());
+
+        result
+    }
+}
+
+#[async_trait]
+impl Database for MeteredDatabase {
+    async fn ping(&self) -> Result<()> {
+        self.observe("ping", self.inner.ping()).await
+    }
+
+    async fn get_api_key(&self, api_key: &str) -> Result<ApiKey> {
+        self.observe("get_api_key", self.inner.get_api_key(api_key))
+            .await
+    }
+
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.observe("create_api_key", self.inner.create_api_key(api_key))
+            .await
+    }
+
+    async fn get_api_key_by_id(&self, id: &str) -> Result<ApiKey> {
+        self.observe("get_api_key_by_id", self.inner.get_api_key_by_id(id))
+            .await
+    }
+
+    async fn get_api_keys_by_user_id(&self, user_id: &str) -> Result<Vec<ApiKey>> {
+        self.observe(
+            "get_api_keys_by_user_id",
+            self.inner.get_api_keys_by_user_id(user_id),
+        )
+        .await
+    }
+
+    async fn update_api_key(&self, api_key: &ApiKey) -> Result<()> {
+        self.observe("update_api_key", self.inner.update_api_key(api_key))
+            .await
+    }
+
+    async fn get_user_by_id(&self, id: &str) -> Result<UserModel> {
+        self.observe("get_user_by_id", self.inner.get_user_by_id(id))
+            .await
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<UserModel> {
+        self.observe(
+            "get_user_by_username",
+            self.inner.get_user_by_username(username),
+        )
+        .await
+    }
+
+    async fn get_user_by_id_including_deleted(&self, id: &str) -> Result<UserModel> {
+        self.observe(
+            "get_user_by_id_including_deleted",
+            self.inner.get_user_by_id_including_deleted(id),
+        )
+        .await
+    }
+
+    async fn get_user_by_username_including_deleted(&self, username: &str) -> Result<UserModel> {
+        self.observe(
+            "get_user_by_username_including_deleted",
+            self.inner.get_user_by_username_including_deleted(username),
+        )
+        .await
+    }
+
+    async fn get_users_by_ids(&self, ids: &[String]) -> Result<Vec<UserModel>> {
+        self.observe("get_users_by_ids", self.inner.get_users_by_ids(ids))
+            .await
+    }
+
+    async fn claim_user(&self, id: &str, claimant_id: &str) -> Result<UserModel> {
+        self.observe("claim_user", self.inner.claim_user(id, claimant_id))
+            .await
+    }
+
+    async fn create_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.observe(
+            "create_profile_claim",
+            self.inner.create_profile_claim(claim),
+        )
+        .await
+    }
+
+    async fn get_profile_claim_by_id(&self, id: &str) -> Result<ProfileClaim> {
+        self.observe(
+            "get_profile_claim_by_id",
+            self.inner.get_profile_claim_by_id(id),
+        )
+        .await
+    }
+
+    async fn get_profile_claims_by_user_id(&self, user_id: &str) -> Result<Vec<ProfileClaim>> {
+        self.observe(
+            "get_profile_claims_by_user_id",
+            self.inner.get_profile_claims_by_user_id(user_id),
+        )
+        .await
+    }
+
+    async fn update_profile_claim(&self, claim: &ProfileClaim) -> Result<()> {
+        self.observe(
+            "update_profile_claim",
+            self.inner.update_profile_claim(claim),
+        )
+        .await
+    }
+
+    async fn create_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.observe(
+            "create_calendar_feed",
+            self.inner.create_calendar_feed(feed),
+        )
+        .await
+    }
+
+    async fn get_calendar_feed_by_id(&self, id: &str) -> Result<CalendarFeed> {
+        self.observe(
+            "get_calendar_feed_by_id",
+            self.inner.get_calendar_feed_by_id(id),
+        )
+        .await
+    }
+
+    async fn get_calendar_feed_by_token_hash(&self, token_hash: &str) -> Result<CalendarFeed> {
+        self.observe(
+            "get_calendar_feed_by_token_hash",
+            self.inner.get_calendar_feed_by_token_hash(token_hash),
+        )
+        .await
+    }
+
+    async fn get_calendar_feeds_by_performer_id(
+        &self,
+        performer_id: &str,
+    ) -> Result<Vec<CalendarFeed>> {
+        self.observe(
+            "get_calendar_feeds_by_performer_id",
+            self.inner.get_calendar_feeds_by_performer_id(performer_id),
+        )
+        .await
+    }
+
+    async fn update_calendar_feed(&self, feed: &CalendarFeed) -> Result<()> {
+        self.observe(
+            "update_calendar_feed",
+            self.inner.update_calendar_feed(feed),
+        )
+        .await
+    }
+
+    async fn create_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.observe("create_export_job", self.inner.create_export_job(job))
+            .await
+    }
+
+    async fn get_export_job_by_id(&self, id: &str) -> Result<ExportJob> {
+        self.observe("get_export_job_by_id", self.inner.get_export_job_by_id(id))
+            .await
+    }
+
+    async fn update_export_job(&self, job: &ExportJob) -> Result<()> {
+        self.observe("update_export_job", self.inner.update_export_job(job))
+            .await
+    }
+
+    async fn get_export_jobs_created_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<ExportJob>> {
+        self.observe(
+            "get_export_jobs_created_before",
+            self.inner.get_export_jobs_created_before(cutoff),
+        )
+        .await
+    }
+
+    async fn delete_export_job(&self, id: &str) -> Result<()> {
+        self.observe("delete_export_job", self.inner.delete_export_job(id))
+            .await
+    }
+
+    async fn get_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_bookings_by_performer_id",
+            self.inner.get_bookings_by_performer_id(performer_id),
+        )
+        .await
+    }
+
+    async fn get_open_bookings_by_performer_id(&self, performer_id: &str) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_open_bookings_by_performer_id",
+            self.inner.get_open_bookings_by_performer_id(performer_id),
+        )
+        .await
+    }
+
+    async fn get_bookings_by_booker_id(&self, booker_id: &str) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_bookings_by_booker_id",
+            self.inner.get_bookings_by_booker_id(booker_id),
+        )
+        .await
+    }
+
+    async fn get_bookings_by_performer_ids(
+        &self,
+        performer_ids: &[String],
+    ) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_bookings_by_performer_ids",
+            self.inner.get_bookings_by_performer_ids(performer_ids),
+        )
+        .await
+    }
+
+    async fn get_bookings_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_bookings_by_booker_ids",
+            self.inner.get_bookings_by_booker_ids(booker_ids),
+        )
+        .await
+    }
+
+    async fn get_booking_by_id(&self, id: &str) -> Result<Booking> {
+        self.observe("get_booking_by_id", self.inner.get_booking_by_id(id))
+            .await
+    }
+
+    async fn create_booking(&self, booking: &Booking) -> Result<()> {
+        self.observe("create_booking", self.inner.create_booking(booking))
+            .await
+    }
+
+    async fn get_bookings_by_event_id(&self, event_id: &str) -> Result<Vec<Booking>> {
+        self.observe(
+            "get_bookings_by_event_id",
+            self.inner.get_bookings_by_event_id(event_id),
+        )
+        .await
+    }
+
+    async fn get_event_by_id(&self, id: &str) -> Result<Event> {
+        self.observe("get_event_by_id", self.inner.get_event_by_id(id))
+            .await
+    }
+
+    async fn get_events_by_start_time(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.observe(
+            "get_events_by_start_time",
+            self.inner.get_events_by_start_time(from, to),
+        )
+        .await
+    }
+
+    async fn get_reviews_by_performer_id(&self, performer_id: &str) -> Result<Vec<Review>> {
+        self.observe(
+            "get_reviews_by_performer_id",
+            self.inner.get_reviews_by_performer_id(performer_id),
+        )
+        .await
+    }
+
+    async fn get_reviews_by_booker_id(&self, booker_id: &str) -> Result<Vec<Review>> {
+        self.observe(
+            "get_reviews_by_booker_id",
+            self.inner.get_reviews_by_booker_id(booker_id),
+        )
+        .await
+    }
+
+    async fn get_reviews_by_performer_ids(&self, performer_ids: &[String]) -> Result<Vec<Review>> {
+        self.observe(
+            "get_reviews_by_performer_ids",
+            self.inner.get_reviews_by_performer_ids(performer_ids),
+        )
+        .await
+    }
+
+    async fn get_reviews_by_booker_ids(&self, booker_ids: &[String]) -> Result<Vec<Review>> {
+        self.observe(
+            "get_reviews_by_booker_ids",
+            self.inner.get_reviews_by_booker_ids(booker_ids),
+        )
+        .await
+    }
+
+    async fn get_reviews_by_booking_id(&self, booking_id: &str) -> Result<Vec<Review>> {
+        self.observe(
+            "get_reviews_by_booking_id",
+            self.inner.get_reviews_by_booking_id(booking_id),
+        )
+        .await
+    }
+
+    async fn create_review(&self, review: &Review) -> Result<()> {
+        self.observe("create_review", self.inner.create_review(review))
+            .await
+    }
+
+    async fn create_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.observe("create_webhook", self.inner.create_webhook(webhook))
+            .await
+    }
+
+    async fn get_webhook_by_id(&self, id: &str) -> Result<Webhook> {
+        self.observe("get_webhook_by_id", self.inner.get_webhook_by_id(id))
+            .await
+    }
+
+    async fn get_webhooks_by_owner_id(&self, owner_id: &str) -> Result<Vec<Webhook>> {
+        self.observe(
+            "get_webhooks_by_owner_id",
+            self.inner.get_webhooks_by_owner_id(owner_id),
+        )
+        .await
+    }
+
+    async fn get_webhooks_by_event_type(
+        &self,
+        event_type: WebhookEventType,
+    ) -> Result<Vec<Webhook>> {
+        self.observe(
+            "get_webhooks_by_event_type",
+            self.inner.get_webhooks_by_event_type(event_type),
+        )
+        .await
+    }
+
+    async fn update_webhook(&self, webhook: &Webhook) -> Result<()> {
+        self.observe("update_webhook", self.inner.update_webhook(webhook))
+            .await
+    }
+
+    async fn delete_webhook(&self, id: &str) -> Result<()> {
+        self.observe("delete_webhook", self.inner.delete_webhook(id))
+            .await
+    }
+
+    async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        self.observe(
+            "create_webhook_delivery",
+            self.inner.create_webhook_delivery(delivery),
+        )
+        .await
+    }
+
+    async fn upsert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.observe(
+            "upsert_webhook_delivery",
+            self.inner.upsert_webhook_delivery(delivery),
+        )
+        .await
+    }
+
+    async fn claim_webhook_delivery(
+        &self,
+        stalled: &WebhookDelivery,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebhookDelivery>> {
+        self.observe(
+            "claim_webhook_delivery",
+            self.inner.claim_webhook_delivery(stalled, now),
+        )
+        .await
+    }
+
+    async fn get_webhook_delivery_by_id(&self, id: &str) -> Result<WebhookDelivery> {
+        self.observe(
+            "get_webhook_delivery_by_id",
+            self.inner.get_webhook_delivery_by_id(id),
+        )
+        .await
+    }
+
+    async fn get_webhook_deliveries_by_webhook_id(
+        &self,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>> {
+        self.observe(
+            "get_webhook_deliveries_by_webhook_id",
+            self.inner.get_webhook_deliveries_by_webhook_id(webhook_id),
+        )
+        .await
+    }
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        self.observe(
+            "get_pending_webhook_deliveries",
+            self.inner.get_pending_webhook_deliveries(),
+        )
+        .await
+    }
+}
+
+/// Times every search against the wrapped backend.
+#[derive(Clone)]
+pub struct MeteredSearch {
+    inner: Arc<dyn Search>,
+    backend: &'static str,
+    metrics: Metrics,
+}
+
+impl MeteredSearch {
+    pub fn new(inner: Arc<dyn Search>, backend: &'static str, metrics: Metrics) -> Self {
+        Self {
+            inner,
+            backend,
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl Search for MeteredSearch {
+    async fn ping(&self) -> Result<()> {
+        self.inner.ping().await
+    }
+
+    async fn search_users(
+        &self,
+        query: String,
+        options: UserSearchOptions,
+    ) -> Result<Vec<UserModel>> {
+        let started = Instant::now();
+        let result = self.inner.search_users(query, options).await;
+        self.metrics
+            .observe_search(self.backend, result.is_ok(), started.elapsed());
+
+        result
+    }
+}
@@ -1,2 +1,10 @@
+pub mod cache;
+pub mod change_feed;
 pub mod database;
+pub mod export_files;
+pub mod fixtures;
+pub mod mailer;
+pub mod memory;
+pub mod metered;
 pub mod search;
+pub mod soft_delete;
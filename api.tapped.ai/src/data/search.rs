@@ -1,14 +1,65 @@
-use crate::domain::models::user::UserModel;
+use crate::{
+    configuration::AlgoliaSettings, data::memory::InMemoryDatabase, domain::models::user::UserModel,
+};
 use algoliasearch::{index::AroundRadius, Client, SearchQueryBuilder};
 use anyhow::Result;
 use axum::async_trait;
+use firestore::{FirestoreDb, FirestoreQueryDirection, FirestoreResult};
+use futures::{future, stream::BoxStream, StreamExt, TryStreamExt};
 use std::collections::HashSet;
 use tracing::instrument;
 
+/// Tech-rider filters. Legacy venues only describe their kit in free text,
+/// which no backend can filter on, so these are applied to the parsed rider
+/// after the search.
+#[derive(Debug, Clone, Default)]
+pub struct TechRiderFilters {
+    pub in_ear_monitors: Option<bool>,
+    pub min_channels: Option<u32>,
+    pub min_monitor_mixes: Option<u32>,
+    pub backline: Option<Vec<String>>,
+}
+
+impl TechRiderFilters {
+    fn is_empty(&self) -> bool {
+        self.in_ear_monitors.is_none()
+            && self.min_channels.is_none()
+            && self.min_monitor_mixes.is_none()
+            && self.backline.is_none()
+    }
+
+    /// Users without a rider, i.e. anyone but venues, only match when no
+    /// filter is set.
+    pub fn matches(&self, user: &UserModel) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let Some(rider) = user.tech_rider() else {
+            return false;
+        };
+
+        self.in_ear_monitors
+            .is_none_or(|wanted| rider.in_ear_monitors == wanted)
+            && self
+                .min_channels
+                .is_none_or(|min| rider.channels.is_some_and(|channels| channels >= min))
+            && self
+                .min_monitor_mixes
+                .is_none_or(|min| rider.monitor_mixes.is_some_and(|mixes| mixes >= min))
+            && self
+                .backline
+                .as_ref()
+                .is_none_or(|backline| rider.has_backline(backline))
+    }
+}
+
 #[derive(Debug, Default, Builder)]
 pub struct UserSearchOptions {
     #[builder(default)]
     hits_per_page: Option<u64>,
+    /// Zero-based page of results to return.
+    #[builder(default)]
+    page: Option<u64>,
     #[builder(default)]
     labels: Option<Vec<String>>,
     #[builder(default)]
@@ -31,10 +82,14 @@ pub struct UserSearchOptions {
     min_capacity: Option<u32>,
     #[builder(default)]
     max_capacity: Option<u32>,
+    #[builder(default)]
+    tech_rider: TechRiderFilters,
 }
 
 #[async_trait]
 pub trait Search: Send + Sync {
+    /// Checks that the search backend can be reached.
+    async fn ping(&self) -> Result<()>;
     async fn search_users(
         &self,
         query: String,
@@ -42,18 +97,44 @@ pub trait Search: Send + Sync {
     ) -> Result<Vec<UserModel>>;
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Algolia {}
+#[derive(Debug, Clone)]
+pub struct Algolia {
+    users_index: String,
+}
+
+impl Algolia {
+    pub fn new(settings: &AlgoliaSettings) -> Self {
+        Self {
+            users_index: settings.users_index.clone(),
+        }
+    }
+}
 
 #[async_trait]
 impl Search for Algolia {
+    #[instrument]
+    async fn ping(&self) -> Result<()> {
+        let index = Client::default().init_index::<UserModel>(&self.users_index);
+        let query = SearchQueryBuilder::default()
+            .query(String::new())
+            .hits_per_page(0)
+            .build()?;
+
+        index
+            .search(query)
+            .await
+            .map_err(|error| anyhow::anyhow!("failed to reach Algolia: {error:?}"))?;
+
+        Ok(())
+    }
+
     #[instrument]
     async fn search_users(
         &self,
         query: String,
         options: UserSearchOptions,
     ) -> Result<Vec<UserModel>> {
-        let index = Client::default().init_index::<UserModel>("prod_users");
+        let index = Client::default().init_index::<UserModel>(&self.users_index);
 
         tracing::info!("searching users from Algolia: {}", query);
 
@@ -166,6 +247,7 @@ impl Search for Algolia {
             .query(query)
             .filters(filters)
             .hits_per_page(options.hits_per_page.unwrap_or(10))
+            .page(options.page)
             .around_radius(AroundRadius::Radius(options.radius.unwrap_or(50_000)))
             .around_lat_lng(formatted_location_filter)
             .numeric_filters(numeric_filters)
@@ -176,6 +258,165 @@ impl Search for Algolia {
             .await
             .expect("failed to search users from Algolia");
 
-        Ok(response.hits)
+        Ok(response
+            .hits
+            .into_iter()
+            .filter(|user| options.tech_rider.matches(user))
+            .collect())
+    }
+}
+
+/// Searches the users of an `InMemoryDatabase`, applying the same filters as
+/// the Algolia index. Results are ordered by id.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySearch {
+    database: InMemoryDatabase,
+}
+
+impl InMemorySearch {
+    pub fn new(database: InMemoryDatabase) -> Self {
+        Self { database }
+    }
+}
+
+fn any_of(filter: &Option<Vec<String>>, values: &[String]) -> bool {
+    filter
+        .as_ref()
+        .is_none_or(|filter| filter.iter().any(|value| values.contains(value)))
+}
+
+impl UserSearchOptions {
+    fn matches(&self, query: &str, user: &UserModel) -> bool {
+        let query = query.trim().to_lowercase();
+        let matches_query = query.is_empty()
+            || [user.username.as_str(), user.display_name(), user.bio()]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&query));
+
+        let matches_labels = self.labels.as_ref().is_none_or(|labels| {
+            user.label()
+                .is_some_and(|label| labels.iter().any(|l| l == label))
+        });
+        let matches_black_list = self
+            .occupations_black_list
+            .as_ref()
+            .is_none_or(|black_list| {
+                !user
+                    .occupations()
+                    .iter()
+                    .any(|occupation| black_list.contains(occupation))
+            });
+        let matches_location = match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => user.location().is_some_and(|location| {
+                location.distance_to(lat, lng) <= self.radius.unwrap_or(50_000) as f64
+            }),
+            _ => true,
+        };
+        let matches_capacity = (self.min_capacity.is_none() && self.max_capacity.is_none())
+            || user.capacity().is_some_and(|capacity| {
+                self.min_capacity.is_none_or(|min| capacity >= min)
+                    && self.max_capacity.is_none_or(|max| capacity <= max)
+            });
+
+        !user.is_deleted()
+            && matches_query
+            && matches_labels
+            && any_of(&self.genres, user.performer_genres())
+            && any_of(&self.occupations, user.occupations())
+            && matches_black_list
+            && any_of(&self.venue_genres, user.venue_genres())
+            && self
+                .unclaimed
+                .is_none_or(|unclaimed| user.is_unclaimed() == unclaimed)
+            && matches_location
+            && matches_capacity
+            && self.tech_rider.matches(user)
+    }
+}
+
+#[async_trait]
+impl Search for InMemorySearch {
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn search_users(
+        &self,
+        query: String,
+        options: UserSearchOptions,
+    ) -> Result<Vec<UserModel>> {
+        let hits_per_page = options.hits_per_page.unwrap_or(10) as usize;
+        let page = options.page.unwrap_or(0) as usize;
+
+        Ok(self
+            .database
+            .users()
+            .into_iter()
+            .filter(|user| options.matches(&query, user))
+            .skip(page * hits_per_page)
+            .take(hits_per_page)
+            .collect())
+    }
+}
+
+/// Scans the Firestore `users` collection and applies the search filters in
+/// memory. Meant for the local emulator, where Algolia isn't available.
+#[derive(Debug, Clone)]
+pub struct FirestoreSearch {
+    db: FirestoreDb,
+}
+
+impl FirestoreSearch {
+    pub fn new(db: FirestoreDb) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl Search for FirestoreSearch {
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<()> {
+        let _: Option<UserModel> = self
+            .db
+            .fluent()
+            .select()
+            .by_id_in("users")
+            .obj()
+            .one("healthcheck")
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn search_users(
+        &self,
+        query: String,
+        options: UserSearchOptions,
+    ) -> Result<Vec<UserModel>> {
+        tracing::info!("searching users in Firestore: {}", query);
+
+        let hits_per_page = options.hits_per_page.unwrap_or(10) as usize;
+        let page = options.page.unwrap_or(0) as usize;
+
+        let object_stream: BoxStream<FirestoreResult<UserModel>> = self
+            .db
+            .fluent()
+            .select()
+            .from("users")
+            .filter(|q| q.field("deleted").eq(false))
+            .order_by([("__name__", FirestoreQueryDirection::Ascending)])
+            .obj()
+            .stream_query_with_errors()
+            .await?;
+
+        let users: Vec<UserModel> = object_stream
+            .try_filter(|user| future::ready(options.matches(&query, user)))
+            .skip(page * hits_per_page)
+            .take(hits_per_page)
+            .try_collect()
+            .await?;
+
+        Ok(users)
     }
 }
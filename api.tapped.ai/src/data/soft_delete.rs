@@ -0,0 +1,76 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::domain::models::user::UserModel;
+
+/// Returned by user reads for a user that has been soft-deleted, so callers
+/// can tell a deleted profile from one that never existed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserDeleted {
+    pub id: String,
+}
+
+impl fmt::Display for UserDeleted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user {} has been deleted", self.id)
+    }
+}
+
+impl std::error::Error for UserDeleted {}
+
+/// The soft-delete policy for single user reads: deleted users are hidden
+/// behind a `UserDeleted` error. Every `Database` applies it, and only the
+/// `*_including_deleted` reads skip it.
+pub fn visible(user: UserModel) -> Result<UserModel> {
+    if user.is_deleted() {
+        return Err(UserDeleted { id: user.id }.into());
+    }
+
+    Ok(user)
+}
+
+/// The soft-delete policy for batch user reads: deleted users are left out,
+/// like missing ones.
+pub fn without_deleted(users: Vec<UserModel>) -> Vec<UserModel> {
+    users
+        .into_iter()
+        .filter(|user| !user.is_deleted())
+        .collect()
+}
+
+/// Whether a user read failed because the user has been deleted.
+pub fn is_user_deleted(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<UserDeleted>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deleted_users_are_hidden_behind_an_error() {
+        assert_eq!(
+            "live",
+            visible(UserModel::fixture("live", json!({}))).unwrap().id
+        );
+
+        let error = visible(UserModel::fixture("gone", json!({ "deleted": true }))).unwrap_err();
+        assert!(is_user_deleted(&error));
+        assert_eq!("user gone has been deleted", error.to_string());
+        assert!(!is_user_deleted(&anyhow::anyhow!("user not found")));
+    }
+
+    #[test]
+    fn deleted_users_are_left_out_of_batches() {
+        let users = without_deleted(vec![
+            UserModel::fixture("a", json!({})),
+            UserModel::fixture("b", json!({ "deleted": true })),
+            UserModel::fixture("c", json!({})),
+        ]);
+
+        let ids: Vec<_> = users.iter().map(|user| user.id.as_str()).collect();
+        assert_eq!(vec!["a", "c"], ids);
+    }
+}
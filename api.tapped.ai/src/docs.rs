@@ -10,9 +10,13 @@ use aide::{
     redoc::Redoc,
     scalar::Scalar,
 };
-use axum::{response::IntoResponse, Extension};
+use axum::{http::header::CONTENT_TYPE, response::IntoResponse, routing, Extension};
 
-pub fn docs_routes(state: AppStateDyn) -> ApiRouter {
+/// `/v1` predates the generated docs, so its document is maintained by hand.
+const V1_OPENAPI: &str = include_str!("openapi.json");
+
+/// `v2_api` is generated from the typed `/v2` routes.
+pub fn docs_routes(state: AppStateDyn, v2_api: Arc<OpenApi>) -> ApiRouter {
     // We infer the return types for these routes
     // as an example.
     //
@@ -42,7 +46,25 @@ pub fn docs_routes(state: AppStateDyn) -> ApiRouter {
             ),
             |p| p.security_requirement("ApiKey"),
         )
+        .api_route_with(
+            "/v2",
+            get_with(
+                Scalar::new("/docs/v2/openapi.json")
+                    .with_title("Tapped API v2 docs")
+                    .axum_handler(),
+                |op| op.description("The /v2 documentation page."),
+            ),
+            |p| p.security_requirement("ApiKey"),
+        )
         .route("/private/api.json", get(serve_docs))
+        .route(
+            "/v1/openapi.json",
+            routing::get(|| async { ([(CONTENT_TYPE, "application/json")], V1_OPENAPI) }),
+        )
+        .route(
+            "/v2/openapi.json",
+            routing::get(move || async move { Json(v2_api).into_response() }),
+        )
         .with_state(state);
 
     // Afterwards we disable response inference because
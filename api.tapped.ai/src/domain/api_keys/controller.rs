@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+
+use crate::{
+    domain::{
+        auth::Caller,
+        models::api_key::{ApiKey, ApiScope, GuardedApiKey},
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueApiKeyRequest {
+    name: Option<String>,
+    #[serde(default)]
+    scopes: Vec<ApiScope>,
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    allowed_origins: Vec<String>,
+}
+
+/// The key is only ever returned when it is issued or rotated.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    api_key: GuardedApiKey,
+    key: String,
+}
+
+impl From<ApiKey> for IssuedApiKey {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            api_key: api_key.to_guarded(),
+            key: api_key.key,
+        }
+    }
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("tapped_{}", hex::encode(bytes))
+}
+
+async fn find_api_key(state: &AppStateDyn, id: &str) -> Result<ApiKey, AppError> {
+    state.database.get_api_key_by_id(id).await.map_err(|error| {
+        tracing::warn!("{error}");
+        AppError::new("api key not found").with_status(StatusCode::NOT_FOUND)
+    })
+}
+
+pub async fn issue_api_key(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(user_id): Path<String>,
+    Json(request): Json<IssueApiKeyRequest>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), AppError> {
+    let now = Utc::now();
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err(AppError::new("expiresAt must be in the future"));
+    }
+    state
+        .database
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|error| {
+            tracing::warn!("{error}");
+            AppError::user_not_found("user", &error)
+        })?;
+
+    let api_key = ApiKey {
+        key: generate_key(),
+        id: Uuid::new_v4().to_string(),
+        user_id,
+        name: request.name,
+        scopes: request.scopes,
+        allowed_origins: request.allowed_origins,
+        timestamp: now,
+        expires_at: request.expires_at,
+        revoked_at: None,
+        ..Default::default()
+    }
+    .hashed();
+    state
+        .database
+        .create_api_key(&api_key)
+        .await
+        .map_err(internal_error)?;
+    tracing::info!(
+        "{} issued API key '{}' to {}",
+        caller.user_id,
+        api_key.id,
+        api_key.user_id
+    );
+
+    Ok((StatusCode::CREATED, Json(api_key.into())))
+}
+
+/// Every key the user has been issued, revoked ones included.
+pub async fn list_api_keys(
+    State(state): State<AppStateDyn>,
+    Path(user_id): Path<String>,
+) -> Result<Json<Vec<GuardedApiKey>>, AppError> {
+    let mut api_keys = state
+        .database
+        .get_api_keys_by_user_id(&user_id)
+        .await
+        .map_err(internal_error)?;
+    api_keys.sort_by_key(|api_key| Reverse(api_key.timestamp));
+
+    Ok(Json(
+        api_keys
+            .iter()
+            .map(|api_key| api_key.to_guarded())
+            .collect(),
+    ))
+}
+
+/// Issues a replacement with the same owner, scopes and expiry, and revokes
+/// the old key straight away.
+pub async fn rotate_api_key(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), AppError> {
+    let mut old = find_api_key(&state, &id).await?;
+    if old.is_revoked() {
+        return Err(
+            AppError::new("revoked keys can't be rotated").with_status(StatusCode::CONFLICT)
+        );
+    }
+
+    let now = Utc::now();
+    let new = ApiKey {
+        key: generate_key(),
+        id: Uuid::new_v4().to_string(),
+        timestamp: now,
+        revoked_at: None,
+        ..old.clone()
+    }
+    .hashed();
+    state
+        .database
+        .create_api_key(&new)
+        .await
+        .map_err(internal_error)?;
+
+    old.revoked_at = Some(now);
+    state
+        .database
+        .update_api_key(&old)
+        .await
+        .map_err(internal_error)?;
+    tracing::info!(
+        "{} rotated API key '{}' to '{}'",
+        caller.user_id,
+        old.id,
+        new.id
+    );
+
+    Ok((StatusCode::CREATED, Json(new.into())))
+}
+
+/// Revoking is idempotent; the key is kept so it still shows up in the list.
+pub async fn revoke_api_key(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut api_key = find_api_key(&state, &id).await?;
+    if !api_key.is_revoked() {
+        api_key.revoked_at = Some(Utc::now());
+        state
+            .database
+            .update_api_key(&api_key)
+            .await
+            .map_err(internal_error)?;
+        tracing::info!("{} revoked API key '{}'", caller.user_id, api_key.id);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,14 +1,32 @@
-use crate::state::AppStateDyn;
+use crate::{domain::models::api_key::ApiScope, state::AppStateDyn};
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{header::ORIGIN, StatusCode},
     middleware::Next,
     response::Response,
+    Extension,
 };
+use chrono::Utc;
+
+/// The owner of the API key a request was authenticated with. Inserted into
+/// the request extensions by [`verify_api_token`].
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// The id of the key itself, which its rate limit is kept under.
+    pub api_key_id: String,
+    pub user_id: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl Caller {
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
 
 pub async fn verify_api_token(
     State(state): State<AppStateDyn>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let auth_header = req
@@ -18,20 +36,76 @@ pub async fn verify_api_token(
 
     match auth_header {
         Some(api_key) => {
-            let user_id = state
-                .database
-                .get_user_from_api_key(api_key)
-                .await
-                .map_err(|err| {
-                    tracing::error!("error verifying API key: {:?}", err);
-                    StatusCode::UNAUTHORIZED
-                })?;
+            let api_key = state.database.get_api_key(api_key).await.map_err(|err| {
+                tracing::error!("error verifying API key: {:?}", err);
+                state.metrics.record_auth_failure("invalid_key");
+                StatusCode::UNAUTHORIZED
+            })?;
+
+            if api_key.is_revoked() {
+                tracing::warn!("revoked API key used: '{}'", api_key.id);
+                state.metrics.record_auth_failure("revoked_key");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            if api_key.is_expired(Utc::now()) {
+                tracing::warn!("expired API key used: '{}'", api_key.id);
+                state.metrics.record_auth_failure("expired_key");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
 
+            // Keys restricted to some origins can't be used from any other
+            // site, even one allowed by the global CORS policy.
+            let origin = req
+                .headers()
+                .get(ORIGIN)
+                .and_then(|origin| origin.to_str().ok());
+            if let Some(origin) = origin {
+                if !api_key.allowed_origins.is_empty()
+                    && !api_key
+                        .allowed_origins
+                        .iter()
+                        .any(|allowed| allowed == origin)
+                {
+                    tracing::warn!("API key used from disallowed origin {origin}");
+                    state.metrics.record_auth_failure("origin_not_allowed");
+                    return Err(StatusCode::FORBIDDEN);
+                }
+            }
+
+            let user_id = api_key.user_id;
             tracing::info!("User ID: {:?}", user_id);
+            req.extensions_mut().insert(Caller {
+                api_key_id: api_key.id,
+                user_id,
+                scopes: api_key.scopes,
+            });
 
             let res = next.run(req).await;
             Ok(res)
         }
-        None => Err(StatusCode::UNAUTHORIZED),
+        None => {
+            state.metrics.record_auth_failure("missing_key");
+            Err(StatusCode::UNAUTHORIZED)
+        }
     }
 }
+
+/// A `route_layer` for routes only `admin` keys may call. Must run after
+/// [`verify_api_token`].
+pub async fn require_admin(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !caller.has_scope(ApiScope::Admin) {
+        tracing::warn!(
+            "{} called an admin route without the admin scope",
+            caller.user_id
+        );
+        state.metrics.record_auth_failure("missing_scope");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(next.run(req).await)
+}
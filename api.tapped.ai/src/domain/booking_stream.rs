@@ -0,0 +1,110 @@
+use std::convert::Infallible;
+
+use crate::{
+    data::change_feed::BookingChange,
+    domain::{auth::Caller, models::api_key::ApiScope},
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Extension,
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingStreamParams {
+    performer_id: Option<String>,
+    venue_id: Option<String>,
+    booker_id: Option<String>,
+}
+
+impl BookingStreamParams {
+    /// Whether every matching booking is one `user_id` is a party to.
+    fn is_limited_to(&self, user_id: &str) -> bool {
+        [&self.performer_id, &self.venue_id, &self.booker_id]
+            .into_iter()
+            .any(|id| id.as_deref() == Some(user_id))
+    }
+
+    fn matches(&self, change: &BookingChange) -> bool {
+        let booking = &change.booking;
+
+        self.performer_id
+            .as_ref()
+            .is_none_or(|id| &booking.performer_id == id)
+            && self
+                .venue_id
+                .as_ref()
+                .is_none_or(|id| booking.venue_id.as_ref() == Some(id))
+            && self
+                .booker_id
+                .as_ref()
+                .is_none_or(|id| booking.booker_id.as_ref() == Some(id))
+    }
+}
+
+/// Bookings carry their rate and note, so without the `bookings:read` scope
+/// a key can only follow bookings its user is the performer, venue or
+/// booker of, and has to filter by that.
+pub async fn stream_bookings(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<BookingStreamParams>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    if !caller.has_scope(ApiScope::BookingsRead) && !params.is_limited_to(&caller.user_id) {
+        tracing::warn!(
+            "{} streamed bookings they aren't a party to without the bookings:read scope",
+            caller.user_id
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .map(|header| {
+            header
+                .to_str()
+                .ok()
+                .and_then(|id| id.parse::<u64>().ok())
+                .ok_or(StatusCode::BAD_REQUEST)
+        })
+        .transpose()?;
+
+    tracing::info!(
+        "streaming booking changes with {:?} after {:?}",
+        params,
+        last_event_id
+    );
+
+    let changes = state
+        .change_feed
+        .subscribe(last_event_id)
+        .await
+        .map_err(|error| {
+            tracing::error!("{error}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let events = changes
+        .filter(move |change| futures::future::ready(params.matches(change)))
+        .filter_map(|change| async move {
+            match Event::default()
+                .id(change.id.to_string())
+                .event(change.event_type())
+                .json_data(&change)
+            {
+                Ok(event) => Some(Ok(event)),
+                Err(error) => {
+                    tracing::error!("failed to serialize booking change: {error}");
+                    None
+                }
+            }
+        });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
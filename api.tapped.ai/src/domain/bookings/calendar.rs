@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::models::booking::Booking;
+
+/// RFC 5545 lines should be folded once they pass 75 octets.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A confirmed gig as it appears in the feed. `venue` is the venue's display
+/// name, when the booking names one.
+pub struct Gig<'a> {
+    pub booking: &'a Booking,
+    pub venue: Option<&'a str>,
+}
+
+/// Renders an iCalendar (RFC 5545) feed of `gigs`.
+pub fn render(calendar_name: &str, gigs: &[Gig]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Tapped//Tapped API//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(calendar_name)),
+    ];
+
+    for gig in gigs {
+        let booking = gig.booking;
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@tapped.ai", escape(&booking.id)));
+        lines.push(format!("DTSTAMP:{}", timestamp(booking.timestamp)));
+        lines.push(format!("DTSTART:{}", timestamp(booking.start_time)));
+        lines.push(format!("DTEND:{}", timestamp(booking.end_time)));
+        lines.push(format!("SUMMARY:{}", escape(&booking.name)));
+        if !booking.note.trim().is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&booking.note)));
+        }
+        if let Some(venue) = gig.venue {
+            lines.push(format!("LOCATION:{}", escape(venue)));
+        }
+        if let Some(location) = &booking.location {
+            lines.push(format!("GEO:{:.6};{:.6}", location.lat, location.lng));
+        }
+        if let Some(url) = &booking.event_url {
+            // A URI rather than text, so it can't be escaped, only kept on
+            // its line.
+            lines.push(format!("URL:{}", without_controls(url)));
+        }
+        lines.push("STATUS:CONFIRMED".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// UTC date-times in the basic format, e.g. `20240601T200000Z`.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes a TEXT value. Line breaks become `\n`, and other control
+/// characters are dropped so nothing can start a line of its own.
+fn escape(text: &str) -> String {
+    without_controls(
+        &text
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\\n")
+            .replace(['\n', '\r'], "\\n"),
+    )
+}
+
+fn without_controls(value: &str) -> String {
+    value.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Splits `line` into CRLF-terminated chunks of at most 75 octets, each
+/// continuation starting with a space. Never splits a UTF-8 character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::user::Location;
+    use chrono::TimeZone;
+
+    fn booking() -> Booking {
+        Booking {
+            id: "gig".into(),
+            name: "Late show; doors, 9pm".into(),
+            note: "bring\nearplugs".into(),
+            location: Some(Location {
+                place_id: "place".into(),
+                lat: 40.7,
+                lng: -74.0,
+            }),
+            start_time: Utc.with_ymd_and_hms(2024, 6, 1, 21, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2024, 6, 1, 23, 30, 0).unwrap(),
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            event_url: Some("https://example.com/late-show".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_gigs_as_events() {
+        let booking = booking();
+        let ics = render(
+            "Performer",
+            &[Gig {
+                booking: &booking,
+                venue: Some("The Venue"),
+            }],
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        for line in [
+            "UID:gig@tapped.ai",
+            "DTSTAMP:20240501T120000Z",
+            "DTSTART:20240601T210000Z",
+            "DTEND:20240601T233000Z",
+            "SUMMARY:Late show\\; doors\\, 9pm",
+            "DESCRIPTION:bring\\nearplugs",
+            "LOCATION:The Venue",
+            "GEO:40.700000;-74.000000",
+            "URL:https://example.com/late-show",
+        ] {
+            assert!(ics.contains(&format!("\r\n{line}\r\n")), "{line}");
+        }
+    }
+
+    #[test]
+    fn values_cannot_add_properties() {
+        let booking = Booking {
+            id: "gig\r\nATTENDEE:mailto:x@example.com".into(),
+            name: "Show\u{b}".into(),
+            event_url: Some("https://example.com/\r\nSTATUS:CANCELLED".into()),
+            ..booking()
+        };
+        let ics = render(
+            "Performer",
+            &[Gig {
+                booking: &booking,
+                venue: None,
+            }],
+        );
+
+        assert!(ics.contains("\r\nUID:gig\\nATTENDEE:mailto:x@example.com@tapped.ai\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Show\r\n"));
+        assert!(ics.contains("\r\nURL:https://example.com/STATUS:CANCELLED\r\n"));
+        assert!(!ics.contains("\r\nATTENDEE"));
+        assert!(!ics.contains("\r\nSTATUS:CANCELLED"));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold(&line);
+
+        for chunk in folded.split("\r\n").filter(|chunk| !chunk.is_empty()) {
+            assert!(chunk.len() <= MAX_LINE_OCTETS);
+        }
+        assert_eq!(line, folded.replace("\r\n ", "").trim_end());
+    }
+}
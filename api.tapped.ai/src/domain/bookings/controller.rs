@@ -0,0 +1,457 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    domain::{
+        auth::Caller,
+        bookings::{
+            calendar::{self, Gig},
+            schedule::{busy, conflicts, TimeSlot},
+        },
+        models::{
+            api_key::ApiScope,
+            booking::{Booking, BookingStatus, GuardedBooking},
+            calendar_feed::{CalendarFeed, GuardedCalendarFeed},
+            user::Location,
+        },
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// The most a single booking may be requested for.
+pub const MAX_RATE: f64 = 1_000_000.0;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBookingRequest {
+    performer_id: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    rate: f64,
+    location: Option<Location>,
+    venue_id: Option<String>,
+    flier_url: Option<String>,
+    event_url: Option<String>,
+}
+
+/// How far ahead `/availability` looks when no `to` is given.
+pub const DEFAULT_WINDOW_DAYS: i64 = 30;
+/// The widest window a single `/availability` request may cover.
+pub const MAX_WINDOW_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityParams {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyInterval {
+    start_time: String,
+    end_time: String,
+    status: BookingStatus,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Availability {
+    performer_id: String,
+    from: String,
+    to: String,
+    busy: Vec<BusyInterval>,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn validate_rate(rate: f64) -> Result<(), AppError> {
+    if !rate.is_finite() || !(0.0..=MAX_RATE).contains(&rate) {
+        return Err(AppError::new(&format!(
+            "rate must be between 0 and {MAX_RATE}"
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_location(location: &Location) -> Result<(), AppError> {
+    if location.place_id.trim().is_empty() {
+        return Err(AppError::new("location.placeId is required"));
+    }
+    if !(-90.0..=90.0).contains(&location.lat) || !(-180.0..=180.0).contains(&location.lng) {
+        return Err(AppError::new("location is not a valid coordinate"));
+    }
+
+    Ok(())
+}
+
+/// Creates a pending booking request for the caller. Confirming it is up to
+/// the performer, outside the API.
+pub async fn create_booking(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<CreateBookingRequest>,
+) -> Result<(StatusCode, Json<GuardedBooking>), AppError> {
+    if request.title.trim().is_empty() {
+        return Err(AppError::new("title is required"));
+    }
+    let slot = TimeSlot::new(request.start_time, request.end_time)
+        .ok_or_else(|| AppError::new("endTime must be after startTime"))?;
+    if slot.start <= Utc::now() {
+        return Err(AppError::new("startTime must be in the future"));
+    }
+    validate_rate(request.rate)?;
+    if let Some(location) = &request.location {
+        validate_location(location)?;
+    }
+    if request.performer_id == caller.user_id {
+        return Err(AppError::new("performers can't book themselves"));
+    }
+
+    state
+        .database
+        .get_user_by_id(&request.performer_id)
+        .await
+        .map_err(performer_not_found)?;
+    if let Some(venue_id) = &request.venue_id {
+        let venue = state.database.get_user_by_id(venue_id).await.ok();
+        if !venue.is_some_and(|venue| venue.is_venue()) {
+            return Err(AppError::new("venueId is not a venue"));
+        }
+    }
+
+    let bookings = state
+        .database
+        .get_bookings_by_performer_id(&request.performer_id)
+        .await
+        .map_err(internal_error)?;
+    let conflicting = conflicts(&bookings, &slot);
+    if !conflicting.is_empty() {
+        // Only the times are shared; the other bookings aren't the caller's.
+        let taken: Vec<_> = conflicting
+            .iter()
+            .map(|booking| {
+                json!({
+                    "startTime": booking.start_time.to_rfc3339(),
+                    "endTime": booking.end_time.to_rfc3339(),
+                })
+            })
+            .collect();
+
+        return Err(
+            AppError::new("the performer is already booked at that time")
+                .with_status(StatusCode::CONFLICT)
+                .with_details(json!({ "conflicts": taken })),
+        );
+    }
+
+    let booking = Booking {
+        id: Uuid::new_v4().to_string(),
+        name: request.title.trim().to_string(),
+        note: request.description,
+        requester_id: Some(caller.user_id),
+        requestee_id: request.performer_id,
+        status: BookingStatus::Pending,
+        rate: request.rate,
+        location: request.location,
+        start_time: slot.start,
+        end_time: slot.end,
+        timestamp: Utc::now(),
+        flier_url: request.flier_url,
+        event_url: request.event_url,
+        venue_id: request.venue_id,
+        reference_event_id: None,
+    };
+    state
+        .database
+        .create_booking(&booking)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((StatusCode::CREATED, Json(booking.to_guarded())))
+}
+
+fn performer_not_found(error: anyhow::Error) -> AppError {
+    tracing::warn!("{error}");
+    AppError::user_not_found("performer", &error)
+}
+
+/// When the performer is busy between `from` (default now) and `to`
+/// (default 30 days later). Only the times are shared, not who booked them.
+pub async fn get_availability(
+    State(state): State<AppStateDyn>,
+    Path(id): Path<String>,
+    Query(params): Query<AvailabilityParams>,
+) -> Result<Json<Availability>, AppError> {
+    let from = params.from.unwrap_or_else(Utc::now);
+    let to = params
+        .to
+        .unwrap_or_else(|| from + Duration::days(DEFAULT_WINDOW_DAYS));
+    let window = TimeSlot::new(from, to).ok_or_else(|| AppError::new("to must be after from"))?;
+    if window.end - window.start > Duration::days(MAX_WINDOW_DAYS) {
+        return Err(AppError::new(&format!(
+            "the window can span at most {MAX_WINDOW_DAYS} days"
+        )));
+    }
+
+    state
+        .database
+        .get_user_by_id(&id)
+        .await
+        .map_err(performer_not_found)?;
+    let bookings = state
+        .database
+        .get_open_bookings_by_performer_id(&id)
+        .await
+        .map_err(internal_error)?;
+
+    let busy = busy(&bookings, &window)
+        .into_iter()
+        .map(|booking| BusyInterval {
+            start_time: booking.start_time.to_rfc3339(),
+            end_time: booking.end_time.to_rfc3339(),
+            status: booking.status,
+        })
+        .collect();
+
+    Ok(Json(Availability {
+        performer_id: id,
+        from: window.start.to_rfc3339(),
+        to: window.end.to_rfc3339(),
+        busy,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarParams {
+    token: Option<String>,
+}
+
+/// Returned once, when the feed is issued. The token can't be read back.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedCalendarFeed {
+    #[serde(flatten)]
+    feed: GuardedCalendarFeed,
+    token: String,
+    /// The path to subscribe to, token included.
+    url: String,
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hex::encode(bytes)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn calendar_feed_not_found() -> AppError {
+    AppError::new("calendar feed not found").with_status(StatusCode::NOT_FOUND)
+}
+
+/// Issues a link to the performer's calendar feed that calendar apps can
+/// subscribe to without an API key.
+pub async fn issue_calendar_feed(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<IssuedCalendarFeed>), AppError> {
+    let performer = state
+        .database
+        .get_user_by_id(&id)
+        .await
+        .map_err(performer_not_found)?;
+
+    let token = generate_token();
+    let feed = CalendarFeed {
+        id: Uuid::new_v4().to_string(),
+        performer_id: performer.id,
+        owner_id: caller.user_id,
+        token_hash: hash_token(&token),
+        timestamp: Utc::now(),
+        revoked_at: None,
+    };
+    state
+        .database
+        .create_calendar_feed(&feed)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedCalendarFeed {
+            url: format!(
+                "/v1/performer/{}/calendar.ics?token={token}",
+                feed.performer_id
+            ),
+            feed: feed.to_guarded(),
+            token,
+        }),
+    ))
+}
+
+/// The caller's feeds of the performer, or everyone's for admins.
+pub async fn list_calendar_feeds(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<GuardedCalendarFeed>>, AppError> {
+    let mut feeds = state
+        .database
+        .get_calendar_feeds_by_performer_id(&id)
+        .await
+        .map_err(internal_error)?;
+    feeds.retain(|feed| feed.owner_id == caller.user_id || caller.has_scope(ApiScope::Admin));
+    feeds.sort_by_key(|feed| std::cmp::Reverse(feed.timestamp));
+
+    Ok(Json(feeds.iter().map(CalendarFeed::to_guarded).collect()))
+}
+
+/// Stops the feed's link from working. Other callers' feeds are reported
+/// missing, unless the caller is an admin.
+pub async fn revoke_calendar_feed(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let mut feed = state
+        .database
+        .get_calendar_feed_by_id(&id)
+        .await
+        .ok()
+        .filter(|feed| feed.owner_id == caller.user_id || caller.has_scope(ApiScope::Admin))
+        .ok_or_else(calendar_feed_not_found)?;
+
+    if !feed.is_revoked() {
+        feed.revoked_at = Some(Utc::now());
+        state
+            .database
+            .update_calendar_feed(&feed)
+            .await
+            .map_err(internal_error)?;
+        tracing::info!("{} revoked calendar feed '{}'", caller.user_id, feed.id);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `id` with anything but ASCII letters, digits, `-` and `_` replaced, so it
+/// can be quoted in a header.
+fn file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// An iCalendar feed of the performer's confirmed gigs, for subscribing to
+/// from a calendar app. Served outside API key auth; the `token` of a feed
+/// issued for the performer is required instead.
+pub async fn get_calendar(
+    State(state): State<AppStateDyn>,
+    Path(id): Path<String>,
+    Query(params): Query<CalendarParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let feed = match params.token {
+        Some(token) => state
+            .database
+            .get_calendar_feed_by_token_hash(&hash_token(&token))
+            .await
+            .ok(),
+        None => None,
+    };
+    if !feed.is_some_and(|feed| feed.performer_id == id && !feed.is_revoked()) {
+        state.metrics.record_auth_failure("invalid_calendar_token");
+        return Err(AppError::new("invalid calendar token").with_status(StatusCode::UNAUTHORIZED));
+    }
+
+    let performer = state
+        .database
+        .get_user_by_id(&id)
+        .await
+        .map_err(performer_not_found)?;
+    let mut bookings = state
+        .database
+        .get_bookings_by_performer_id(&id)
+        .await
+        .map_err(internal_error)?;
+    bookings.retain(|booking| booking.status == BookingStatus::Confirmed);
+    bookings.sort_by_key(|booking| booking.start_time);
+
+    // A venue that can't be found just leaves its gigs without a LOCATION.
+    let venue_ids: Vec<String> = bookings
+        .iter()
+        .filter_map(|booking| booking.venue_id.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let venues: HashMap<String, String> = if venue_ids.is_empty() {
+        HashMap::new()
+    } else {
+        state
+            .database
+            .get_users_by_ids(&venue_ids)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!("{error}");
+                vec![]
+            })
+            .into_iter()
+            .map(|venue| (venue.id.clone(), venue.display_name().to_string()))
+            .collect()
+    };
+
+    let gigs: Vec<Gig> = bookings
+        .iter()
+        .map(|booking| Gig {
+            booking,
+            venue: booking
+                .venue_id
+                .as_ref()
+                .and_then(|venue_id| venues.get(venue_id))
+                .map(String::as_str),
+        })
+        .collect();
+    let name = match performer.display_name() {
+        "" => performer.username.as_str(),
+        name => name,
+    };
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}.ics\"", file_name(&performer.id)),
+            ),
+        ],
+        calendar::render(name, &gigs),
+    ))
+}
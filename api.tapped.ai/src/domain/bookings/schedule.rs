@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::models::booking::{Booking, BookingStatus};
+
+/// A span of time from `start` up to, but not including, `end`, so a set
+/// that ends at 22:00 doesn't clash with one starting at 22:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSlot {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimeSlot {
+    /// `None` unless `start` is before `end`.
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Option<Self> {
+        (start < end).then_some(Self { start, end })
+    }
+
+    pub fn of(booking: &Booking) -> Self {
+        Self {
+            start: booking.start_time,
+            end: booking.end_time,
+        }
+    }
+
+    pub fn overlaps(&self, other: &TimeSlot) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// The confirmed bookings that take up any part of `slot`. Pending and
+/// canceled bookings don't hold the performer's time.
+pub fn conflicts<'a>(bookings: &'a [Booking], slot: &TimeSlot) -> Vec<&'a Booking> {
+    bookings
+        .iter()
+        .filter(|booking| booking.status == BookingStatus::Confirmed)
+        .filter(|booking| TimeSlot::of(booking).overlaps(slot))
+        .collect()
+}
+
+/// The confirmed and pending bookings that fall at least partly inside
+/// `window`, earliest first. Pending requests count as busy until the
+/// performer answers them.
+pub fn busy<'a>(bookings: &'a [Booking], window: &TimeSlot) -> Vec<&'a Booking> {
+    let mut busy: Vec<&Booking> = bookings
+        .iter()
+        .filter(|booking| booking.status != BookingStatus::Canceled)
+        .filter(|booking| TimeSlot::of(booking).overlaps(window))
+        .collect();
+    busy.sort_by_key(|booking| (booking.start_time, booking.end_time));
+
+    busy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    /// Hours from midnight on a fixed day; 25 is 1am the next day.
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap() + Duration::hours(hour)
+    }
+
+    fn slot(start: i64, end: i64) -> TimeSlot {
+        TimeSlot::new(at(start), at(end)).unwrap()
+    }
+
+    fn booking(id: &str, start: i64, end: i64, status: BookingStatus) -> Booking {
+        Booking {
+            id: id.into(),
+            status,
+            start_time: at(start),
+            end_time: at(end),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn slots_must_end_after_they_start() {
+        assert!(TimeSlot::new(at(20), at(20)).is_none());
+        assert!(TimeSlot::new(at(21), at(20)).is_none());
+    }
+
+    #[test]
+    fn overlapping_slots() {
+        assert!(slot(20, 23).overlaps(&slot(22, 24)));
+        assert!(slot(20, 23).overlaps(&slot(18, 21)));
+        assert!(slot(20, 23).overlaps(&slot(21, 22)));
+        assert!(slot(21, 22).overlaps(&slot(20, 23)));
+        assert!(slot(20, 23).overlaps(&slot(20, 23)));
+    }
+
+    #[test]
+    fn back_to_back_slots_do_not_overlap() {
+        assert!(!slot(20, 22).overlaps(&slot(22, 23)));
+        assert!(!slot(22, 23).overlaps(&slot(20, 22)));
+        assert!(!slot(10, 12).overlaps(&slot(20, 22)));
+    }
+
+    #[test]
+    fn only_confirmed_bookings_conflict() {
+        let bookings = vec![
+            booking("confirmed", 20, 23, BookingStatus::Confirmed),
+            booking("pending", 20, 23, BookingStatus::Pending),
+            booking("canceled", 20, 23, BookingStatus::Canceled),
+            booking("earlier", 17, 20, BookingStatus::Confirmed),
+        ];
+
+        let ids: Vec<&str> = conflicts(&bookings, &slot(19, 21))
+            .into_iter()
+            .map(|booking| booking.id.as_str())
+            .collect();
+
+        assert_eq!(vec!["confirmed", "earlier"], ids);
+        assert!(conflicts(&bookings, &slot(23, 24)).is_empty());
+    }
+
+    #[test]
+    fn busy_includes_pending_bookings_in_the_window() {
+        let bookings = vec![
+            booking("late", 22, 26, BookingStatus::Confirmed),
+            booking("pending", 12, 14, BookingStatus::Pending),
+            booking("canceled", 15, 16, BookingStatus::Canceled),
+            booking("tomorrow", 30, 32, BookingStatus::Confirmed),
+        ];
+
+        let ids: Vec<&str> = busy(&bookings, &slot(0, 24))
+            .into_iter()
+            .map(|booking| booking.id.as_str())
+            .collect();
+
+        assert_eq!(vec!["pending", "late"], ids);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    data::{database::UserAlreadyClaimed, mailer::Email, soft_delete},
+    domain::{
+        auth::Caller,
+        models::{
+            claim::{ClaimStatus, GuardedProfileClaim, ProfileClaim},
+            user::UserModel,
+        },
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// How long an emailed token can be used for.
+pub const CLAIM_TTL_HOURS: i64 = 24;
+/// Wrong tokens allowed before the claim fails for good.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// How long after a token is emailed before another one is sent for the
+/// same profile.
+pub const CLAIM_COOLDOWN_MINUTES: i64 = 15;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartClaimRequest {
+    /// The unclaimed profile.
+    user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyClaimRequest {
+    token: String,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hex::encode(bytes)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+fn claim_email(to: &str, username: &str, token: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: format!("Verify your claim to @{username} on Tapped"),
+        body: format!(
+            "Someone asked to take over the Tapped profile @{username}.\n\n\
+             If that was you, verify the claim with this token:\n\n{token}\n\n\
+             It expires in {CLAIM_TTL_HOURS} hours. If it wasn't you, ignore this email."
+        ),
+    }
+}
+
+/// The caller's own claim. Other callers' claims are reported missing.
+async fn find_claim(
+    state: &AppStateDyn,
+    caller: &Caller,
+    id: &str,
+) -> Result<ProfileClaim, AppError> {
+    state
+        .database
+        .get_profile_claim_by_id(id)
+        .await
+        .ok()
+        .filter(|claim| claim.claimant_id == caller.user_id)
+        .ok_or_else(|| AppError::new("claim not found").with_status(StatusCode::NOT_FOUND))
+}
+
+/// The profile as stored right now. Reads around the user cache, which may
+/// not have seen a claim made through another instance yet.
+async fn fresh_profile(state: &AppStateDyn, id: &str) -> Result<UserModel, AppError> {
+    state
+        .database
+        .get_user_by_id_including_deleted(id)
+        .await
+        .and_then(soft_delete::visible)
+        .map_err(|error| {
+            tracing::warn!("{error}");
+            AppError::user_not_found("profile", &error)
+        })
+}
+
+fn already_claimed() -> AppError {
+    AppError::new("profile is already claimed").with_status(StatusCode::CONFLICT)
+}
+
+/// Emails a verification token to the contact of an unclaimed profile. At
+/// most one email goes out per profile every [`CLAIM_COOLDOWN_MINUTES`].
+pub async fn start_claim(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<StartClaimRequest>,
+) -> Result<(StatusCode, Json<GuardedProfileClaim>), AppError> {
+    let profile = fresh_profile(&state, &request.user_id).await?;
+    if !profile.is_unclaimed() {
+        return Err(already_claimed());
+    }
+    let email = profile.contact_email().ok_or_else(|| {
+        AppError::new("profile has no contact email to verify a claim with")
+            .with_status(StatusCode::UNPROCESSABLE_ENTITY)
+    })?;
+
+    let now = Utc::now();
+    let claims = state
+        .database
+        .get_profile_claims_by_user_id(&profile.id)
+        .await
+        .map_err(internal_error)?;
+    let cooling_down = claims
+        .iter()
+        .any(|claim| claim.timestamp + Duration::minutes(CLAIM_COOLDOWN_MINUTES) > now);
+    let open = claims.into_iter().find(|claim| {
+        claim.claimant_id == caller.user_id
+            && claim.status == ClaimStatus::Pending
+            && !claim.is_expired(now)
+    });
+
+    // Starting again while the last email is fresh hands back the open claim
+    // instead of emailing the profile's contact over and over.
+    if cooling_down {
+        return match open {
+            Some(claim) => Ok((StatusCode::ACCEPTED, Json(claim.to_guarded()))),
+            None => Err(AppError::new(
+                "a claim for this profile was started recently, try again later",
+            )
+            .with_status(StatusCode::TOO_MANY_REQUESTS)),
+        };
+    }
+
+    let token = generate_token();
+    let claim = match open {
+        // A fresh token for the open claim; the wrong tokens tried so far
+        // still count.
+        Some(mut claim) => {
+            claim.email = email.to_string();
+            claim.token_hash = hash_token(&token);
+            claim.timestamp = now;
+            claim.expires_at = now + Duration::hours(CLAIM_TTL_HOURS);
+            state
+                .database
+                .update_profile_claim(&claim)
+                .await
+                .map_err(internal_error)?;
+
+            claim
+        }
+        None => {
+            let claim = ProfileClaim {
+                id: Uuid::new_v4().to_string(),
+                user_id: profile.id.clone(),
+                claimant_id: caller.user_id,
+                email: email.to_string(),
+                token_hash: hash_token(&token),
+                attempts: 0,
+                status: ClaimStatus::Pending,
+                timestamp: now,
+                expires_at: now + Duration::hours(CLAIM_TTL_HOURS),
+                verified_at: None,
+            };
+            state
+                .database
+                .create_profile_claim(&claim)
+                .await
+                .map_err(internal_error)?;
+
+            claim
+        }
+    };
+    state
+        .mailer
+        .send(&claim_email(email, &profile.username, &token))
+        .await
+        .map_err(internal_error)?;
+
+    Ok((StatusCode::ACCEPTED, Json(claim.to_guarded())))
+}
+
+/// Proves the claim with the emailed token and links the profile to the
+/// caller.
+pub async fn verify_claim(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Json(request): Json<VerifyClaimRequest>,
+) -> Result<Json<GuardedProfileClaim>, AppError> {
+    let mut claim = find_claim(&state, &caller, &id).await?;
+    match claim.status {
+        ClaimStatus::Verified => {
+            return Err(AppError::new("claim is already verified").with_status(StatusCode::CONFLICT))
+        }
+        ClaimStatus::Failed => {
+            return Err(AppError::new("claim failed, start a new one").with_status(StatusCode::GONE))
+        }
+        ClaimStatus::Pending => {}
+    }
+    let now = Utc::now();
+    if claim.is_expired(now) {
+        return Err(AppError::new("claim has expired").with_status(StatusCode::GONE));
+    }
+
+    if hash_token(&request.token) != claim.token_hash {
+        claim.attempts += 1;
+        if claim.attempts >= MAX_ATTEMPTS {
+            claim.status = ClaimStatus::Failed;
+        }
+        state
+            .database
+            .update_profile_claim(&claim)
+            .await
+            .map_err(internal_error)?;
+
+        return Err(AppError::new("invalid token"));
+    }
+
+    // Someone else may have won the profile since the token was sent. The
+    // check happens in the same transaction as the write.
+    state
+        .database
+        .claim_user(&claim.user_id, &claim.claimant_id)
+        .await
+        .map_err(|error| {
+            tracing::warn!("{error}");
+            if error.downcast_ref::<UserAlreadyClaimed>().is_some() {
+                already_claimed()
+            } else {
+                AppError::user_not_found("profile", &error)
+            }
+        })?;
+
+    claim.status = ClaimStatus::Verified;
+    claim.verified_at = Some(now);
+    state
+        .database
+        .update_profile_claim(&claim)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(claim.to_guarded()))
+}
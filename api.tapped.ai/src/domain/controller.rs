@@ -1,18 +1,27 @@
 use std::collections::HashMap;
 
 use crate::{
-    data::search::{UserSearchOptions, UserSearchOptionsBuilder},
-    domain::models::user::UserModel,
+    data::search::{TechRiderFilters, UserSearchOptionsBuilder},
+    domain::{
+        auth::Caller,
+        fieldset::{to_values, Fieldset, FieldsetParams, Relation},
+        models::{api_key::ApiScope, user::UserModel},
+        pricing::estimate_rate,
+        redaction::Viewer,
+    },
+    errors::{user_lookup_status, AppError},
     state::AppStateDyn,
 };
 use anyhow::Result;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use futures::future;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tracing::instrument;
 
 use super::models::user::{GuardedPerformer, GuardedVenue};
@@ -20,175 +29,316 @@ use super::models::user::{GuardedPerformer, GuardedVenue};
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchParams {
     query: Option<String>,
+    #[serde(flatten)]
+    fieldset: FieldsetParams,
 }
 
-async fn transform_performer_id(id: String, state: &AppStateDyn) -> Result<GuardedPerformer> {
-    let user = state.database.get_user_by_id(&id).await?;
+/// Search filters shared by `/performer/search` and the export endpoints.
+/// Lists are comma separated, e.g. `?genres=rock,jazz`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFilters {
+    genres: Option<String>,
+    labels: Option<String>,
+    occupations: Option<String>,
+    venue_genres: Option<String>,
+    unclaimed: Option<bool>,
+    lat: Option<f64>,
+    lng: Option<f64>,
+    radius: Option<u64>,
+    min_capacity: Option<u32>,
+    max_capacity: Option<u32>,
+    /// Venues whose tech rider mentions in-ear monitors.
+    in_ear_monitors: Option<bool>,
+    min_channels: Option<u32>,
+    min_monitor_mixes: Option<u32>,
+    /// Backline every venue must have, e.g. `?backline=drum kit,bass amp`.
+    backline: Option<String>,
+}
+
+/// `?includeDeleted=true`, for admins to read soft-deleted profiles.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedParams {
+    include_deleted: Option<bool>,
+}
+
+fn internal_error() -> AppError {
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// A fieldset that couldn't be parsed or applied.
+fn fieldset_error(status: StatusCode) -> AppError {
+    if status.is_server_error() {
+        return internal_error();
+    }
 
-    transform_performer(user, state).await
+    AppError::new("invalid fields or include").with_status(status)
 }
 
+/// Whether soft-deleted users should be read too. Only admins may ask for
+/// them.
+pub fn include_deleted(caller: &Caller, include_deleted: Option<bool>) -> Result<bool, AppError> {
+    let include_deleted = include_deleted.unwrap_or_default();
+    if include_deleted && !caller.has_scope(ApiScope::Admin) {
+        return Err(AppError::new("includeDeleted needs the admin scope")
+            .with_status(StatusCode::FORBIDDEN));
+    }
+
+    Ok(include_deleted)
+}
+
+fn split_list(list: &Option<String>) -> Option<Vec<String>> {
+    list.as_ref().map(|list| {
+        list.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+impl SearchFilters {
+    pub fn to_options(&self) -> UserSearchOptionsBuilder {
+        let mut options = UserSearchOptionsBuilder::default();
+        options
+            .genres(split_list(&self.genres))
+            .labels(split_list(&self.labels))
+            .occupations(split_list(&self.occupations))
+            .venue_genres(split_list(&self.venue_genres))
+            .unclaimed(self.unclaimed)
+            .lat(self.lat)
+            .lng(self.lng)
+            .radius(self.radius)
+            .min_capacity(self.min_capacity)
+            .max_capacity(self.max_capacity)
+            .tech_rider(TechRiderFilters {
+                in_ear_monitors: self.in_ear_monitors,
+                min_channels: self.min_channels,
+                min_monitor_mixes: self.min_monitor_mixes,
+                backline: split_list(&self.backline),
+            });
+
+        options
+    }
+}
+
+/// Relations left out of the fieldset are never fetched.
 #[instrument(skip(state))]
-async fn transform_performer(user: UserModel, state: &AppStateDyn) -> Result<GuardedPerformer> {
-    let bookings = state
-        .database
-        .get_bookings_by_performer_id(&user.id)
-        .await?;
-    let guarded_bookings = bookings
-        .into_iter()
-        .map(|booking| booking.to_guarded())
-        .collect();
+pub(crate) async fn transform_performer(
+    user: UserModel,
+    state: &AppStateDyn,
+    fieldset: &Fieldset,
+    viewer: &Viewer,
+) -> Result<GuardedPerformer> {
+    let guarded_bookings = if fieldset.includes(Relation::Bookings) {
+        let bookings = state
+            .database
+            .get_bookings_by_performer_id(&user.id)
+            .await?;
+
+        Some(
+            bookings
+                .into_iter()
+                .map(|booking| booking.to_guarded())
+                .collect(),
+        )
+    } else {
+        None
+    };
 
-    let reviews = state.database.get_reviews_by_performer_id(&user.id).await?;
-    let guarded_reviews = reviews
-        .into_iter()
-        .map(|review| review.to_guarded())
-        .collect();
+    let reviews = if fieldset.includes(Relation::Reviews) {
+        Some(state.database.get_reviews_by_performer_id(&user.id).await?)
+    } else {
+        None
+    };
 
-    Ok(user.to_guarded_performer(guarded_bookings, guarded_reviews))
+    let rate_estimate = if fieldset.includes(Relation::RateEstimate) {
+        Some(estimate_rate(state, &user).await?)
+    } else {
+        None
+    };
+
+    Ok(GuardedPerformer {
+        rate_estimate,
+        ..user.to_guarded_performer(viewer, guarded_bookings, reviews)
+    })
 }
 
 #[instrument(skip(state))]
-async fn transform_venue(user: UserModel, state: &AppStateDyn) -> Result<GuardedVenue> {
-    let bookings = state
-        .database
-        .get_bookings_by_booker_id(&user.id)
-        .await
-        .map_err(|e| {
-            tracing::error!("failed to get bookings: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
-        .unwrap_or_default();
-    let guarded_bookings = bookings
-        .into_iter()
-        .map(|booking| booking.to_guarded())
-        .collect();
+pub(crate) async fn transform_venue(
+    user: UserModel,
+    state: &AppStateDyn,
+    fieldset: &Fieldset,
+    viewer: &Viewer,
+) -> Result<GuardedVenue> {
+    let guarded_bookings = if fieldset.includes(Relation::Bookings) {
+        let bookings = state
+            .database
+            .get_bookings_by_booker_id(&user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to get bookings: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+            .unwrap_or_default();
+
+        Some(
+            bookings
+                .into_iter()
+                .map(|booking| booking.to_guarded())
+                .collect(),
+        )
+    } else {
+        None
+    };
 
-    let reviews = state
-        .database
-        .get_reviews_by_booker_id(&user.id)
-        .await
-        .map_err(|e| {
-            tracing::error!("failed to get reviews: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
-        .unwrap_or_default();
-    let guarded_reviews = reviews
-        .into_iter()
-        .map(|review| review.to_guarded())
-        .collect();
+    let reviews = if fieldset.includes(Relation::Reviews) {
+        let reviews = state
+            .database
+            .get_reviews_by_booker_id(&user.id)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to get reviews: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+            .unwrap_or_default();
+
+        Some(reviews)
+    } else {
+        None
+    };
 
-    let guarded_venue = user.to_guarded_venue(guarded_bookings, guarded_reviews);
+    let guarded_venue = user.to_guarded_venue(viewer, guarded_bookings, reviews);
 
     Ok(guarded_venue)
 }
 
 pub async fn search_performers(
     State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
     Query(params): Query<SearchParams>,
-) -> Result<Json<Vec<GuardedPerformer>>, StatusCode> {
-    tracing::info!("searching users with {:?}", params);
+    Query(filters): Query<SearchFilters>,
+) -> Result<Json<Vec<Value>>, AppError> {
+    tracing::info!("searching users with {:?} {:?}", params, filters);
+    let fieldset = Fieldset::parse(&params.fieldset).map_err(fieldset_error)?;
+    let viewer = Viewer::of(&caller);
     let query = params.query.unwrap_or_default();
+    let options = filters.to_options().build().map_err(|e| {
+        tracing::error!("failed to build search options: {:?}", e);
+        internal_error()
+    })?;
     let users = state
         .search
-        .search_users(query, UserSearchOptions::default())
+        .search_users(query, options)
         .await
         .map_err(|error| {
             tracing::error!("{error}");
-            StatusCode::INTERNAL_SERVER_ERROR
+            internal_error()
         })?;
 
     let guarded_performers = future::try_join_all(
         users
             .into_iter()
-            .map(|user| transform_performer(user, &state)),
+            .map(|user| transform_performer(user, &state, &fieldset, &viewer)),
     )
     .await
-    .unwrap();
+    .map_err(|e| {
+        tracing::error!("failed to transform performers: {:?}", e);
+        internal_error()
+    })?;
 
-    Ok(Json(guarded_performers))
+    Ok(Json(
+        fieldset
+            .shape(&guarded_performers)
+            .map_err(fieldset_error)?,
+    ))
 }
 
 pub async fn get_performer_username(
     State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
     Path(username): Path<String>,
-) -> Result<Json<GuardedPerformer>, StatusCode> {
-    let user = state
-        .database
-        .get_user_by_username(&username)
+    Query(params): Query<FieldsetParams>,
+    Query(deleted): Query<DeletedParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let fieldset = Fieldset::parse(&params)?;
+    let viewer = Viewer::of(&caller);
+    let user =
+        if include_deleted(&caller, deleted.include_deleted).map_err(|error| error.status)? {
+            state
+                .database
+                .get_user_by_username_including_deleted(&username)
+        } else {
+            state.database.get_user_by_username(&username)
+        }
         .await
         .map_err(|error| {
             tracing::error!("{error}");
-            StatusCode::NOT_FOUND
+            user_lookup_status(&error)
         })?;
 
-    let guarded_performer = transform_performer(user, &state).await.map_err(|error| {
-        tracing::error!("{error}");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let guarded_performer = transform_performer(user, &state, &fieldset, &viewer)
+        .await
+        .map_err(|error| {
+            tracing::error!("{error}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    Ok(Json(guarded_performer))
+    Ok(Json(fieldset.shape_one(&guarded_performer)?))
 }
 
 pub async fn get_performer(
     State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
     Path(id): Path<String>,
-) -> Result<Json<GuardedPerformer>, StatusCode> {
-    let user = state.database.get_user_by_id(&id).await.map_err(|error| {
-        tracing::error!("{error}");
-        StatusCode::NOT_FOUND
-    })?;
-
-    let bookings = state
-        .database
-        .get_bookings_by_performer_id(&id)
+    Query(params): Query<FieldsetParams>,
+    Query(deleted): Query<DeletedParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let fieldset = Fieldset::parse(&params)?;
+    let viewer = Viewer::of(&caller);
+    let user =
+        if include_deleted(&caller, deleted.include_deleted).map_err(|error| error.status)? {
+            state.database.get_user_by_id_including_deleted(&id)
+        } else {
+            state.database.get_user_by_id(&id)
+        }
         .await
         .map_err(|error| {
             tracing::error!("{error}");
-            StatusCode::INTERNAL_SERVER_ERROR
+            user_lookup_status(&error)
         })?;
 
-    let guarded_bookings = bookings
-        .into_iter()
-        .map(|booking| booking.to_guarded())
-        .collect();
-
-    let reviews = state
-        .database
-        .get_reviews_by_performer_id(&id)
+    let guarded_user = transform_performer(user, &state, &fieldset, &viewer)
         .await
         .map_err(|error| {
             tracing::error!("{error}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    let guarded_reviews = reviews
-        .into_iter()
-        .map(|review| review.to_guarded())
-        .collect();
-
-    let guarded_user = user.to_guarded_performer(guarded_bookings, guarded_reviews);
-
-    Ok(Json(guarded_user))
+    Ok(Json(fieldset.shape_one(&guarded_user)?))
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LocationResponse {
-    pub venues: Vec<GuardedVenue>,
-    pub top_performers: Vec<GuardedPerformer>,
+    /// `GuardedVenue`s, shaped by the request's fieldset.
+    pub venues: Vec<Value>,
+    /// `GuardedPerformer`s, shaped by the request's fieldset.
+    pub top_performers: Vec<Value>,
     pub genres: HashMap<String, f64>,
 }
 
 pub async fn get_location(
     State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
     Path(latlng): Path<String>,
+    Query(params): Query<FieldsetParams>,
 ) -> Result<Json<LocationResponse>, StatusCode> {
-    let mut latlng = latlng.split(",");
-    let lat = latlng.next().unwrap();
-    let lng = latlng.next().unwrap();
-
-    let lat: f64 = lat.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
-    let lng: f64 = lng.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let fieldset = Fieldset::parse(&params)?;
+    let viewer = Viewer::of(&caller);
+    let (lat, lng) = latlng.split_once(',').ok_or(StatusCode::BAD_REQUEST)?;
+    let lat: f64 = lat.trim().parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let lng: f64 = lng.trim().parse().map_err(|_| StatusCode::BAD_REQUEST)?;
 
     let options = UserSearchOptionsBuilder::default()
         .lat(Some(lat))
@@ -213,7 +363,7 @@ pub async fn get_location(
     let guarded_venues = future::try_join_all(
         venues
             .into_iter()
-            .map(|venue| transform_venue(venue, &state)),
+            .map(|venue| transform_venue(venue, &state, &fieldset, &viewer)),
     )
     .await
     .map_err(|e| {
@@ -221,11 +371,33 @@ pub async fn get_location(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // One round trip for every venue's top performers. Deleted and missing
+    // performers are skipped rather than failing the whole response.
+    let mut top_performer_ids: Vec<String> = Vec::new();
+    for id in guarded_venues
+        .iter()
+        .flat_map(|venue| venue.top_performer_ids.iter())
+    {
+        if !top_performer_ids.contains(id) {
+            top_performer_ids.push(id.clone());
+        }
+    }
+    let top_performers = if top_performer_ids.is_empty() {
+        vec![]
+    } else {
+        state
+            .database
+            .get_users_by_ids(&top_performer_ids)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to get top performers: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    };
     let top_guarded_performers = future::try_join_all(
-        guarded_venues
-            .iter()
-            .flat_map(|venue| venue.top_performer_ids.clone())
-            .map(|id| transform_performer_id(id, &state)),
+        top_performers
+            .into_iter()
+            .map(|performer| transform_performer(performer, &state, &fieldset, &viewer)),
     )
     .await
     .map_err(|e| {
@@ -253,9 +425,13 @@ pub async fn get_location(
         .map(|(genre, count)| (genre, count as f64 / genre_count as f64))
         .collect();
 
+    let mut venues = to_values(&guarded_venues)?;
+    let mut top_performers = to_values(&top_guarded_performers)?;
+    fieldset.retain(&mut [&mut venues, &mut top_performers])?;
+
     let res = LocationResponse {
-        venues: guarded_venues,
-        top_performers: top_guarded_performers,
+        venues,
+        top_performers,
         genres: normalized_genres,
     };
 
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        auth::Caller,
+        bookings::schedule::TimeSlot,
+        models::{
+            event::{Event, GuardedEvent},
+            user::{GuardedPerformer, Location, UserModel},
+        },
+        redaction::Viewer,
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How far ahead `/events` looks when no `to` is given.
+pub const DEFAULT_WINDOW_DAYS: i64 = 30;
+/// The widest window a single `/events` request may cover.
+pub const MAX_WINDOW_DAYS: i64 = 366;
+/// How far from `lat`,`lng` to look when no `radius` is given.
+pub const DEFAULT_RADIUS_METERS: u64 = 50_000;
+
+/// `?from=..&to=..&lat=..&lng=..&radius=..&genres=house,techno`. Every
+/// filter is optional; without `from` the search starts now.
+#[derive(Debug, Deserialize)]
+pub struct EventSearchParams {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    lat: Option<f64>,
+    lng: Option<f64>,
+    /// Meters around `lat`,`lng`.
+    radius: Option<u64>,
+    genres: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDetails {
+    #[serde(flatten)]
+    event: GuardedEvent,
+    /// The billed performers followed by anyone else with a confirmed
+    /// booking for the event.
+    lineup: Vec<GuardedPerformer>,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+impl EventSearchParams {
+    fn window(&self) -> Result<TimeSlot, AppError> {
+        let from = self.from.unwrap_or_else(Utc::now);
+        let to = self
+            .to
+            .unwrap_or_else(|| from + Duration::days(DEFAULT_WINDOW_DAYS));
+        let window =
+            TimeSlot::new(from, to).ok_or_else(|| AppError::new("to must be after from"))?;
+        if window.end - window.start > Duration::days(MAX_WINDOW_DAYS) {
+            return Err(AppError::new(&format!(
+                "the window can span at most {MAX_WINDOW_DAYS} days"
+            )));
+        }
+
+        Ok(window)
+    }
+
+    fn center(&self) -> Result<Option<(f64, f64)>, AppError> {
+        match (self.lat, self.lng) {
+            (Some(lat), Some(lng)) => Ok(Some((lat, lng))),
+            (None, None) if self.radius.is_some() => Err(AppError::new("radius needs lat and lng")),
+            (None, None) => Ok(None),
+            _ => Err(AppError::new("lat and lng must be given together")),
+        }
+    }
+
+    fn genres(&self) -> Vec<String> {
+        self.genres
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Where each event is: its own location, or else its venue's. Venues are
+/// looked up in one round trip.
+async fn locate(state: &AppStateDyn, events: &[Event]) -> anyhow::Result<Vec<Option<Location>>> {
+    let mut venue_ids: Vec<String> = events
+        .iter()
+        .filter(|event| event.location.is_none())
+        .filter_map(|event| event.venue_id.clone())
+        .collect();
+    venue_ids.sort();
+    venue_ids.dedup();
+    let venues: HashMap<String, Location> = if venue_ids.is_empty() {
+        HashMap::new()
+    } else {
+        state
+            .database
+            .get_users_by_ids(&venue_ids)
+            .await?
+            .into_iter()
+            .filter_map(|venue| Some((venue.id.clone(), venue.location()?.clone())))
+            .collect()
+    };
+
+    Ok(events
+        .iter()
+        .map(|event| {
+            event.location.clone().or_else(|| {
+                event
+                    .venue_id
+                    .as_ref()
+                    .and_then(|venue_id| venues.get(venue_id).cloned())
+            })
+        })
+        .collect())
+}
+
+/// Events starting between `from` (default now) and `to` (default 30 days
+/// later), soonest first, optionally near a point and in any of `genres`.
+pub async fn search_events(
+    State(state): State<AppStateDyn>,
+    Query(params): Query<EventSearchParams>,
+) -> Result<Json<Vec<GuardedEvent>>, AppError> {
+    let window = params.window()?;
+    let center = params.center()?;
+    let genres = params.genres();
+
+    let mut events = state
+        .database
+        .get_events_by_start_time(window.start, window.end)
+        .await
+        .map_err(internal_error)?;
+    if !genres.is_empty() {
+        events.retain(|event| event.has_any_genre(&genres));
+    }
+    if let Some((lat, lng)) = center {
+        let radius = params.radius.unwrap_or(DEFAULT_RADIUS_METERS) as f64;
+        let locations = locate(&state, &events).await.map_err(internal_error)?;
+        events = events
+            .into_iter()
+            .zip(locations)
+            .filter(|(_, location)| {
+                location
+                    .as_ref()
+                    .is_some_and(|location| location.distance_to(lat, lng) <= radius)
+            })
+            .map(|(event, _)| event)
+            .collect();
+    }
+    events.sort_by(|a, b| {
+        a.start_time
+            .cmp(&b.start_time)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    Ok(Json(events.iter().map(Event::to_guarded).collect()))
+}
+
+/// The event with its lineup.
+pub async fn get_event(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<EventDetails>, AppError> {
+    let event = state.database.get_event_by_id(&id).await.map_err(|error| {
+        tracing::warn!("{error}");
+        AppError::new("event not found").with_status(StatusCode::NOT_FOUND)
+    })?;
+
+    let mut performer_ids = event.performer_ids.clone();
+    let mut bookings = state
+        .database
+        .get_bookings_by_event_id(&event.id)
+        .await
+        .map_err(internal_error)?;
+    bookings.sort_by_key(|booking| booking.start_time);
+    for booking in bookings {
+        if !performer_ids.contains(&booking.requestee_id) {
+            performer_ids.push(booking.requestee_id);
+        }
+    }
+
+    let mut performers: HashMap<String, _> = if performer_ids.is_empty() {
+        HashMap::new()
+    } else {
+        state
+            .database
+            .get_users_by_ids(&performer_ids)
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .filter(UserModel::is_performer)
+            .map(|user| (user.id.clone(), user))
+            .collect()
+    };
+    let viewer = Viewer::of(&caller);
+    let lineup = performer_ids
+        .iter()
+        .filter_map(|id| performers.remove(id))
+        .map(|performer| performer.to_guarded_performer(&viewer, None, None))
+        .collect();
+
+    Ok(Json(EventDetails {
+        event: event.to_guarded(),
+        lineup,
+    }))
+}
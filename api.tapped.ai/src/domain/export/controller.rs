@@ -0,0 +1,220 @@
+use crate::{
+    domain::{
+        auth::Caller,
+        controller::SearchFilters,
+        fieldset::{Fieldset, FieldsetParams},
+        models::export_job::{ExportJob, ExportJobStatus, GuardedExportJob},
+        redaction::Viewer,
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE, LOCATION},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use super::{encode_rows, export_rows, ExportFormat, ExportRequest, ExportResource};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportParams {
+    query: Option<String>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+fn export_request(
+    resource: ExportResource,
+    params: ExportParams,
+    filters: &SearchFilters,
+    fieldset: &FieldsetParams,
+    caller: &Caller,
+) -> Result<ExportRequest, AppError> {
+    // Loading relations for every row of a whole-market dump is expensive,
+    // so exports only embed them when asked to.
+    let fieldset = Fieldset::parse_opt_in(fieldset)
+        .map_err(|status| AppError::new("invalid fields or include").with_status(status))?;
+
+    Ok(ExportRequest {
+        resource,
+        format: params.format,
+        query: params.query.unwrap_or_default(),
+        options: filters.to_options(),
+        fieldset,
+        viewer: Viewer::of(caller),
+    })
+}
+
+fn stream_export(state: AppStateDyn, request: ExportRequest) -> Response {
+    let format = request.format;
+    let filename = format!("{}.{}", request.resource.as_str(), format.extension());
+    let columns = request.columns();
+    let chunks = encode_rows(format, columns, export_rows(state, request)).map(|chunk| {
+        chunk.inspect_err(|error| tracing::error!("export failed mid-stream: {error}"))
+    });
+
+    (
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+async fn start_export(
+    state: &AppStateDyn,
+    caller: Caller,
+    request: ExportRequest,
+) -> Result<Response, AppError> {
+    let (resource, format) = (request.resource, request.format);
+    let columns = request.columns();
+    let chunks = encode_rows(format, columns, export_rows(state.clone(), request));
+    let job = state
+        .exports
+        .start(caller.user_id, resource, format, chunks)
+        .await
+        .map_err(|error| {
+            tracing::error!("failed to start export: {error}");
+            AppError::new("failed to start export").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(LOCATION, format!("/v1/export/jobs/{}", job.id))],
+        Json(job.to_guarded()),
+    )
+        .into_response())
+}
+
+/// Streams every matching performer as NDJSON or CSV.
+pub async fn export_performers(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<SearchFilters>,
+    Query(fieldset): Query<FieldsetParams>,
+) -> Result<Response, AppError> {
+    let request = export_request(
+        ExportResource::Performers,
+        params,
+        &filters,
+        &fieldset,
+        &caller,
+    )?;
+
+    Ok(stream_export(state, request))
+}
+
+/// Streams every matching venue as NDJSON or CSV.
+pub async fn export_venues(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<SearchFilters>,
+    Query(fieldset): Query<FieldsetParams>,
+) -> Result<Response, AppError> {
+    let request = export_request(ExportResource::Venues, params, &filters, &fieldset, &caller)?;
+
+    Ok(stream_export(state, request))
+}
+
+/// Starts a background export of performers, for dumps too large to stream
+/// over a single request. Poll the job in the `Location` header.
+pub async fn create_performers_export(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<SearchFilters>,
+    Query(fieldset): Query<FieldsetParams>,
+) -> Result<Response, AppError> {
+    let request = export_request(
+        ExportResource::Performers,
+        params,
+        &filters,
+        &fieldset,
+        &caller,
+    )?;
+
+    start_export(&state, caller, request).await
+}
+
+/// Starts a background export of venues.
+pub async fn create_venues_export(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<SearchFilters>,
+    Query(fieldset): Query<FieldsetParams>,
+) -> Result<Response, AppError> {
+    let request = export_request(ExportResource::Venues, params, &filters, &fieldset, &caller)?;
+
+    start_export(&state, caller, request).await
+}
+
+async fn owned_job(state: &AppStateDyn, caller: &Caller, id: &str) -> Result<ExportJob, AppError> {
+    state
+        .exports
+        .get(id)
+        .await
+        .ok()
+        .filter(|job| job.owner_id == caller.user_id)
+        .ok_or_else(|| AppError::new("export not found").with_status(StatusCode::NOT_FOUND))
+}
+
+pub async fn get_export_job(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<GuardedExportJob>, AppError> {
+    Ok(Json(owned_job(&state, &caller, &id).await?.to_guarded()))
+}
+
+/// The finished export file. Jobs that are still running are a conflict.
+pub async fn download_export_job(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let job = owned_job(&state, &caller, &id).await?;
+
+    match job.status {
+        ExportJobStatus::Completed => {}
+        ExportJobStatus::Running => {
+            return Err(AppError::new("export is still running").with_status(StatusCode::CONFLICT))
+        }
+        ExportJobStatus::Failed => {
+            return Err(AppError::new("export failed").with_status(StatusCode::GONE))
+        }
+    }
+
+    let file = state.exports.read(&job).await.map_err(|error| {
+        tracing::error!("failed to open export {}: {error}", job.id);
+        AppError::new("export not found").with_status(StatusCode::GONE)
+    })?;
+    let filename = format!("{}.{}", job.resource.as_str(), job.format.extension());
+
+    Ok((
+        [
+            (CONTENT_TYPE, job.format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(file),
+    )
+        .into_response())
+}
@@ -0,0 +1,172 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use axum::body::Bytes;
+use chrono::Utc;
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use uuid::Uuid;
+
+use super::{ExportFormat, ExportResource};
+use crate::{
+    data::{database::Database, export_files::ExportFiles},
+    domain::models::export_job::{ExportJob, ExportJobStatus},
+};
+
+/// A running job's row count is saved every this many rows.
+const PROGRESS_ROWS: u64 = 1_000;
+
+/// Background exports. Jobs are kept in the database and their files in the
+/// export storage, so any instance can report on them and serve them.
+#[derive(Clone)]
+pub struct ExportJobs {
+    database: Arc<dyn Database>,
+    files: Arc<dyn ExportFiles>,
+    retention: Duration,
+}
+
+impl ExportJobs {
+    pub fn new(
+        database: Arc<dyn Database>,
+        files: Arc<dyn ExportFiles>,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            database,
+            files,
+            retention,
+        }
+    }
+
+    pub async fn get(&self, id: &str) -> Result<ExportJob> {
+        self.database.get_export_job_by_id(id).await
+    }
+
+    /// The file of a completed job.
+    pub async fn read(&self, job: &ExportJob) -> Result<BoxStream<'static, Result<Bytes>>> {
+        self.files.read(&job.file_name).await
+    }
+
+    /// Records a running job and writes `chunks` to its file in the
+    /// background. Each chunk is one encoded row.
+    pub async fn start(
+        &self,
+        owner_id: String,
+        resource: ExportResource,
+        format: ExportFormat,
+        chunks: impl Stream<Item = Result<Bytes>> + Send + 'static,
+    ) -> Result<ExportJob> {
+        let id = Uuid::new_v4().to_string();
+        let job = ExportJob {
+            file_name: format!("{id}.{}", format.extension()),
+            id,
+            owner_id,
+            resource,
+            format,
+            status: ExportJobStatus::Running,
+            rows: 0,
+            error: None,
+            timestamp: Utc::now(),
+            completed_at: None,
+        };
+        self.database.create_export_job(&job).await?;
+
+        let jobs = self.clone();
+        let mut finished = job.clone();
+        tokio::spawn(async move {
+            jobs.prune().await;
+
+            let rows = Arc::new(AtomicU64::new(0));
+            let chunks = jobs.counted(&finished, rows.clone(), chunks);
+            let result = jobs.files.write(&finished.file_name, chunks).await;
+
+            finished.rows = rows.load(Ordering::Relaxed);
+            finished.completed_at = Some(Utc::now());
+            match result {
+                Ok(()) => finished.status = ExportJobStatus::Completed,
+                Err(error) => {
+                    tracing::error!("export {} failed: {error}", finished.id);
+                    finished.status = ExportJobStatus::Failed;
+                    finished.error = Some(error.to_string());
+                }
+            }
+            jobs.save(&finished).await;
+        });
+
+        Ok(job)
+    }
+
+    /// Counts the rows of `chunks` into `rows`, saving the count as it goes
+    /// so the job can be followed from any instance.
+    fn counted(
+        &self,
+        job: &ExportJob,
+        rows: Arc<AtomicU64>,
+        chunks: impl Stream<Item = Result<Bytes>> + Send + 'static,
+    ) -> BoxStream<'static, Result<Bytes>> {
+        let (jobs, job) = (self.clone(), job.clone());
+
+        chunks
+            .and_then(move |chunk| {
+                let rows = rows.fetch_add(1, Ordering::Relaxed) + 1;
+                let progress = rows.is_multiple_of(PROGRESS_ROWS).then(|| ExportJob {
+                    rows,
+                    ..job.clone()
+                });
+                let jobs = jobs.clone();
+
+                async move {
+                    if let Some(job) = progress {
+                        jobs.save(&job).await;
+                    }
+
+                    Ok(chunk)
+                }
+            })
+            .boxed()
+    }
+
+    async fn save(&self, job: &ExportJob) {
+        if let Err(error) = self.database.update_export_job(job).await {
+            tracing::warn!("failed to save export {}: {error}", job.id);
+        }
+    }
+
+    /// Forgets jobs started before the retention period and removes their
+    /// files. Jobs still running by then were left behind by an instance
+    /// that stopped, so they are marked failed instead, and forgotten on a
+    /// later run.
+    async fn prune(&self) {
+        let cutoff = Utc::now() - self.retention;
+        let expired = match self.database.get_export_jobs_created_before(cutoff).await {
+            Ok(expired) => expired,
+            Err(error) => {
+                tracing::warn!("failed to find expired exports: {error}");
+                return;
+            }
+        };
+
+        for mut job in expired {
+            if job.status == ExportJobStatus::Running {
+                tracing::warn!("export {} was abandoned", job.id);
+                job.status = ExportJobStatus::Failed;
+                job.error = Some("the export stopped before it finished".into());
+                job.completed_at = Some(Utc::now());
+                self.save(&job).await;
+                continue;
+            }
+
+            if let Err(error) = self.files.delete(&job.file_name).await {
+                tracing::warn!("failed to remove export {}: {error}", job.id);
+            }
+            if let Err(error) = self.database.delete_export_job(&job.id).await {
+                tracing::warn!("failed to forget export {}: {error}", job.id);
+            }
+        }
+    }
+}
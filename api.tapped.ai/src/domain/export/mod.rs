@@ -0,0 +1,312 @@
+pub mod controller;
+pub mod jobs;
+
+use crate::{
+    data::search::UserSearchOptionsBuilder,
+    domain::{
+        controller::{transform_performer, transform_venue},
+        fieldset::Fieldset,
+        redaction::Viewer,
+    },
+    state::AppStateDyn,
+};
+use anyhow::Result;
+use axum::body::Bytes;
+use futures::{stream, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Search results are paged through this many users at a time, so an export
+/// never holds more than one page in memory.
+pub const EXPORT_PAGE_SIZE: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportResource {
+    Performers,
+    Venues,
+}
+
+impl ExportResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportResource::Performers => "performers",
+            ExportResource::Venues => "venues",
+        }
+    }
+
+    /// Every top-level field of an exported row, in the order of
+    /// `GuardedPerformer` and `GuardedVenue`.
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            ExportResource::Performers => &[
+                "id",
+                "username",
+                "claimed",
+                "verified",
+                "displayName",
+                "bio",
+                "profilePictureUrl",
+                "location",
+                "socialFollowing",
+                "pressKitUrl",
+                "genres",
+                "spotifyId",
+                "averageTicketRange",
+                "averageAttendance",
+                "rateEstimate",
+                "bookings",
+                "reviews",
+            ],
+            ExportResource::Venues => &[
+                "id",
+                "username",
+                "claimed",
+                "verified",
+                "displayName",
+                "bio",
+                "profilePictureUrl",
+                "location",
+                "genres",
+                "bookingEmail",
+                "capacity",
+                "idealPerformerProfile",
+                "productionInfo",
+                "frontOfHouse",
+                "monitors",
+                "microphones",
+                "lights",
+                "techRider",
+                "topPerformerIds",
+                "bookings",
+                "reviews",
+            ],
+        }
+    }
+}
+
+/// Everything needed to produce an export, owned so it can outlive the
+/// request that started it.
+#[derive(Clone)]
+pub struct ExportRequest {
+    pub resource: ExportResource,
+    pub format: ExportFormat,
+    pub query: String,
+    pub options: UserSearchOptionsBuilder,
+    pub fieldset: Fieldset,
+    /// Rows are redacted for the caller that asked for the export.
+    pub viewer: Viewer,
+}
+
+impl ExportRequest {
+    /// The CSV columns: the resource's fields that the fieldset selects.
+    pub fn columns(&self) -> Vec<String> {
+        self.resource
+            .columns()
+            .iter()
+            .filter(|column| self.fieldset.selects(column))
+            .map(|column| column.to_string())
+            .collect()
+    }
+}
+
+/// Every matching row, shaped by the fieldset, fetched one search page at
+/// a time.
+pub fn export_rows(
+    state: AppStateDyn,
+    request: ExportRequest,
+) -> impl Stream<Item = Result<Value>> + Send + 'static {
+    let ExportRequest {
+        resource,
+        query,
+        options,
+        fieldset,
+        viewer,
+        ..
+    } = request;
+
+    let pages = stream::try_unfold(Some(0u64), {
+        let state = state.clone();
+        move |page| {
+            let state = state.clone();
+            let query = query.clone();
+            let mut options = options.clone();
+
+            async move {
+                let Some(page) = page else {
+                    return Ok::<_, anyhow::Error>(None);
+                };
+
+                let options = options
+                    .page(Some(page))
+                    .hits_per_page(Some(EXPORT_PAGE_SIZE))
+                    .build()?;
+                let users = state.search.search_users(query, options).await?;
+                let next = (users.len() as u64 == EXPORT_PAGE_SIZE).then_some(page + 1);
+
+                Ok(Some((users, next)))
+            }
+        }
+    });
+
+    pages
+        .map_ok(|users| stream::iter(users.into_iter().map(Ok)))
+        .try_flatten()
+        .try_filter(move |user| {
+            std::future::ready(match resource {
+                ExportResource::Performers => user.is_performer(),
+                ExportResource::Venues => user.is_venue(),
+            })
+        })
+        .and_then(move |user| {
+            let state = state.clone();
+            let fieldset = fieldset.clone();
+
+            async move {
+                let row = match resource {
+                    ExportResource::Performers => {
+                        let performer =
+                            transform_performer(user, &state, &fieldset, &viewer).await?;
+                        fieldset.shape_one(&performer)
+                    }
+                    ExportResource::Venues => {
+                        let venue = transform_venue(user, &state, &fieldset, &viewer).await?;
+                        fieldset.shape_one(&venue)
+                    }
+                };
+
+                row.map_err(|status| anyhow::anyhow!("failed to shape export row: {status}"))
+            }
+        })
+}
+
+/// Encodes rows in the export format. CSV has a fixed set of `columns`, so
+/// a field missing from some rows is left empty in them, and nested values
+/// are written as JSON.
+pub fn encode_rows(
+    format: ExportFormat,
+    columns: Vec<String>,
+    rows: impl Stream<Item = Result<Value>> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes>> + Send + 'static {
+    let mut header = Some(csv_record(columns.iter().map(|c| c.as_str())));
+
+    rows.map_ok(move |row| {
+        let line = match format {
+            ExportFormat::Ndjson => format!("{row}\n"),
+            ExportFormat::Csv => {
+                // Sent with the first row, so each chunk is still one row.
+                let mut line = header.take().unwrap_or_default();
+                let cells: Vec<String> = columns.iter().map(|c| csv_cell(&row[c])).collect();
+                line.push_str(&csv_record(cells.iter().map(|c| c.as_str())));
+
+                line
+            }
+        };
+
+        Bytes::from(line)
+    })
+}
+
+/// Text starting with one of these is taken as a formula by spreadsheets.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) if string.starts_with(FORMULA_PREFIXES) => format!("'{string}"),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// A single RFC 4180 record, quoting cells where needed.
+fn csv_record<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    let mut record = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push_str("\r\n");
+
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn csv(columns: &[&str], rows: Vec<Value>) -> String {
+        let columns = columns.iter().map(|c| c.to_string()).collect();
+        let chunks: Vec<Bytes> = encode_rows(
+            ExportFormat::Csv,
+            columns,
+            stream::iter(rows.into_iter().map(Ok)),
+        )
+        .try_collect()
+        .await
+        .unwrap();
+
+        chunks
+            .iter()
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn every_row_has_the_same_columns() {
+        let body = csv(
+            &["id", "rateEstimate"],
+            vec![
+                json!({ "id": "first" }),
+                json!({ "id": "second", "rateEstimate": 5 }),
+            ],
+        )
+        .await;
+
+        assert_eq!("id,rateEstimate\r\nfirst,\r\nsecond,5\r\n", body);
+    }
+
+    #[tokio::test]
+    async fn formulas_are_written_as_text() {
+        let body = csv(
+            &["bio", "averageAttendance"],
+            vec![json!({ "bio": "=HYPERLINK(\"http://x\")", "averageAttendance": -1 })],
+        )
+        .await;
+
+        assert_eq!(
+            "bio,averageAttendance\r\n\"'=HYPERLINK(\"\"http://x\"\")\",-1\r\n",
+            body
+        );
+    }
+}
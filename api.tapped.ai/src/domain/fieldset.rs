@@ -0,0 +1,187 @@
+use std::collections::BTreeSet;
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// `?fields=id,displayName&include=bookings,reviews` on performer and venue
+/// responses.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FieldsetParams {
+    fields: Option<String>,
+    include: Option<String>,
+}
+
+/// The embedded relations that are loaded from the database on demand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relation {
+    Bookings,
+    Reviews,
+    /// A performer's estimated booking rate. It takes a search and a
+    /// database read per comparable performer, so it is only computed when
+    /// named in `include`.
+    RateEstimate,
+}
+
+impl Relation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::Bookings => "bookings",
+            Relation::Reviews => "reviews",
+            Relation::RateEstimate => "rateEstimate",
+        }
+    }
+
+    /// Whether the relation is loaded when `include` isn't given.
+    fn is_default(&self) -> bool {
+        !matches!(self, Relation::RateEstimate)
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bookings" => Some(Relation::Bookings),
+            "reviews" => Some(Relation::Reviews),
+            "rateEstimate" => Some(Relation::RateEstimate),
+            _ => None,
+        }
+    }
+}
+
+/// Which top-level fields and relations a caller asked for. Without either
+/// parameter the full resource is returned, as it always has been.
+#[derive(Debug, Clone, Default)]
+pub struct Fieldset {
+    fields: Option<BTreeSet<String>>,
+    include: Option<BTreeSet<Relation>>,
+}
+
+fn split(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn parse_include(include: Option<&str>) -> Result<Option<BTreeSet<Relation>>, StatusCode> {
+    include
+        .map(|include| {
+            split(include)
+                .map(|name| {
+                    Relation::parse(name).ok_or_else(|| {
+                        tracing::warn!("unknown relation in include: {name}");
+                        StatusCode::BAD_REQUEST
+                    })
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+impl Fieldset {
+    pub fn parse(params: &FieldsetParams) -> Result<Self, StatusCode> {
+        let include = parse_include(params.include.as_deref())?;
+        let fields = params
+            .fields
+            .as_deref()
+            .map(|fields| split(fields).map(str::to_string).collect());
+
+        Ok(Self { fields, include })
+    }
+
+    /// Like `parse`, but relations are only loaded when they are named in
+    /// `include`.
+    pub fn parse_opt_in(params: &FieldsetParams) -> Result<Self, StatusCode> {
+        let mut fieldset = Self::parse(params)?;
+        fieldset.include.get_or_insert_with(BTreeSet::new);
+
+        Ok(fieldset)
+    }
+
+    /// Every field, and only the relations named in `include`.
+    pub fn include_only(include: Option<&str>) -> Result<Self, StatusCode> {
+        Ok(Self {
+            fields: None,
+            include: Some(parse_include(include)?.unwrap_or_default()),
+        })
+    }
+
+    /// Whether the relation should be fetched at all. It has to be both
+    /// included and, when a sparse fieldset is given, part of it.
+    pub fn includes(&self, relation: Relation) -> bool {
+        self.include
+            .as_ref()
+            .map_or(relation.is_default(), |include| include.contains(&relation))
+            && self
+                .fields
+                .as_ref()
+                .is_none_or(|fields| fields.contains(relation.as_str()))
+    }
+
+    /// Whether a top-level field is in the response: asked for, when
+    /// `fields` is given, and fetched, for relations.
+    pub fn selects(&self, field: &str) -> bool {
+        match Relation::parse(field) {
+            Some(relation) => self.includes(relation),
+            None => self
+                .fields
+                .as_ref()
+                .is_none_or(|fields| fields.contains(field)),
+        }
+    }
+
+    /// Serializes `values` keeping only the requested top-level fields.
+    pub fn shape<T: Serialize>(&self, values: &[T]) -> Result<Vec<Value>, StatusCode> {
+        let mut values = to_values(values)?;
+        self.retain(&mut [&mut values])?;
+
+        Ok(values)
+    }
+
+    pub fn shape_one<T: Serialize>(&self, value: &T) -> Result<Value, StatusCode> {
+        self.shape(std::slice::from_ref(value))
+            .map(|mut values| values.remove(0))
+    }
+
+    /// Drops the fields that weren't asked for from every serialized value
+    /// in a response. Asking for a field that none of them have is a bad
+    /// request.
+    pub fn retain(&self, groups: &mut [&mut Vec<Value>]) -> Result<(), StatusCode> {
+        let Some(fields) = &self.fields else {
+            return Ok(());
+        };
+
+        let mut unknown = fields.clone();
+        let mut empty = true;
+        for value in groups.iter_mut().flat_map(|values| values.iter_mut()) {
+            empty = false;
+            if let Value::Object(object) = value {
+                for key in object.keys() {
+                    unknown.remove(key);
+                }
+                object.retain(|key, _| fields.contains(key));
+            }
+        }
+
+        // Relations that were left out are absent from every value, but
+        // are still valid field names.
+        unknown.remove(Relation::Bookings.as_str());
+        unknown.remove(Relation::Reviews.as_str());
+        unknown.remove(Relation::RateEstimate.as_str());
+        if !empty && !unknown.is_empty() {
+            tracing::warn!("unknown fields requested: {unknown:?}");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn to_values<T: Serialize>(values: &[T]) -> Result<Vec<Value>, StatusCode> {
+    values
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| {
+            tracing::error!("failed to serialize response: {error}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
@@ -0,0 +1,556 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use crate::{
+    data::{
+        database::{is_user_not_found, Database},
+        search::{Search, UserSearchOptionsBuilder},
+        soft_delete::is_user_deleted,
+    },
+    domain::{
+        auth::Caller,
+        models::{
+            booking::GuardedBooking,
+            review::{GuardedReview, Review},
+            tech_rider::TechRider,
+            user::{
+                Bookings, GuardedPerformer, GuardedVenue, Location, Reviews, SocialFollowing,
+                TicketRange, UserModel,
+            },
+        },
+        redaction::Viewer,
+    },
+    state::AppStateDyn,
+};
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    Context, EmptyMutation, EmptySubscription, Object, Result, Schema, ID,
+};
+use axum::{extract::State, Extension, Json};
+use futures::future;
+
+/// Queries nested deeper than this are rejected before they are executed.
+pub const MAX_QUERY_DEPTH: usize = 8;
+/// Every field costs 1 and list fields multiply their children by `limit`,
+/// clamped to [`MAX_LIMIT`].
+pub const MAX_QUERY_COMPLEXITY: usize = 1_000;
+
+const DEFAULT_LIMIT: usize = 10;
+/// Larger `limit`s are clamped to this.
+pub const MAX_LIMIT: usize = 100;
+
+/// The page size a list field is searched with.
+fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+}
+
+pub type TappedSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn build_schema() -> TappedSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+pub async fn graphql_handler(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Extension(schema): Extension<TappedSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    // Loaders are per request so their caches never serve stale data
+    // across requests.
+    let request = request
+        .data(state.database.clone())
+        .data(state.search.clone())
+        .data(Viewer::of(&caller))
+        .data(DataLoader::new(
+            UserLoader(state.database.clone()),
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            BookingLoader(state.database.clone()),
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            ReviewLoader(state.database.clone()),
+            tokio::spawn,
+        ));
+
+    Json(schema.execute(request).await)
+}
+
+/// Whose side of a booking or review to load them for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Party {
+    Performer(String),
+    Booker(String),
+}
+
+pub struct UserLoader(Arc<dyn Database>);
+
+impl Loader<String> for UserLoader {
+    type Value = UserModel;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, UserModel>, Self::Error> {
+        let users = self.0.get_users_by_ids(keys).await.map_err(Arc::new)?;
+
+        Ok(users
+            .into_iter()
+            .map(|user| (user.id.clone(), user))
+            .collect())
+    }
+}
+
+impl Party {
+    /// The performer ids and the booker ids among `keys`.
+    fn split(keys: &[Party]) -> (Vec<String>, Vec<String>) {
+        let mut performer_ids = Vec::new();
+        let mut booker_ids = Vec::new();
+        for party in keys {
+            match party {
+                Party::Performer(id) => performer_ids.push(id.clone()),
+                Party::Booker(id) => booker_ids.push(id.clone()),
+            }
+        }
+
+        (performer_ids, booker_ids)
+    }
+}
+
+/// Runs `query` unless there are no `ids` to run it for.
+async fn unless_empty<T>(
+    ids: &[String],
+    query: impl Future<Output = anyhow::Result<Vec<T>>>,
+) -> anyhow::Result<Vec<T>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    query.await
+}
+
+/// Files each item under its party, giving every key an entry so parties
+/// without any are cached too.
+fn group<T>(keys: &[Party], items: impl IntoIterator<Item = (Party, T)>) -> HashMap<Party, Vec<T>> {
+    let mut grouped: HashMap<Party, Vec<T>> = keys
+        .iter()
+        .map(|party| (party.clone(), Vec::new()))
+        .collect();
+    for (party, item) in items {
+        if let Some(group) = grouped.get_mut(&party) {
+            group.push(item);
+        }
+    }
+
+    grouped
+}
+
+pub struct BookingLoader(Arc<dyn Database>);
+
+impl Loader<Party> for BookingLoader {
+    type Value = Vec<GuardedBooking>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[Party]) -> Result<HashMap<Party, Self::Value>, Self::Error> {
+        let (performer_ids, booker_ids) = Party::split(keys);
+        let (performed, booked) = future::try_join(
+            unless_empty(
+                &performer_ids,
+                self.0.get_bookings_by_performer_ids(&performer_ids),
+            ),
+            unless_empty(&booker_ids, self.0.get_bookings_by_booker_ids(&booker_ids)),
+        )
+        .await
+        .map_err(Arc::new)?;
+
+        let performed = performed
+            .iter()
+            .map(|booking| (Party::Performer(booking.requestee_id.clone()), booking));
+        let booked = booked.iter().filter_map(|booking| {
+            let booker_id = booking.requester_id.clone()?;
+            Some((Party::Booker(booker_id), booking))
+        });
+
+        Ok(group(
+            keys,
+            performed
+                .chain(booked)
+                .map(|(party, booking)| (party, booking.to_guarded())),
+        ))
+    }
+}
+
+pub struct ReviewLoader(Arc<dyn Database>);
+
+impl Loader<Party> for ReviewLoader {
+    type Value = Vec<Review>;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(&self, keys: &[Party]) -> Result<HashMap<Party, Self::Value>, Self::Error> {
+        let (performer_ids, booker_ids) = Party::split(keys);
+        let (of_performers, of_bookers) = future::try_join(
+            unless_empty(
+                &performer_ids,
+                self.0.get_reviews_by_performer_ids(&performer_ids),
+            ),
+            unless_empty(&booker_ids, self.0.get_reviews_by_booker_ids(&booker_ids)),
+        )
+        .await
+        .map_err(Arc::new)?;
+
+        let of_performers = of_performers
+            .into_iter()
+            .map(|review| (Party::Performer(review.performer_id.clone()), review));
+        let of_bookers = of_bookers
+            .into_iter()
+            .map(|review| (Party::Booker(review.booker_id.clone()), review));
+
+        Ok(group(keys, of_performers.chain(of_bookers)))
+    }
+}
+
+/// The party's bookings, counting all of them but listing the `first`.
+async fn load_bookings(
+    ctx: &Context<'_>,
+    party: Party,
+    first: Option<usize>,
+) -> Result<Bookings<GuardedBooking>> {
+    let bookings = ctx
+        .data_unchecked::<DataLoader<BookingLoader>>()
+        .load_one(party)
+        .await?
+        .unwrap_or_default();
+
+    let mut bookings = Bookings::new(bookings);
+    bookings.items.truncate(page_size(first));
+
+    Ok(bookings)
+}
+
+/// The party's reviews, summarizing all of them but listing the `first`.
+async fn load_reviews(
+    ctx: &Context<'_>,
+    party: Party,
+    first: Option<usize>,
+) -> Result<Reviews<GuardedReview>> {
+    let reviews = ctx
+        .data_unchecked::<DataLoader<ReviewLoader>>()
+        .load_one(party)
+        .await?
+        .unwrap_or_default();
+
+    let mut reviews = Reviews::new(&reviews);
+    reviews.items.truncate(page_size(first));
+
+    Ok(reviews)
+}
+
+async fn search_users(
+    ctx: &Context<'_>,
+    query: String,
+    options: UserSearchOptionsBuilder,
+) -> Result<Vec<UserModel>> {
+    let options = options.build()?;
+
+    Ok(ctx
+        .data_unchecked::<Arc<dyn Search>>()
+        .search_users(query, options)
+        .await?)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn performer(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Performer>> {
+        let user = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_one(id.to_string())
+            .await?;
+
+        Ok(user.map(|user| Performer::new(ctx, user)))
+    }
+
+    async fn performer_by_username(
+        &self,
+        ctx: &Context<'_>,
+        username: String,
+    ) -> Result<Option<Performer>> {
+        let user = ctx
+            .data_unchecked::<Arc<dyn Database>>()
+            .get_user_by_username(&username)
+            .await;
+
+        match user {
+            Ok(user) => Ok(Some(Performer::new(ctx, user))),
+            // Like `performer`, missing and deleted users are null, but
+            // failed reads are errors.
+            Err(error) if is_user_not_found(&error) || is_user_deleted(&error) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    #[graphql(complexity = "page_size(limit).saturating_mul(child_complexity)")]
+    async fn search_performers(
+        &self,
+        ctx: &Context<'_>,
+        query: Option<String>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Performer>> {
+        let mut options = UserSearchOptionsBuilder::default();
+        options.hits_per_page(Some(page_size(limit) as u64));
+
+        let users = search_users(ctx, query.unwrap_or_default(), options).await?;
+
+        Ok(users
+            .into_iter()
+            .map(|user| Performer::new(ctx, user))
+            .collect())
+    }
+
+    async fn venue(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Venue>> {
+        let user = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_one(id.to_string())
+            .await?;
+
+        Ok(user.map(|user| Venue::new(ctx, user)))
+    }
+
+    /// Venues within `radius` meters of a point.
+    #[graphql(complexity = "page_size(limit).saturating_mul(child_complexity)")]
+    async fn venues_near(
+        &self,
+        ctx: &Context<'_>,
+        lat: f64,
+        lng: f64,
+        radius: Option<u64>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Venue>> {
+        let mut options = UserSearchOptionsBuilder::default();
+        options
+            .lat(Some(lat))
+            .lng(Some(lng))
+            .radius(Some(radius.unwrap_or(100_000)))
+            .hits_per_page(Some(page_size(limit) as u64));
+
+        let users = search_users(ctx, " ".into(), options).await?;
+
+        Ok(users
+            .into_iter()
+            .map(|user| Venue::new(ctx, user))
+            .collect())
+    }
+}
+
+/// Mirrors `GuardedPerformer`, but only loads bookings and reviews when they
+/// are selected.
+pub struct Performer(GuardedPerformer);
+
+impl Performer {
+    fn new(ctx: &Context<'_>, user: UserModel) -> Self {
+        Self(user.to_guarded_performer(ctx.data_unchecked::<Viewer>(), None, None))
+    }
+}
+
+#[Object]
+impl Performer {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    async fn claimed(&self) -> bool {
+        self.0.claimed
+    }
+
+    async fn verified(&self) -> bool {
+        self.0.verified
+    }
+
+    async fn display_name(&self) -> &str {
+        &self.0.display_name
+    }
+
+    async fn bio(&self) -> &str {
+        &self.0.bio
+    }
+
+    async fn profile_picture_url(&self) -> Option<&str> {
+        self.0.profile_picture_url.as_deref()
+    }
+
+    async fn location(&self) -> Option<&Location> {
+        self.0.location.as_ref()
+    }
+
+    async fn social_following(&self) -> &SocialFollowing {
+        &self.0.social_following
+    }
+
+    async fn press_kit_url(&self) -> Option<&str> {
+        self.0.press_kit_url.as_deref()
+    }
+
+    async fn genres(&self) -> &[String] {
+        &self.0.genres
+    }
+
+    async fn spotify_id(&self) -> Option<&str> {
+        self.0.spotify_id.as_deref()
+    }
+
+    async fn average_ticket_range(&self) -> &TicketRange {
+        &self.0.average_ticket_range
+    }
+
+    async fn average_attendance(&self) -> u32 {
+        self.0.average_attendance
+    }
+
+    #[graphql(complexity = "page_size(first).saturating_mul(child_complexity)")]
+    async fn bookings(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<usize>,
+    ) -> Result<Bookings<GuardedBooking>> {
+        load_bookings(ctx, Party::Performer(self.0.id.clone()), first).await
+    }
+
+    #[graphql(complexity = "page_size(first).saturating_mul(child_complexity)")]
+    async fn reviews(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<usize>,
+    ) -> Result<Reviews<GuardedReview>> {
+        load_reviews(ctx, Party::Performer(self.0.id.clone()), first).await
+    }
+}
+
+/// Mirrors `GuardedVenue`, resolving relations lazily.
+pub struct Venue(GuardedVenue);
+
+impl Venue {
+    fn new(ctx: &Context<'_>, user: UserModel) -> Self {
+        Self(user.to_guarded_venue(ctx.data_unchecked::<Viewer>(), None, None))
+    }
+}
+
+#[Object]
+impl Venue {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    async fn claimed(&self) -> bool {
+        self.0.claimed
+    }
+
+    async fn verified(&self) -> bool {
+        self.0.verified
+    }
+
+    async fn display_name(&self) -> &str {
+        &self.0.display_name
+    }
+
+    async fn bio(&self) -> &str {
+        &self.0.bio
+    }
+
+    async fn profile_picture_url(&self) -> Option<&str> {
+        self.0.profile_picture_url.as_deref()
+    }
+
+    async fn location(&self) -> Option<&Location> {
+        self.0.location.as_ref()
+    }
+
+    async fn genres(&self) -> &[String] {
+        &self.0.genres
+    }
+
+    async fn booking_email(&self) -> Option<&str> {
+        self.0.booking_email.as_deref()
+    }
+
+    async fn capacity(&self) -> Option<u32> {
+        self.0.capacity
+    }
+
+    async fn ideal_performer_profile(&self) -> Option<&str> {
+        self.0.ideal_performer_profile.as_deref()
+    }
+
+    async fn production_info(&self) -> Option<&str> {
+        self.0.production_info.as_deref()
+    }
+
+    async fn front_of_house(&self) -> Option<&str> {
+        self.0.front_of_house.as_deref()
+    }
+
+    async fn monitors(&self) -> Option<&str> {
+        self.0.monitors.as_deref()
+    }
+
+    async fn microphones(&self) -> Option<&str> {
+        self.0.microphones.as_deref()
+    }
+
+    async fn lights(&self) -> Option<&str> {
+        self.0.lights.as_deref()
+    }
+
+    async fn tech_rider(&self) -> &TechRider {
+        &self.0.tech_rider
+    }
+
+    async fn top_performer_ids(&self) -> &[String] {
+        &self.0.top_performer_ids
+    }
+
+    #[graphql(complexity = "DEFAULT_LIMIT * child_complexity")]
+    async fn top_performers(&self, ctx: &Context<'_>) -> Result<Vec<Performer>> {
+        let users = ctx
+            .data_unchecked::<DataLoader<UserLoader>>()
+            .load_many(self.0.top_performer_ids.clone())
+            .await?;
+
+        // Keep the venue's own ordering of its top performers.
+        Ok(self
+            .0
+            .top_performer_ids
+            .iter()
+            .filter_map(|id| users.get(id).cloned())
+            .map(|user| Performer::new(ctx, user))
+            .collect())
+    }
+
+    #[graphql(complexity = "page_size(first).saturating_mul(child_complexity)")]
+    async fn bookings(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<usize>,
+    ) -> Result<Bookings<GuardedBooking>> {
+        load_bookings(ctx, Party::Booker(self.0.id.clone()), first).await
+    }
+
+    #[graphql(complexity = "page_size(first).saturating_mul(child_complexity)")]
+    async fn reviews(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<usize>,
+    ) -> Result<Reviews<GuardedReview>> {
+        load_reviews(ctx, Party::Booker(self.0.id.clone()), first).await
+    }
+}
@@ -0,0 +1,93 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::state::AppStateDyn;
+
+/// A dependency that doesn't answer within this long counts as down.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyHealth {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dependencies {
+    pub database: DependencyHealth,
+    pub search: DependencyHealth,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub status: HealthStatus,
+    pub dependencies: Dependencies,
+}
+
+async fn probe(ping: impl Future<Output = Result<()>>) -> DependencyHealth {
+    let started = Instant::now();
+    let result = tokio::time::timeout(PING_TIMEOUT, ping).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(error)) => Some(error.to_string()),
+        Err(_) => Some(format!("no response within {}ms", PING_TIMEOUT.as_millis())),
+    };
+    if let Some(error) = &error {
+        tracing::warn!("readiness probe failed: {error}");
+    }
+
+    DependencyHealth {
+        status: match error {
+            None => HealthStatus::Ok,
+            Some(_) => HealthStatus::Unavailable,
+        },
+        latency_ms,
+        error,
+    }
+}
+
+/// The process is up and serving requests. Never touches a dependency, so a
+/// Firestore outage doesn't get every instance restarted.
+pub async fn live() -> Json<serde_json::Value> {
+    serde_json::json!({ "status": "ok" }).into()
+}
+
+/// Whether this instance can serve traffic, probing each backend. Responds
+/// with 503 when any of them is unavailable.
+pub async fn ready(State(state): State<AppStateDyn>) -> (StatusCode, Json<Readiness>) {
+    let (database, search) = tokio::join!(probe(state.database.ping()), probe(state.search.ping()));
+
+    let status = if database.status == HealthStatus::Ok && search.status == HealthStatus::Ok {
+        HealthStatus::Ok
+    } else {
+        HealthStatus::Unavailable
+    };
+    let code = match status {
+        HealthStatus::Ok => StatusCode::OK,
+        HealthStatus::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (
+        code,
+        Json(Readiness {
+            status,
+            dependencies: Dependencies { database, search },
+        }),
+    )
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use crate::{
+    configuration::SimilaritySettings,
+    data::search::UserSearchOptionsBuilder,
+    domain::{
+        auth::Caller,
+        matchmaking::{
+            scoring::{score, MatchScore, PerformerHistory, Weights, MAX_DISTANCE_METERS},
+            similarity::similarity,
+        },
+        models::user::{GuardedPerformer, GuardedVenue, UserModel},
+        redaction::Viewer,
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LIMIT: usize = 10;
+pub const MAX_LIMIT: usize = 50;
+/// How many nearby users are scored for each request.
+const CANDIDATES: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct RecommendationParams {
+    limit: Option<usize>,
+    /// Meters around the venue or performer to look for candidates.
+    radius: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendedPerformer {
+    performer: GuardedPerformer,
+    #[serde(flatten)]
+    score: MatchScore,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendedVenue {
+    venue: GuardedVenue,
+    #[serde(flatten)]
+    score: MatchScore,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendations<T> {
+    id: String,
+    recommendations: Vec<T>,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+impl RecommendationParams {
+    fn limit(&self) -> Result<usize, AppError> {
+        match self.limit.unwrap_or(DEFAULT_LIMIT) {
+            limit @ 1..=MAX_LIMIT => Ok(limit),
+            _ => Err(AppError::new(&format!(
+                "limit must be between 1 and {MAX_LIMIT}"
+            ))),
+        }
+    }
+}
+
+/// Users within `radius` of `user`, or anywhere when `user` has no location.
+async fn nearby(
+    state: &AppStateDyn,
+    user: &UserModel,
+    radius: Option<u64>,
+) -> Result<Vec<UserModel>> {
+    let location = user.location();
+    let options = UserSearchOptionsBuilder::default()
+        .hits_per_page(Some(CANDIDATES))
+        .lat(location.map(|location| location.lat))
+        .lng(location.map(|location| location.lng))
+        .radius(Some(radius.unwrap_or(MAX_DISTANCE_METERS as u64)))
+        .build()?;
+
+    state.search.search_users(String::new(), options).await
+}
+
+/// Performers sharing a genre with `performer`, performers near them, and
+/// whoever else the search ranks first, so a similar act elsewhere in another
+/// genre still gets considered.
+async fn similar_candidates(state: &AppStateDyn, performer: &UserModel) -> Result<Vec<UserModel>> {
+    let location = performer.location();
+    let genres = performer.performer_genres();
+    let mut searches = vec![UserSearchOptionsBuilder::default()
+        .hits_per_page(Some(CANDIDATES))
+        .build()?];
+    if !genres.is_empty() {
+        searches.push(
+            UserSearchOptionsBuilder::default()
+                .hits_per_page(Some(CANDIDATES))
+                .genres(Some(genres.to_vec()))
+                .build()?,
+        );
+    }
+    if let Some(location) = location {
+        searches.push(
+            UserSearchOptionsBuilder::default()
+                .hits_per_page(Some(CANDIDATES))
+                .lat(Some(location.lat))
+                .lng(Some(location.lng))
+                .radius(Some(MAX_DISTANCE_METERS as u64))
+                .build()?,
+        );
+    }
+
+    let mut candidates: HashMap<String, UserModel> = HashMap::new();
+    for options in searches {
+        for user in state.search.search_users(String::new(), options).await? {
+            candidates.entry(user.id.clone()).or_insert(user);
+        }
+    }
+
+    Ok(candidates.into_values().collect())
+}
+
+/// The venues of the performer's confirmed gigs, once per gig. Venues are
+/// looked up once per request through `venues`.
+async fn booked_venues(
+    state: &AppStateDyn,
+    performer_id: &str,
+    venues: &mut HashMap<String, Option<UserModel>>,
+) -> Result<Vec<UserModel>> {
+    let bookings = state
+        .database
+        .get_bookings_by_performer_id(performer_id)
+        .await?;
+
+    let mut booked = Vec::new();
+    for venue_id in bookings.into_iter().filter_map(|booking| booking.venue_id) {
+        if !venues.contains_key(&venue_id) {
+            let venue = state.database.get_user_by_id(&venue_id).await.ok();
+            venues.insert(venue_id.clone(), venue);
+        }
+        if let Some(Some(venue)) = venues.get(&venue_id) {
+            booked.push(venue.clone());
+        }
+    }
+
+    Ok(booked)
+}
+
+fn best_first<T>(recommendations: &mut [(MatchScore, T)], id: impl Fn(&T) -> &str) {
+    recommendations.sort_by(|(a, a_user), (b, b_user)| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| id(a_user).cmp(id(b_user)))
+    });
+}
+
+async fn get_user(state: &AppStateDyn, id: &str, what: &str) -> Result<UserModel, AppError> {
+    state.database.get_user_by_id(id).await.map_err(|error| {
+        tracing::warn!("{error}");
+        AppError::user_not_found(what, &error)
+    })
+}
+
+/// Performers near the venue, and its top performers, best match first.
+pub async fn get_recommended_performers(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<Recommendations<RecommendedPerformer>>, AppError> {
+    let limit = params.limit()?;
+    let venue = get_user(&state, &id, "venue").await?;
+    if !venue.is_venue() {
+        return Err(AppError::new("venue not found").with_status(StatusCode::NOT_FOUND));
+    }
+
+    let mut candidates = nearby(&state, &venue, params.radius)
+        .await
+        .map_err(internal_error)?;
+    for performer_id in venue.top_performer_ids() {
+        if candidates
+            .iter()
+            .all(|candidate| &candidate.id != performer_id)
+        {
+            if let Ok(performer) = state.database.get_user_by_id(performer_id).await {
+                candidates.push(performer);
+            }
+        }
+    }
+    candidates.retain(|candidate| {
+        candidate.is_performer() && !candidate.is_deleted() && candidate.id != venue.id
+    });
+
+    let weights = Weights::default();
+    let mut venues = HashMap::new();
+    let mut scored = Vec::with_capacity(candidates.len());
+    for performer in candidates {
+        let booked_venues = booked_venues(&state, &performer.id, &mut venues)
+            .await
+            .map_err(internal_error)?;
+        let history = PerformerHistory {
+            booked_venues: &booked_venues,
+        };
+        scored.push((score(&performer, &venue, &history, &weights), performer));
+    }
+    best_first(&mut scored, |performer| &performer.id);
+
+    let viewer = Viewer::of(&caller);
+    let recommendations = scored
+        .into_iter()
+        .take(limit)
+        .map(|(score, performer)| RecommendedPerformer {
+            performer: performer.to_guarded_performer(&viewer, None, None),
+            score,
+        })
+        .collect();
+
+    Ok(Json(Recommendations {
+        id: venue.id,
+        recommendations,
+    }))
+}
+
+/// Venues near the performer, best match first.
+pub async fn get_recommended_venues(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<Recommendations<RecommendedVenue>>, AppError> {
+    let limit = params.limit()?;
+    let performer = get_user(&state, &id, "performer").await?;
+
+    let mut candidates = nearby(&state, &performer, params.radius)
+        .await
+        .map_err(internal_error)?;
+    candidates.retain(|candidate| {
+        candidate.is_venue() && !candidate.is_deleted() && candidate.id != performer.id
+    });
+
+    let booked_venues = booked_venues(&state, &performer.id, &mut HashMap::new())
+        .await
+        .map_err(internal_error)?;
+    let history = PerformerHistory {
+        booked_venues: &booked_venues,
+    };
+    let weights = Weights::default();
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .map(|venue| (score(&performer, &venue, &history, &weights), venue))
+        .collect();
+    best_first(&mut scored, |venue| &venue.id);
+
+    let viewer = Viewer::of(&caller);
+    let recommendations = scored
+        .into_iter()
+        .take(limit)
+        .map(|(score, venue)| RecommendedVenue {
+            venue: venue.to_guarded_venue(&viewer, None, None),
+            score,
+        })
+        .collect();
+
+    Ok(Json(Recommendations {
+        id: performer.id,
+        recommendations,
+    }))
+}
+
+/// Performers most like this one in genre, label, category, following and
+/// location, most similar first.
+pub async fn get_similar_performers(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Extension(weights): Extension<SimilaritySettings>,
+    Path(id): Path<String>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<Recommendations<RecommendedPerformer>>, AppError> {
+    let limit = params.limit()?;
+    let performer = get_user(&state, &id, "performer").await?;
+    if !performer.is_performer() {
+        return Err(AppError::new("performer not found").with_status(StatusCode::NOT_FOUND));
+    }
+
+    let mut candidates = similar_candidates(&state, &performer)
+        .await
+        .map_err(internal_error)?;
+    candidates.retain(|candidate| {
+        candidate.is_performer() && !candidate.is_deleted() && candidate.id != performer.id
+    });
+
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .map(|candidate| (similarity(&performer, &candidate, &weights), candidate))
+        .collect();
+    best_first(&mut scored, |candidate| &candidate.id);
+
+    let viewer = Viewer::of(&caller);
+    let recommendations = scored
+        .into_iter()
+        .take(limit)
+        .map(|(score, candidate)| RecommendedPerformer {
+            performer: candidate.to_guarded_performer(&viewer, None, None),
+            score,
+        })
+        .collect();
+
+    Ok(Json(Recommendations {
+        id: performer.id,
+        recommendations,
+    }))
+}
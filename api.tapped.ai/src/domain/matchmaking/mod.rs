@@ -0,0 +1,3 @@
+pub mod controller;
+pub mod scoring;
+pub mod similarity;
@@ -0,0 +1,360 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::domain::models::user::UserModel;
+
+/// Past this distance, in meters, a venue and performer are too far apart
+/// for distance to count in the match.
+pub const MAX_DISTANCE_METERS: f64 = 100_000.0;
+/// Gigs at similar venues past this many don't raise the history score
+/// further.
+pub const MAX_SIMILAR_GIGS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Factor {
+    Genre,
+    Audience,
+    Distance,
+    History,
+    Rating,
+    Label,
+    Category,
+}
+
+/// How much each factor counts towards the overall score. They add up to 1.
+#[derive(Debug, Clone, Copy)]
+pub struct Weights {
+    pub genre: f64,
+    pub audience: f64,
+    pub distance: f64,
+    pub history: f64,
+    pub rating: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            genre: 0.3,
+            audience: 0.2,
+            distance: 0.2,
+            history: 0.15,
+            rating: 0.15,
+        }
+    }
+}
+
+/// One factor's share of a match: a score from 0 to 1 and why it got it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactorScore {
+    pub factor: Factor,
+    pub score: f64,
+    pub weight: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchScore {
+    /// The weighted sum of the factor scores.
+    pub score: f64,
+    pub factors: Vec<FactorScore>,
+}
+
+impl MatchScore {
+    /// Combines `(factor, weight, (score, reason))` triples.
+    pub fn new(factors: Vec<(Factor, f64, (f64, String))>) -> Self {
+        let factors: Vec<FactorScore> = factors
+            .into_iter()
+            .map(|(factor, weight, (score, reason))| FactorScore {
+                factor,
+                score: round(score),
+                weight,
+                reason,
+            })
+            .collect();
+        let score = factors
+            .iter()
+            .map(|factor| factor.score * factor.weight)
+            .sum();
+
+        Self {
+            score: round(score),
+            factors,
+        }
+    }
+}
+
+/// What the scorer needs to know about the performer beyond their profile.
+pub struct PerformerHistory<'a> {
+    /// Venues the performer has confirmed gigs at, once per gig.
+    pub booked_venues: &'a [UserModel],
+}
+
+/// Scores how well `performer` fits `venue`.
+pub fn score(
+    performer: &UserModel,
+    venue: &UserModel,
+    history: &PerformerHistory,
+    weights: &Weights,
+) -> MatchScore {
+    MatchScore::new(vec![
+        (Factor::Genre, weights.genre, genre(performer, venue)),
+        (
+            Factor::Audience,
+            weights.audience,
+            audience(performer, venue),
+        ),
+        (
+            Factor::Distance,
+            weights.distance,
+            distance(performer, venue),
+        ),
+        (
+            Factor::History,
+            weights.history,
+            past_gigs(performer, venue, history),
+        ),
+        (Factor::Rating, weights.rating, rating(performer, venue)),
+    ])
+}
+
+fn round(score: f64) -> f64 {
+    (score * 1000.0).round() / 1000.0
+}
+
+fn normalized(genres: &[String]) -> HashSet<String> {
+    genres
+        .iter()
+        .map(|genre| genre.trim().to_lowercase())
+        .filter(|genre| !genre.is_empty())
+        .collect()
+}
+
+/// The share of the two genre lists they have in common (Jaccard index).
+pub(crate) fn genre_overlap(a: &[String], b: &[String]) -> f64 {
+    let (a, b) = (normalized(a), normalized(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// The overlap of two genre lists, naming the genres they share.
+pub(crate) fn shared_genres(a: &[String], b: &[String]) -> (f64, String) {
+    if a.is_empty() || b.is_empty() {
+        return (0.0, "no genres listed".to_string());
+    }
+
+    let mut shared: Vec<String> = normalized(a)
+        .intersection(&normalized(b))
+        .cloned()
+        .collect();
+    shared.sort();
+    let reason = if shared.is_empty() {
+        "no genres in common".to_string()
+    } else {
+        format!("shares {}", shared.join(", "))
+    };
+
+    (genre_overlap(a, b), reason)
+}
+
+fn genre(performer: &UserModel, venue: &UserModel) -> (f64, String) {
+    shared_genres(performer.performer_genres(), venue.venue_genres())
+}
+
+fn audience(performer: &UserModel, venue: &UserModel) -> (f64, String) {
+    let Some(capacity) = venue.capacity().filter(|capacity| *capacity > 0) else {
+        return (0.5, "venue capacity unknown".to_string());
+    };
+    let attendance = performer.average_attendance();
+    let fit = attendance.min(capacity) as f64 / attendance.max(capacity) as f64;
+
+    (
+        fit,
+        format!("draws about {attendance} for a capacity of {capacity}"),
+    )
+}
+
+pub(crate) fn distance(a: &UserModel, b: &UserModel) -> (f64, String) {
+    let (Some(from), Some(to)) = (a.location(), b.location()) else {
+        return (0.0, "location unknown".to_string());
+    };
+    let meters = from.distance_to(to.lat, to.lng);
+
+    (
+        (1.0 - meters / MAX_DISTANCE_METERS).max(0.0),
+        format!("{:.1} km away", meters / 1000.0),
+    )
+}
+
+/// Two venues are similar when they share a genre and are within a factor of
+/// two in size, where both sizes are known.
+fn similar(a: &UserModel, b: &UserModel) -> bool {
+    if a.id == b.id {
+        return true;
+    }
+    let sized_alike = match (a.capacity(), b.capacity()) {
+        (Some(a), Some(b)) => a.max(b) <= a.min(b).saturating_mul(2),
+        _ => true,
+    };
+
+    sized_alike && genre_overlap(a.venue_genres(), b.venue_genres()) > 0.0
+}
+
+fn past_gigs(
+    performer: &UserModel,
+    venue: &UserModel,
+    history: &PerformerHistory,
+) -> (f64, String) {
+    if venue.top_performer_ids().contains(&performer.id) {
+        return (1.0, "one of the venue's top performers".to_string());
+    }
+    let gigs = history
+        .booked_venues
+        .iter()
+        .filter(|booked| similar(booked, venue))
+        .count();
+
+    (
+        gigs.min(MAX_SIMILAR_GIGS) as f64 / MAX_SIMILAR_GIGS as f64,
+        format!("{gigs} past gigs at similar venues"),
+    )
+}
+
+/// Both sides' ratings count: the performer's from bookers, and the venue's
+/// from the performers it has booked.
+fn rating(performer: &UserModel, venue: &UserModel) -> (f64, String) {
+    let rated = [
+        ("performer", performer.performer_rating()),
+        ("venue", venue.booker_rating()),
+    ];
+    let ratings: Vec<(&str, f64, u32)> = rated
+        .into_iter()
+        .filter_map(|(who, rating)| rating.map(|(rating, count)| (who, rating, count)))
+        .collect();
+    if ratings.is_empty() {
+        return (0.5, "no ratings yet".to_string());
+    }
+
+    let score = ratings
+        .iter()
+        .map(|(_, rating, _)| (rating / 5.0).clamp(0.0, 1.0))
+        .sum::<f64>()
+        / ratings.len() as f64;
+    let reason = ratings
+        .iter()
+        .map(|(who, rating, count)| format!("{who} rated {rating:.1} from {count} reviews"))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    (score, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn performer() -> UserModel {
+        UserModel::fixture(
+            "performer",
+            json!({
+                "location": { "placeId": "nyc", "lat": 40.7128, "lng": -74.0060 },
+                // 50,000 followers draw about 200 people.
+                "socialFollowing": { "instagramFollowers": 50_000 },
+                "performerInfo": { "genres": ["Rock", "Jazz"], "rating": 4.0, "reviewCount": 8 },
+            }),
+        )
+    }
+
+    fn venue(id: &str, capacity: u32, genres: &[&str]) -> UserModel {
+        UserModel::fixture(
+            id,
+            json!({
+                "location": { "placeId": "nyc", "lat": 40.7128, "lng": -74.0060 },
+                "venueInfo": { "capacity": capacity, "genres": genres },
+            }),
+        )
+    }
+
+    fn factor(score: &MatchScore, factor: Factor) -> &FactorScore {
+        score.factors.iter().find(|f| f.factor == factor).unwrap()
+    }
+
+    #[test]
+    fn genre_overlap_is_case_insensitive() {
+        let a = ["Rock".to_string(), "jazz".to_string()];
+        let b = ["rock".to_string(), "Pop".to_string()];
+
+        assert_eq!(1.0 / 3.0, genre_overlap(&a, &b));
+        assert_eq!(0.0, genre_overlap(&a, &[]));
+    }
+
+    #[test]
+    fn a_close_fit_scores_well_on_every_factor() {
+        let score = score(
+            &performer(),
+            &venue("venue", 200, &["rock", "jazz"]),
+            &PerformerHistory {
+                booked_venues: &[venue("other", 300, &["rock"])],
+            },
+            &Weights::default(),
+        );
+
+        assert_eq!(1.0, factor(&score, Factor::Genre).score);
+        assert_eq!(1.0, factor(&score, Factor::Audience).score);
+        assert_eq!(1.0, factor(&score, Factor::Distance).score);
+        assert_eq!(0.2, factor(&score, Factor::History).score);
+        assert_eq!(0.8, factor(&score, Factor::Rating).score);
+        assert_eq!("shares jazz, rock", factor(&score, Factor::Genre).reason);
+        assert_eq!(0.85, score.score);
+    }
+
+    #[test]
+    fn mismatches_are_explained() {
+        let far = UserModel::fixture(
+            "far",
+            json!({
+                "location": { "placeId": "la", "lat": 34.0522, "lng": -118.2437 },
+                "venueInfo": { "capacity": 2000, "genres": ["country"] },
+            }),
+        );
+        let score = score(
+            &performer(),
+            &far,
+            &PerformerHistory {
+                booked_venues: &[venue("small", 100, &["country"])],
+            },
+            &Weights::default(),
+        );
+
+        assert_eq!(0.0, factor(&score, Factor::Genre).score);
+        assert_eq!("no genres in common", factor(&score, Factor::Genre).reason);
+        assert_eq!(0.1, factor(&score, Factor::Audience).score);
+        assert_eq!(0.0, factor(&score, Factor::Distance).score);
+        assert_eq!(0.0, factor(&score, Factor::History).score);
+    }
+
+    #[test]
+    fn top_performers_get_full_history_credit() {
+        let venue = UserModel::fixture(
+            "venue",
+            json!({ "venueInfo": { "topPerformerIds": ["performer"] } }),
+        );
+        let score = score(
+            &performer(),
+            &venue,
+            &PerformerHistory { booked_venues: &[] },
+            &Weights::default(),
+        );
+
+        assert_eq!(1.0, factor(&score, Factor::History).score);
+        assert_eq!(0.5, factor(&score, Factor::Audience).score);
+    }
+}
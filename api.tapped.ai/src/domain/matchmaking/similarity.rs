@@ -0,0 +1,110 @@
+use crate::{
+    configuration::SimilaritySettings,
+    domain::{
+        matchmaking::scoring::{distance, shared_genres, Factor, MatchScore},
+        models::user::UserModel,
+    },
+};
+
+/// Audience bands differing by this many orders of magnitude have nothing in
+/// common.
+const MAX_BAND_GAP: f64 = 3.0;
+/// The spread of `PerformerCategory::rank`.
+const MAX_CATEGORY_GAP: f64 = 4.0;
+
+/// Scores how much `candidate` is like `performer`. Weights are scaled to add
+/// up to 1, so scores run from 0 to 1 whatever the configuration.
+pub fn similarity(
+    performer: &UserModel,
+    candidate: &UserModel,
+    weights: &SimilaritySettings,
+) -> MatchScore {
+    let total = weights.total();
+
+    MatchScore::new(vec![
+        (
+            Factor::Genre,
+            weights.genres / total,
+            shared_genres(performer.performer_genres(), candidate.performer_genres()),
+        ),
+        (
+            Factor::Label,
+            weights.label / total,
+            label(performer, candidate),
+        ),
+        (
+            Factor::Category,
+            weights.category / total,
+            category(performer, candidate),
+        ),
+        (
+            Factor::Audience,
+            weights.audience / total,
+            audience(performer, candidate),
+        ),
+        (
+            Factor::Distance,
+            weights.location / total,
+            distance(performer, candidate),
+        ),
+    ])
+}
+
+fn label(performer: &UserModel, candidate: &UserModel) -> (f64, String) {
+    match (performer.label(), candidate.label()) {
+        (Some(a), Some(b)) if a.trim().eq_ignore_ascii_case(b.trim()) => {
+            (1.0, format!("both on {}", a.trim()))
+        }
+        (Some(_), Some(_)) => (0.0, "different labels".to_string()),
+        _ => (0.0, "no label listed".to_string()),
+    }
+}
+
+fn category(performer: &UserModel, candidate: &UserModel) -> (f64, String) {
+    let (a, b) = (
+        performer.performer_category(),
+        candidate.performer_category(),
+    );
+    let gap = a.rank().abs_diff(b.rank()) as f64;
+
+    (
+        1.0 - gap / MAX_CATEGORY_GAP,
+        if a == b {
+            format!("both {a:?}")
+        } else {
+            format!("{a:?} and {b:?}")
+        },
+    )
+}
+
+/// Orders of magnitude of the social following: 0 for under 10, 1 for
+/// under 100, and so on.
+pub fn audience_band(audience: u32) -> u32 {
+    audience.max(1).ilog10()
+}
+
+fn audience(performer: &UserModel, candidate: &UserModel) -> (f64, String) {
+    let (a, b) = (
+        performer.total_audience_size(),
+        candidate.total_audience_size(),
+    );
+    let gap = audience_band(a).abs_diff(audience_band(b)) as f64;
+
+    (
+        (1.0 - gap / MAX_BAND_GAP).max(0.0),
+        format!("followings of {a} and {b}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audience_bands_are_orders_of_magnitude() {
+        assert_eq!(0, audience_band(0));
+        assert_eq!(0, audience_band(9));
+        assert_eq!(1, audience_band(10));
+        assert_eq!(4, audience_band(50_000));
+    }
+}
@@ -1,3 +1,18 @@
+pub mod api_keys;
 pub mod auth;
+pub mod booking_stream;
+pub mod bookings;
+pub mod claims;
 pub mod controller;
+pub mod events;
+pub mod export;
+pub mod fieldset;
+pub mod graphql;
+pub mod health;
+pub mod matchmaking;
 pub mod models;
+pub mod pricing;
+pub mod redaction;
+pub mod reviews;
+pub mod v2;
+pub mod webhooks;
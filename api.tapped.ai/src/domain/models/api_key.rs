@@ -1,12 +1,147 @@
 use ::serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a key may do beyond reading the public API.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiScope {
+    /// Managing other API keys.
+    #[serde(rename = "admin")]
+    Admin,
+    /// Seeing contact details, such as venue booking emails.
+    #[serde(rename = "contacts:read")]
+    ContactsRead,
+    /// Streaming every booking change, not just the caller's own.
+    #[serde(rename = "bookings:read")]
+    BookingsRead,
+}
+
+impl ApiScope {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Admin => "admin",
+            ApiScope::ContactsRead => "contacts:read",
+            ApiScope::BookingsRead => "bookings:read",
+        }
+    }
+}
+
+/// Stored under the hash of the key, so authenticating a request is a point
+/// read and the key itself is never stored. The key is only ever returned
+/// when it is issued.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiKey {
+    /// Only known while the key is being issued, and in legacy documents
+    /// stored under the key itself before keys were hashed.
+    #[serde(default, skip_serializing)]
     pub key: String,
+    /// Hex encoded SHA-256 of the key, and the id of its document. Empty in
+    /// legacy documents.
+    #[serde(default)]
+    pub key_hash: String,
+    /// The last four characters of the key, to tell keys apart.
+    #[serde(default)]
+    pub key_hint: String,
+    /// The handle the key is managed by. Keys created by hand before the
+    /// admin API are given one when they are hashed.
+    #[serde(default)]
+    pub id: String,
     pub user_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<ApiScope>,
+    /// Browser origins this key may be used from. Empty means any origin
+    /// allowed by `cors.allowed_origins`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_origins: Vec<String>,
 
     #[serde(with = "firestore::serialize_as_timestamp")]
     pub timestamp: DateTime<Utc>,
+    #[serde(default, with = "firestore::serialize_as_optional_timestamp")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "firestore::serialize_as_optional_timestamp")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.key_hash.is_empty()
+    }
+
+    /// Fills in the hash and hint from `key`, as keys are stored now. Legacy
+    /// keys without an id get one derived from the hash, so hashing the same
+    /// document twice gives the same id.
+    #[must_use]
+    pub fn hashed(self) -> Self {
+        if self.key.is_empty() {
+            return self;
+        }
+
+        let key_hash = Self::hash(&self.key);
+        let hint_start = self
+            .key
+            .char_indices()
+            .rev()
+            .nth(3)
+            .map_or(0, |(index, _)| index);
+
+        Self {
+            id: if self.id.is_empty() {
+                format!("legacy-{}", &key_hash[..16])
+            } else {
+                self.id
+            },
+            key_hint: self.key[hint_start..].to_string(),
+            key_hash,
+            ..self
+        }
+    }
+
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    pub fn to_guarded(&self) -> GuardedApiKey {
+        GuardedApiKey {
+            id: self.id.clone(),
+            user_id: self.user_id.clone(),
+            name: self.name.clone(),
+            key_hint: self.key_hint.clone(),
+            scopes: self.scopes.clone(),
+            allowed_origins: self.allowed_origins.clone(),
+            created_at: self.timestamp.to_rfc3339(),
+            expires_at: self.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+            revoked_at: self.revoked_at.map(|revoked_at| revoked_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedApiKey {
+    pub id: String,
+    pub user_id: String,
+    pub name: Option<String>,
+    /// The last four characters of the key, to tell keys apart.
+    pub key_hint: String,
+    pub scopes: Vec<ApiScope>,
+    pub allowed_origins: Vec<String>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
 }
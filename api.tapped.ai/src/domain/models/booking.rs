@@ -1,8 +1,10 @@
 use super::user::Location;
+use async_graphql::SimpleObject;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Booking {
     pub id: String,
@@ -47,7 +49,7 @@ impl Booking {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, SimpleObject)]
 #[serde(rename_all = "camelCase")]
 pub struct GuardedBooking {
     pub id: String,
@@ -65,7 +67,7 @@ pub struct GuardedBooking {
     pub reference_event_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum BookingStatus {
     #[default]
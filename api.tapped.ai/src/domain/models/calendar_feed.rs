@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A subscription link to a performer's calendar. Calendar apps can't send
+/// the API key header, so the link carries its own token instead; just its
+/// hash is stored.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    pub id: String,
+    pub performer_id: String,
+    /// The user of the API key the feed was issued with.
+    pub owner_id: String,
+    /// Hex encoded SHA-256 of the token.
+    pub token_hash: String,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, with = "firestore::serialize_as_optional_timestamp")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl CalendarFeed {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    pub fn to_guarded(&self) -> GuardedCalendarFeed {
+        GuardedCalendarFeed {
+            id: self.id.clone(),
+            performer_id: self.performer_id.clone(),
+            created_at: self.timestamp.to_rfc3339(),
+            revoked_at: self.revoked_at.map(|revoked_at| revoked_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedCalendarFeed {
+    pub id: String,
+    pub performer_id: String,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ClaimStatus {
+    /// The token has been emailed and not yet been proven.
+    Pending,
+    /// The token was proven and the profile linked to the claimant.
+    Verified,
+    /// Too many wrong tokens. A new claim has to be started.
+    Failed,
+}
+
+/// An attempt to take over an unclaimed profile. The token proving it is
+/// only ever emailed to the profile's contact; just its hash is stored.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileClaim {
+    pub id: String,
+    /// The unclaimed profile.
+    pub user_id: String,
+    /// The user the profile is linked to once the claim is verified.
+    pub claimant_id: String,
+    /// Where the token was sent.
+    pub email: String,
+    /// Hex encoded SHA-256 of the token.
+    pub token_hash: String,
+    /// Wrong tokens tried so far.
+    #[serde(default)]
+    pub attempts: u32,
+    pub status: ClaimStatus,
+    /// When the latest token was emailed.
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, with = "firestore::serialize_as_optional_timestamp")]
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl ProfileClaim {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn to_guarded(&self) -> GuardedProfileClaim {
+        GuardedProfileClaim {
+            id: self.id.clone(),
+            user_id: self.user_id.clone(),
+            sent_to: mask_email(&self.email),
+            status: self.status,
+            created_at: self.timestamp.to_rfc3339(),
+            expires_at: self.expires_at.to_rfc3339(),
+            verified_at: self.verified_at.map(|verified_at| verified_at.to_rfc3339()),
+        }
+    }
+}
+
+/// `j***@example.com`, enough for the claimant to recognise the address
+/// without handing it out.
+fn mask_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first = local.chars().next().map(String::from).unwrap_or_default();
+            format!("{first}***@{domain}")
+        }
+        None => "***".to_string(),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedProfileClaim {
+    pub id: String,
+    pub user_id: String,
+    /// The masked address the token was emailed to.
+    pub sent_to: String,
+    pub status: ClaimStatus,
+    pub created_at: String,
+    pub expires_at: String,
+    pub verified_at: Option<String>,
+}
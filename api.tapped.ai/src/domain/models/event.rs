@@ -0,0 +1,74 @@
+use super::user::Location;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A show at a venue. Bookings for it point back here through
+/// `Booking::reference_event_id`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub venue_id: Option<String>,
+    pub location: Option<Location>,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub start_time: DateTime<Utc>,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub end_time: DateTime<Utc>,
+    /// The billed performers, headliner first. Performers with a confirmed
+    /// booking for the event are part of the lineup even when missing here.
+    #[serde(default)]
+    pub performer_ids: Vec<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    pub ticket_url: Option<String>,
+    pub event_url: Option<String>,
+    pub flier_url: Option<String>,
+}
+
+impl Event {
+    pub fn to_guarded(&self) -> GuardedEvent {
+        GuardedEvent {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            venue_id: self.venue_id.clone(),
+            location: self.location.clone(),
+            start_time: self.start_time.to_rfc3339(),
+            end_time: self.end_time.to_rfc3339(),
+            performer_ids: self.performer_ids.clone(),
+            genres: self.genres.clone(),
+            ticket_url: self.ticket_url.clone(),
+            event_url: self.event_url.clone(),
+            flier_url: self.flier_url.clone(),
+        }
+    }
+
+    /// Whether the event lists any of `genres`, ignoring case.
+    pub fn has_any_genre(&self, genres: &[String]) -> bool {
+        self.genres.iter().any(|genre| {
+            genres
+                .iter()
+                .any(|wanted| wanted.trim().eq_ignore_ascii_case(genre.trim()))
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedEvent {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub venue_id: Option<String>,
+    pub location: Option<Location>,
+    pub start_time: String,
+    pub end_time: String,
+    pub performer_ids: Vec<String>,
+    pub genres: Vec<String>,
+    pub ticket_url: Option<String>,
+    pub event_url: Option<String>,
+    pub flier_url: Option<String>,
+}
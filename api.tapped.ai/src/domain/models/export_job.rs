@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::export::{ExportFormat, ExportResource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A background export. Stored so any instance can report on it and serve
+/// its file, which is kept in the export storage under `file_name`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: String,
+    /// The user of the API key the export was started with.
+    pub owner_id: String,
+    pub resource: ExportResource,
+    pub format: ExportFormat,
+    pub status: ExportJobStatus,
+    /// Rows written so far.
+    pub rows: u64,
+    pub error: Option<String>,
+    pub file_name: String,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, with = "firestore::serialize_as_optional_timestamp")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl ExportJob {
+    pub fn to_guarded(&self) -> GuardedExportJob {
+        GuardedExportJob {
+            id: self.id.clone(),
+            resource: self.resource,
+            format: self.format,
+            status: self.status,
+            rows: self.rows,
+            error: self.error.clone(),
+            created_at: self.timestamp.to_rfc3339(),
+            completed_at: self.completed_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedExportJob {
+    pub id: String,
+    pub resource: ExportResource,
+    pub format: ExportFormat,
+    pub status: ExportJobStatus,
+    pub rows: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+}
@@ -1,4 +1,10 @@
 pub mod api_key;
 pub mod booking;
+pub mod calendar_feed;
+pub mod claim;
+pub mod event;
+pub mod export_job;
 pub mod review;
+pub mod tech_rider;
 pub mod user;
+pub mod webhook;
@@ -1,7 +1,15 @@
+use async_graphql::SimpleObject;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Every summary starts out as if it had this many reviews at
+/// `PRIOR_RATING`, so a single five-star review doesn't top the charts.
+pub const PRIOR_WEIGHT: f64 = 5.0;
+pub const PRIOR_RATING: f64 = 3.0;
+/// A review this old counts half as much towards the recent rating.
+pub const RECENCY_HALF_LIFE_DAYS: f64 = 180.0;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Review {
     pub id: String,
@@ -15,9 +23,18 @@ pub struct Review {
 
     #[serde(rename = "type")]
     pub review_type: ReviewType,
+    /// Reviews written before moderation existed have no status and count as
+    /// approved.
+    #[serde(default)]
+    pub moderation_status: ModerationStatus,
 }
 
 impl Review {
+    /// Only approved reviews are listed or announced.
+    pub fn is_published(&self) -> bool {
+        self.moderation_status == ModerationStatus::Approved
+    }
+
     pub fn to_guarded(&self) -> GuardedReview {
         GuardedReview {
             id: self.id.clone(),
@@ -30,7 +47,7 @@ impl Review {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, SimpleObject)]
 #[serde(rename_all = "camelCase")]
 pub struct GuardedReview {
     pub id: String,
@@ -41,9 +58,176 @@ pub struct GuardedReview {
     pub text: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ReviewType {
     Performer,
     Booker,
 }
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ModerationStatus {
+    Pending,
+    #[default]
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct StarCount {
+    pub stars: u8,
+    pub count: usize,
+}
+
+/// Aggregates of a set of reviews. Ratings are `None` when there are no
+/// reviews rather than 0, which would read as the worst possible rating.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewSummary {
+    /// The plain mean.
+    pub average: Option<f64>,
+    /// The mean pulled towards `PRIOR_RATING`, less so the more reviews
+    /// there are. Better for ranking than `average`.
+    pub bayesian_average: Option<f64>,
+    /// The mean with each review weighted by how recent it is.
+    pub recent_average: Option<f64>,
+    /// How many reviews gave each number of stars, from 1 to 5. Ratings are
+    /// rounded to the nearest star.
+    pub histogram: Vec<StarCount>,
+}
+
+fn round(rating: f64) -> f64 {
+    (rating * 100.0).round() / 100.0
+}
+
+impl ReviewSummary {
+    /// Reviews whose rating isn't a finite number are left out.
+    pub fn new(reviews: &[Review], now: DateTime<Utc>) -> Self {
+        let reviews: Vec<&Review> = reviews
+            .iter()
+            .filter(|review| review.overall_rating.is_finite())
+            .collect();
+        let mut histogram: Vec<StarCount> =
+            (1..=5).map(|stars| StarCount { stars, count: 0 }).collect();
+        for review in &reviews {
+            let stars = review.overall_rating.round().clamp(1.0, 5.0) as usize;
+            histogram[stars - 1].count += 1;
+        }
+
+        if reviews.is_empty() {
+            return Self {
+                average: None,
+                bayesian_average: None,
+                recent_average: None,
+                histogram,
+            };
+        }
+
+        let count = reviews.len() as f64;
+        let total: f64 = reviews.iter().map(|review| review.overall_rating).sum();
+        let (weighted_total, total_weight) =
+            reviews.iter().fold((0.0, 0.0), |(sum, weights), review| {
+                // Reviews dated in the future count as brand new.
+                let age_days = (now - review.timestamp).num_seconds().max(0) as f64 / 86_400.0;
+                let weight = 0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+
+                (sum + weight * review.overall_rating, weights + weight)
+            });
+
+        Self {
+            average: Some(round(total / count)),
+            bayesian_average: Some(round(
+                (PRIOR_WEIGHT * PRIOR_RATING + total) / (PRIOR_WEIGHT + count),
+            )),
+            recent_average: Some(round(weighted_total / total_weight)),
+            histogram,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn review(rating: f64, days_ago: i64) -> Review {
+        Review {
+            id: format!("{rating}-{days_ago}"),
+            booker_id: "booker".into(),
+            performer_id: "performer".into(),
+            booking_id: "booking".into(),
+            timestamp: now() - Duration::days(days_ago),
+            overall_rating: rating,
+            overall_review: "".into(),
+            review_type: ReviewType::Performer,
+            moderation_status: ModerationStatus::Approved,
+        }
+    }
+
+    fn counts(summary: &ReviewSummary) -> Vec<usize> {
+        summary.histogram.iter().map(|star| star.count).collect()
+    }
+
+    #[test]
+    fn no_reviews_have_no_rating() {
+        let summary = ReviewSummary::new(&[], now());
+
+        assert_eq!(None, summary.average);
+        assert_eq!(None, summary.bayesian_average);
+        assert_eq!(None, summary.recent_average);
+        assert_eq!(vec![0, 0, 0, 0, 0], counts(&summary));
+    }
+
+    #[test]
+    fn ratings_that_are_not_numbers_are_left_out() {
+        let summary = ReviewSummary::new(
+            &[
+                review(f64::NAN, 0),
+                review(f64::INFINITY, 0),
+                review(4.0, 0),
+            ],
+            now(),
+        );
+
+        assert_eq!(Some(4.0), summary.average);
+        assert_eq!(vec![0, 0, 0, 1, 0], counts(&summary));
+
+        let summary = ReviewSummary::new(&[review(f64::NAN, 0)], now());
+        assert_eq!(None, summary.average);
+        assert_eq!(vec![0, 0, 0, 0, 0], counts(&summary));
+    }
+
+    #[test]
+    fn few_reviews_are_pulled_towards_the_prior() {
+        let summary = ReviewSummary::new(&[review(5.0, 0)], now());
+
+        assert_eq!(Some(5.0), summary.average);
+        // (5 * 3 + 5) / 6
+        assert_eq!(Some(3.33), summary.bayesian_average);
+        assert_eq!(vec![0, 0, 0, 0, 1], counts(&summary));
+    }
+
+    #[test]
+    fn recent_reviews_count_for_more() {
+        let summary =
+            ReviewSummary::new(&[review(2.0, 360), review(4.0, 180), review(5.0, 0)], now());
+
+        assert_eq!(Some(3.67), summary.average);
+        // Weights of 1/4, 1/2 and 1.
+        assert_eq!(Some(4.29), summary.recent_average);
+        assert_eq!(vec![0, 1, 0, 1, 1], counts(&summary));
+    }
+
+    #[test]
+    fn ratings_are_rounded_into_star_buckets() {
+        let summary = ReviewSummary::new(&[review(4.5, 0), review(1.2, 0), review(0.0, 0)], now());
+
+        assert_eq!(vec![2, 0, 0, 0, 1], counts(&summary));
+    }
+}
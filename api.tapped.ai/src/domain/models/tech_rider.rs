@@ -0,0 +1,355 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+
+const FEET_TO_METERS: f64 = 0.3048;
+
+/// A piece of kit and how many of it there are.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct Equipment {
+    pub name: String,
+    pub quantity: u32,
+}
+
+/// Stage size in meters.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct StageDimensions {
+    pub width: f64,
+    pub depth: f64,
+}
+
+/// What a venue has for production, in a form that can be filtered on.
+///
+/// Venues that haven't filled one in get one parsed from their free-text
+/// production fields. Anything the parser can't make out is left empty
+/// rather than guessed at.
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, SimpleObject)]
+#[serde(rename_all = "camelCase")]
+pub struct TechRider {
+    /// The front-of-house desk, e.g. "Midas M32".
+    pub mixing_desk: Option<String>,
+    /// Input channels on the desk.
+    pub channels: Option<u32>,
+    pub monitor_mixes: Option<u32>,
+    pub wedges: Option<u32>,
+    #[serde(default)]
+    pub in_ear_monitors: bool,
+    #[serde(default)]
+    pub microphones: Vec<Equipment>,
+    #[serde(default)]
+    pub lighting: Vec<String>,
+    pub stage: Option<StageDimensions>,
+    #[serde(default)]
+    pub backline: Vec<String>,
+    #[serde(default)]
+    pub sound_engineer: bool,
+}
+
+/// The free-text production fields of a venue.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProductionText<'a> {
+    pub production_info: Option<&'a str>,
+    pub front_of_house: Option<&'a str>,
+    pub monitors: Option<&'a str>,
+    pub microphones: Option<&'a str>,
+    pub lights: Option<&'a str>,
+}
+
+impl TechRider {
+    pub fn parse(text: ProductionText) -> Self {
+        let production = text.production_info.unwrap_or_default();
+        let front_of_house = text.front_of_house.unwrap_or_default();
+        let monitors = text.monitors.unwrap_or_default();
+        let everything = [
+            production,
+            front_of_house,
+            monitors,
+            text.microphones.unwrap_or_default(),
+            text.lights.unwrap_or_default(),
+        ]
+        .join("\n")
+        .to_lowercase();
+
+        Self {
+            mixing_desk: items(front_of_house)
+                .into_iter()
+                .next()
+                .filter(|desk| !desk.chars().next().is_some_and(|c| c.is_ascii_digit())),
+            channels: count_of(front_of_house, &["ch", "channel"])
+                .or_else(|| count_of(production, &["ch", "channel"])),
+            monitor_mixes: count_of(monitors, &["mix"]),
+            wedges: count_of(monitors, &["wedge"]),
+            in_ear_monitors: ["iem", "in-ear", "in ear", "inear"]
+                .iter()
+                .any(|term| everything.contains(term)),
+            microphones: items(text.microphones.unwrap_or_default())
+                .iter()
+                .map(|item| equipment(item))
+                .collect(),
+            lighting: items(text.lights.unwrap_or_default()),
+            stage: stage(production),
+            backline: backline(production),
+            sound_engineer: everything.contains("engineer"),
+        }
+    }
+
+    /// Whether the backline has something matching each of `wanted`.
+    pub fn has_backline(&self, wanted: &[String]) -> bool {
+        wanted.iter().all(|wanted| {
+            let wanted = wanted.to_lowercase();
+            self.backline
+                .iter()
+                .any(|item| item.to_lowercase().contains(&wanted))
+        })
+    }
+}
+
+/// Splits a free-text list on commas, semicolons and new lines.
+fn items(text: &str) -> Vec<String> {
+    text.split([',', ';', '\n'])
+        .map(|item| item.trim().trim_end_matches('.').trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Lowercase words, with numbers split from the letters they're stuck to,
+/// so "32ch" and "6x4m" read as "32 ch" and "6 x 4 m".
+fn words(text: &str) -> Vec<String> {
+    let mut spaced = String::with_capacity(text.len());
+    let mut previous: Option<char> = None;
+    for c in text.to_lowercase().chars() {
+        let c = if matches!(c, '-' | '×' | '(' | ')' | ':') {
+            ' '
+        } else {
+            c
+        };
+        if let Some(previous) = previous {
+            let digit_to_letter = previous.is_ascii_digit() && c.is_alphabetic();
+            let letter_to_digit = previous.is_alphabetic() && c.is_ascii_digit();
+            if digit_to_letter || letter_to_digit || c == '\'' {
+                spaced.push(' ');
+            }
+        }
+        spaced.push(c);
+        previous = Some(c);
+    }
+
+    spaced
+        .split_whitespace()
+        .map(|word| word.trim_end_matches('.'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The number right before a word starting with one of `nouns`, as in
+/// "6 wedges" or "32ch".
+fn count_of(text: &str, nouns: &[&str]) -> Option<u32> {
+    let words = words(text);
+    words.windows(2).find_map(|pair| {
+        nouns
+            .iter()
+            .any(|noun| pair[1].starts_with(noun))
+            .then(|| pair[0].parse().ok())
+            .flatten()
+    })
+}
+
+/// "SM58 x8", "SM58 x 8", "8x SM58" and "8 SM58" are all eight SM58s.
+fn equipment(item: &str) -> Equipment {
+    let tokens: Vec<&str> = item.split_whitespace().collect();
+    let quantity_at = |index: usize| -> Option<u32> {
+        let token = tokens.get(index)?.to_lowercase();
+        let digits = token.trim_start_matches('x').trim_end_matches('x');
+        let after_x = index > 0 && tokens[index - 1].eq_ignore_ascii_case("x");
+        if digits == token && index != 0 && !after_x {
+            return None;
+        }
+        digits.parse().ok()
+    };
+
+    let found = if let Some(quantity) = quantity_at(tokens.len().saturating_sub(1)) {
+        Some((tokens.len() - 1, quantity))
+    } else {
+        quantity_at(0).map(|quantity| (0, quantity))
+    };
+    match found {
+        Some((index, quantity)) if tokens.len() > 1 => Equipment {
+            name: tokens
+                .iter()
+                .enumerate()
+                .filter(|(i, token)| *i != index && !token.eq_ignore_ascii_case("x"))
+                .map(|(_, token)| *token)
+                .collect::<Vec<_>>()
+                .join(" "),
+            quantity,
+        },
+        _ => Equipment {
+            name: item.to_string(),
+            quantity: 1,
+        },
+    }
+}
+
+fn to_meters(value: f64, unit: Option<&str>) -> Option<f64> {
+    match unit {
+        Some("m" | "meter" | "meters" | "metre" | "metres") | None => Some(value),
+        Some("ft" | "feet" | "foot" | "'") => Some(value * FEET_TO_METERS),
+        _ => None,
+    }
+}
+
+/// Dimensions written like "stage 6m x 4m", "6x4 m stage" or
+/// "20' by 16' stage", read as width by depth.
+fn stage(text: &str) -> Option<StageDimensions> {
+    items(text)
+        .into_iter()
+        .flat_map(|item| item.split(". ").map(str::to_string).collect::<Vec<_>>())
+        .filter(|sentence| sentence.to_lowercase().contains("stage"))
+        .find_map(|sentence| {
+            let words = words(&sentence);
+            (0..words.len()).find_map(|start| {
+                let mut rest = words[start..].iter().map(String::as_str).peekable();
+                let width: f64 = rest.next()?.parse().ok()?;
+                let width_unit = rest.next_if(|word| to_meters(1.0, Some(word)).is_some());
+                if !matches!(rest.next()?, "x" | "by") {
+                    return None;
+                }
+                let depth: f64 = rest.next()?.parse().ok()?;
+                let unit = rest
+                    .next()
+                    .filter(|word| to_meters(1.0, Some(word)).is_some())
+                    .or(width_unit)?;
+                let round = |meters: f64| (meters * 10.0).round() / 10.0;
+
+                Some(StageDimensions {
+                    width: round(to_meters(width, Some(width_unit.unwrap_or(unit)))?),
+                    depth: round(to_meters(depth, Some(unit))?),
+                })
+            })
+        })
+}
+
+/// The list after "backline", e.g. "Backline: drum kit, bass amp and
+/// keyboard stand."
+fn backline(text: &str) -> Vec<String> {
+    let lower = text.to_ascii_lowercase();
+    let Some(start) = lower.find("backline") else {
+        return vec![];
+    };
+    let after = &text[start + "backline".len()..];
+    let list = after.split(['.', '\n']).next().unwrap_or_default();
+    let list = list
+        .trim_start_matches([':', ' ', '-'])
+        .trim_start_matches("includes")
+        .trim_start_matches("include");
+
+    list.split([',', ';'])
+        .flat_map(|item| item.split(" and "))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_usual_way_venues_describe_their_kit() {
+        let rider = TechRider::parse(ProductionText {
+            production_info: Some(
+                "Full PA, in-house sound engineer. Stage 6m x 4m. Backline: drum kit, bass amp and keyboard stand.",
+            ),
+            front_of_house: Some("Midas M32, 32 channels"),
+            monitors: Some("6 wedges, 4 mixes, IEM on request"),
+            microphones: Some("SM58 x8, 6x SM57, Beta 52"),
+            lights: Some("LED wash with DMX control"),
+        });
+
+        assert_eq!(Some("Midas M32".to_string()), rider.mixing_desk);
+        assert_eq!(Some(32), rider.channels);
+        assert_eq!(Some(4), rider.monitor_mixes);
+        assert_eq!(Some(6), rider.wedges);
+        assert!(rider.in_ear_monitors);
+        assert!(rider.sound_engineer);
+        assert_eq!(
+            vec![
+                Equipment {
+                    name: "SM58".into(),
+                    quantity: 8
+                },
+                Equipment {
+                    name: "SM57".into(),
+                    quantity: 6
+                },
+                Equipment {
+                    name: "Beta 52".into(),
+                    quantity: 1
+                },
+            ],
+            rider.microphones
+        );
+        assert_eq!(vec!["LED wash with DMX control"], rider.lighting);
+        assert_eq!(
+            Some(StageDimensions {
+                width: 6.0,
+                depth: 4.0
+            }),
+            rider.stage
+        );
+        assert_eq!(
+            vec!["drum kit", "bass amp", "keyboard stand"],
+            rider.backline
+        );
+        assert!(rider.has_backline(&["Drum".into(), "bass".into()]));
+        assert!(!rider.has_backline(&["guitar amp".into()]));
+    }
+
+    #[test]
+    fn reads_compact_and_imperial_forms() {
+        assert_eq!(
+            Equipment {
+                name: "SM58".into(),
+                quantity: 8
+            },
+            equipment("SM58 x 8")
+        );
+        assert_eq!(Some(24), count_of("Allen & Heath SQ5 (24ch)", &["ch"]));
+        assert_eq!(Some(16), count_of("16-channel analog desk", &["channel"]));
+        assert_eq!(
+            Some(StageDimensions {
+                width: 6.1,
+                depth: 4.9
+            }),
+            stage("20' by 16' stage")
+        );
+        assert_eq!(
+            Some(StageDimensions {
+                width: 8.0,
+                depth: 5.0
+            }),
+            stage("8x5m stage with risers")
+        );
+    }
+
+    #[test]
+    fn missing_or_vague_text_leaves_fields_empty() {
+        assert_eq!(
+            TechRider::default(),
+            TechRider::parse(ProductionText::default())
+        );
+
+        let rider = TechRider::parse(ProductionText {
+            production_info: Some("Great sound, big stage"),
+            monitors: Some("wedges"),
+            ..Default::default()
+        });
+        assert_eq!(None, rider.wedges);
+        assert_eq!(None, rider.stage);
+        assert!(!rider.in_ear_monitors);
+    }
+}
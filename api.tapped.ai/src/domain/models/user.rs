@@ -1,22 +1,47 @@
-use super::{booking::GuardedBooking, review::GuardedReview};
-// use chrono::{DateTime, Utc};
+use super::{
+    booking::GuardedBooking,
+    review::{GuardedReview, Review, ReviewSummary},
+    tech_rider::{ProductionText, TechRider},
+};
+use crate::domain::{
+    pricing::RateEstimate,
+    redaction::{Field, Redaction, Viewer},
+};
+use async_graphql::SimpleObject;
+use chrono::Utc;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A price range in US cents.
+#[derive(Debug, Deserialize, Serialize, SimpleObject, JsonSchema)]
 pub struct TicketRange {
-    min: u64,
-    max: u64,
+    pub min: u64,
+    pub max: u64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, SimpleObject, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Location {
-    place_id: String,
-    lat: f64,
-    lng: f64,
+    pub place_id: String,
+    pub lat: f64,
+    pub lng: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+impl Location {
+    /// Great-circle distance to `(lat, lng)` in meters.
+    pub fn distance_to(&self, lat: f64, lng: f64) -> f64 {
+        const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+        let (lat1, lat2) = (self.lat.to_radians(), lat.to_radians());
+        let d_lat = (lat - self.lat).to_radians();
+        let d_lng = (lng - self.lng).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lng / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, SimpleObject, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SocialFollowing {
     youtube_channel_id: Option<String>,
@@ -44,14 +69,30 @@ pub struct SocialFollowing {
     twitch_followers: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl SocialFollowing {
+    /// Only the follower counts, without the handles and links.
+    pub fn counts_only(&self) -> Self {
+        Self {
+            tiktok_followers: self.tiktok_followers,
+            instagram_followers: self.instagram_followers,
+            twitter_followers: self.twitter_followers,
+            facebook_followers: self.facebook_followers,
+            soundcloud_followers: self.soundcloud_followers,
+            audius_followers: self.audius_followers,
+            twitch_followers: self.twitch_followers,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BookerInfo {
     rating: Option<f64>,
     #[serde(default)]
     review_count: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum PerformerCategory {
     #[default]
@@ -63,7 +104,20 @@ pub enum PerformerCategory {
 }
 
 impl PerformerCategory {
-    fn ticket_price_range(&self) -> TicketRange {
+    /// Position from `Undiscovered` (0) to `Legendary` (4).
+    pub fn rank(&self) -> u8 {
+        match self {
+            PerformerCategory::Undiscovered => 0,
+            PerformerCategory::Emerging => 1,
+            PerformerCategory::HometownHero => 2,
+            PerformerCategory::Mainstream => 3,
+            PerformerCategory::Legendary => 4,
+        }
+    }
+
+    /// A fixed range for everyone in the category. Only used where there's
+    /// nothing better to go on; see `domain::pricing`.
+    pub fn ticket_price_range(&self) -> TicketRange {
         match self {
             PerformerCategory::Undiscovered => TicketRange { min: 0, max: 1000 },
             PerformerCategory::Emerging => TicketRange {
@@ -103,7 +157,7 @@ pub struct PerformerInfo {
     spotify_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct VenueInfo {
     #[serde(default)]
@@ -117,11 +171,14 @@ pub struct VenueInfo {
     monitors: Option<String>,
     microphones: Option<String>,
     lights: Option<String>,
+    /// Filled in by venues that have given one; otherwise parsed from the
+    /// fields above.
+    tech_rider: Option<TechRider>,
     #[serde(default)]
     top_performer_ids: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 struct EmailNotifications {
     #[serde(default)]
@@ -134,7 +191,7 @@ struct EmailNotifications {
     direct_messages: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PushNotifications {
     #[serde(default)]
@@ -147,13 +204,45 @@ pub struct PushNotifications {
     direct_messages: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+fn shown() -> bool {
+    true
+}
+
+/// What the user lets API consumers see. Everything is shown until they
+/// say otherwise; see `domain::redaction` for who sees what.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacySettings {
+    #[serde(default = "shown")]
+    pub show_location: bool,
+    #[serde(default = "shown")]
+    pub show_social_handles: bool,
+    #[serde(default = "shown")]
+    pub show_contact_details: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            show_location: true,
+            show_social_handles: true,
+            show_contact_details: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UserModel {
     pub id: String,
     pub email: String,
     #[serde(default)]
     unclaimed: bool,
+    /// Set once someone proved they own the profile through a claim.
+    #[serde(default)]
+    verified: bool,
+    /// The user a verified claim linked this profile to.
+    claimed_by: Option<String>,
     // #[serde(with = "firestore::serialize_as_timestamp")]
     // timestamp: DateTime<Utc>,
     pub username: String,
@@ -172,6 +261,8 @@ pub struct UserModel {
     email_notifications: EmailNotifications,
     #[serde(default)]
     push_notifications: PushNotifications,
+    #[serde(default)]
+    privacy: PrivacySettings,
     deleted: bool,
     #[serde(default)]
     social_following: SocialFollowing,
@@ -180,6 +271,163 @@ pub struct UserModel {
 }
 
 impl UserModel {
+    /// A user for tests, built from its Firestore JSON shape with the
+    /// required fields filled in. Not behind `cfg(test)` so the integration
+    /// tests can use it too.
+    #[doc(hidden)]
+    pub fn fixture(id: &str, fields: serde_json::Value) -> Self {
+        let mut value = serde_json::json!({
+            "id": id,
+            "email": format!("{id}@tapped.ai"),
+            "username": id,
+            "deleted": false,
+        });
+        if let (Some(value), Some(fields)) = (value.as_object_mut(), fields.as_object()) {
+            value.extend(fields.clone());
+        }
+
+        serde_json::from_value(value).expect("Failed to build user")
+    }
+
+    pub fn display_name(&self) -> &str {
+        &self.artist_name
+    }
+
+    pub fn bio(&self) -> &str {
+        &self.bio
+    }
+
+    pub fn occupations(&self) -> &[String] {
+        &self.occupations
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    pub fn is_unclaimed(&self) -> bool {
+        self.unclaimed
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    pub fn claimed_by(&self) -> Option<&str> {
+        self.claimed_by.as_deref()
+    }
+
+    /// Where claim tokens are emailed: a venue's booking email, or else the
+    /// account email.
+    pub fn contact_email(&self) -> Option<&str> {
+        self.venue_info
+            .as_ref()
+            .and_then(|info| info.booking_email.as_deref())
+            .into_iter()
+            .chain([self.email.as_str()])
+            .map(str::trim)
+            .find(|email| email.contains('@'))
+    }
+
+    /// Links the profile to `claimant_id` after a verified claim.
+    pub fn claim(&mut self, claimant_id: &str) {
+        self.unclaimed = false;
+        self.verified = true;
+        self.claimed_by = Some(claimant_id.to_string());
+    }
+
+    pub fn privacy(&self) -> &PrivacySettings {
+        &self.privacy
+    }
+
+    /// Whether the user has asked to hear about booking requests by email.
+    pub fn takes_booking_requests_by_email(&self) -> bool {
+        self.email_notifications.booking_requests
+    }
+
+    pub fn is_performer(&self) -> bool {
+        self.performer_info.is_some()
+    }
+
+    pub fn is_venue(&self) -> bool {
+        self.venue_info.is_some()
+    }
+
+    pub fn performer_genres(&self) -> &[String] {
+        self.performer_info
+            .as_ref()
+            .map_or(&[], |info| info.genres.as_slice())
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.performer_info
+            .as_ref()
+            .map(|info| info.label.as_str())
+            .filter(|label| !label.is_empty())
+    }
+
+    pub fn venue_genres(&self) -> &[String] {
+        self.venue_info
+            .as_ref()
+            .map_or(&[], |info| info.genres.as_slice())
+    }
+
+    /// The venue's structured tech rider, or one parsed from its free-text
+    /// production fields. `None` for users that aren't venues.
+    pub fn tech_rider(&self) -> Option<TechRider> {
+        let info = self.venue_info.as_ref()?;
+        if let Some(rider) = &info.tech_rider {
+            return Some(rider.clone());
+        }
+
+        Some(TechRider::parse(ProductionText {
+            production_info: info.production_info.as_deref(),
+            front_of_house: info.front_of_house.as_deref(),
+            monitors: info.monitors.as_deref(),
+            microphones: info.microphones.as_deref(),
+            lights: info.lights.as_deref(),
+        }))
+    }
+
+    pub fn top_performer_ids(&self) -> &[String] {
+        self.venue_info
+            .as_ref()
+            .map_or(&[], |info| info.top_performer_ids.as_slice())
+    }
+
+    pub fn capacity(&self) -> Option<u32> {
+        self.venue_info.as_ref().and_then(|info| info.capacity)
+    }
+
+    pub fn performer_category(&self) -> PerformerCategory {
+        self.performer_info
+            .as_ref()
+            .map_or(PerformerCategory::Undiscovered, |info| info.category)
+    }
+
+    /// The performer's own rating and how many reviews it's based on.
+    pub fn performer_rating(&self) -> Option<(f64, u32)> {
+        self.performer_info
+            .as_ref()
+            .and_then(|info| Some((info.rating?, info.review_count)))
+    }
+
+    /// The rating performers have given this user as a booker.
+    pub fn booker_rating(&self) -> Option<(f64, u32)> {
+        self.booker_info
+            .as_ref()
+            .and_then(|info| Some((info.rating?, info.review_count)))
+    }
+
+    /// A rough head count per show, from the size of the social following.
+    pub fn average_attendance(&self) -> u32 {
+        (self.total_audience_size() as f64 / 250.0).round() as u32
+    }
+
     pub fn total_audience_size(&self) -> u32 {
         let social_following = &self.social_following;
 
@@ -188,33 +436,44 @@ impl UserModel {
             + (social_following.tiktok_followers)
     }
 
+    fn guarded_social_following(&self, redaction: &Redaction) -> SocialFollowing {
+        if redaction.shows(Field::SocialHandles) {
+            self.social_following.clone()
+        } else {
+            self.social_following.counts_only()
+        }
+    }
+
     pub fn to_guarded_performer(
         &self,
-        bookings: Vec<GuardedBooking>,
-        reviews: Vec<GuardedReview>,
+        viewer: &Viewer,
+        bookings: Option<Vec<GuardedBooking>>,
+        reviews: Option<Vec<Review>>,
     ) -> GuardedPerformer {
-        let audience = self.total_audience_size();
-        let average_attendance = (audience as f64 / 250.0).round() as u32;
-        let category = self
-            .performer_info
-            .as_ref()
-            .map_or(PerformerCategory::Undiscovered, |info| {
-                info.category.clone()
-            });
-        let user_ticket_range = category.ticket_price_range();
+        let redaction = viewer.redaction(self);
+        let average_attendance = self.average_attendance();
+        let user_ticket_range = self.performer_category().ticket_price_range();
 
         GuardedPerformer {
             id: self.id.clone(),
             username: self.username.clone(),
-            display_name: self.artist_name.clone(),
-            bio: self.bio.clone(),
-            profile_picture_url: self.profile_picture.clone(),
-            location: self.location.clone(),
-            social_following: self.social_following.clone(),
-            press_kit_url: self
-                .performer_info
-                .as_ref()
-                .and_then(|info| info.press_kit_url.clone()),
+            claimed: !self.unclaimed,
+            verified: self.verified,
+            display_name: redaction
+                .keep(Field::Profile, Some(self.artist_name.clone()))
+                .unwrap_or_default(),
+            bio: redaction
+                .keep(Field::Profile, Some(self.bio.clone()))
+                .unwrap_or_default(),
+            profile_picture_url: redaction.keep(Field::Profile, self.profile_picture.clone()),
+            location: redaction.keep(Field::Location, self.location.clone()),
+            social_following: self.guarded_social_following(&redaction),
+            press_kit_url: redaction.keep(
+                Field::PressKit,
+                self.performer_info
+                    .as_ref()
+                    .and_then(|info| info.press_kit_url.clone()),
+            ),
             genres: self
                 .performer_info
                 .as_ref()
@@ -225,38 +484,39 @@ impl UserModel {
                 .and_then(|info| info.spotify_id.clone()),
             average_attendance,
             average_ticket_range: user_ticket_range,
-            bookings: Bookings {
-                count: bookings.len(),
-                items: bookings,
-            },
-            reviews: Reviews {
-                count: reviews.len(),
-                rating: if reviews.is_empty() {
-                    0.0
-                } else {
-                    reviews.iter().map(|review| review.rating).sum::<f64>() / reviews.len() as f64
-                },
-                items: reviews,
-            },
+            rate_estimate: None,
+            bookings: bookings.map(Bookings::new),
+            reviews: reviews.map(|reviews| Reviews::new(&reviews)),
         }
     }
 
     pub fn to_guarded_venue(
         &self,
-        bookings: Vec<GuardedBooking>,
-        reviews: Vec<GuardedReview>,
+        viewer: &Viewer,
+        bookings: Option<Vec<GuardedBooking>>,
+        reviews: Option<Vec<Review>>,
     ) -> GuardedVenue {
+        let redaction = viewer.redaction(self);
+
         GuardedVenue {
             id: self.id.clone(),
             username: self.username.clone(),
-            display_name: self.artist_name.clone(),
-            bio: self.bio.clone(),
-            profile_picture_url: self.profile_picture.clone(),
-            location: self.location.clone(),
-            booking_email: self
-                .venue_info
-                .as_ref()
-                .and_then(|info| info.booking_email.clone()),
+            claimed: !self.unclaimed,
+            verified: self.verified,
+            display_name: redaction
+                .keep(Field::Profile, Some(self.artist_name.clone()))
+                .unwrap_or_default(),
+            bio: redaction
+                .keep(Field::Profile, Some(self.bio.clone()))
+                .unwrap_or_default(),
+            profile_picture_url: redaction.keep(Field::Profile, self.profile_picture.clone()),
+            location: redaction.keep(Field::Location, self.location.clone()),
+            booking_email: redaction.keep(
+                Field::BookingEmail,
+                self.venue_info
+                    .as_ref()
+                    .and_then(|info| info.booking_email.clone()),
+            ),
             capacity: self.venue_info.as_ref().and_then(|info| info.capacity),
             production_info: self
                 .venue_info
@@ -286,57 +546,87 @@ impl UserModel {
                 .venue_info
                 .as_ref()
                 .map_or_else(Vec::new, |info| info.genres.clone()),
+            tech_rider: self.tech_rider().unwrap_or_default(),
             top_performer_ids: self
                 .venue_info
                 .as_ref()
                 .map_or_else(Vec::new, |info| info.top_performer_ids.clone()),
-            bookings: Bookings {
-                count: bookings.len(),
-                items: bookings,
-            },
-            reviews: Reviews {
-                count: reviews.len(),
-                rating: if reviews.is_empty() {
-                    0.0
-                } else {
-                    reviews.iter().map(|review| review.rating).sum::<f64>() / reviews.len() as f64
-                },
-                items: reviews,
-            },
+            bookings: bookings.map(Bookings::new),
+            reviews: reviews.map(|reviews| Reviews::new(&reviews)),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Bookings<T> {
-    count: usize,
-    items: Vec<T>,
+#[derive(Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(concrete(name = "Bookings", params(GuardedBooking)))]
+pub struct Bookings<T: async_graphql::OutputType> {
+    pub count: usize,
+    pub items: Vec<T>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Reviews<T> {
-    count: usize,
-    rating: f64,
-    items: Vec<T>,
+impl<T: async_graphql::OutputType> Bookings<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            count: items.len(),
+            items,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, SimpleObject)]
+#[graphql(concrete(name = "Reviews", params(GuardedReview)))]
+pub struct Reviews<T: async_graphql::OutputType> {
+    pub count: usize,
+    /// The mean rating, or `null` without any reviews.
+    pub rating: Option<f64>,
+    pub summary: ReviewSummary,
+    pub items: Vec<T>,
+}
+
+impl Reviews<GuardedReview> {
+    pub fn new(reviews: &[Review]) -> Self {
+        let summary = ReviewSummary::new(reviews, Utc::now());
+
+        Self {
+            count: reviews.len(),
+            rating: summary.average,
+            summary,
+            items: reviews.iter().map(Review::to_guarded).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GuardedPerformer {
-    id: String,
-    username: String,
-    display_name: String,
-    bio: String,
-    profile_picture_url: Option<String>,
-    location: Option<Location>,
-    social_following: SocialFollowing,
-    press_kit_url: Option<String>,
-    genres: Vec<String>,
-    spotify_id: Option<String>,
-    average_ticket_range: TicketRange,
-    average_attendance: u32,
-    bookings: Bookings<GuardedBooking>,
-    reviews: Reviews<GuardedReview>,
+    pub id: String,
+    pub username: String,
+    /// Whether someone runs the profile, rather than it being made by
+    /// Tapped from public data.
+    pub claimed: bool,
+    /// Whether the owner proved it through a claim.
+    pub verified: bool,
+    pub display_name: String,
+    pub bio: String,
+    pub profile_picture_url: Option<String>,
+    pub location: Option<Location>,
+    pub social_following: SocialFollowing,
+    pub press_kit_url: Option<String>,
+    pub genres: Vec<String>,
+    pub spotify_id: Option<String>,
+    /// The fixed range for the performer's category, in cents. Prefer
+    /// `rate_estimate`.
+    pub average_ticket_range: TicketRange,
+    pub average_attendance: u32,
+    /// Only computed when named in `include`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_estimate: Option<RateEstimate>,
+    /// Included by default on v1, unless `include` or `fields` leaves it
+    /// out. v2 and exports only load it when it is named in `include`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookings: Option<Bookings<GuardedBooking>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviews: Option<Reviews<GuardedReview>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -344,6 +634,11 @@ pub struct GuardedPerformer {
 pub struct GuardedVenue {
     pub id: String,
     pub username: String,
+    /// Whether someone runs the profile, rather than it being made by
+    /// Tapped from public data.
+    pub claimed: bool,
+    /// Whether the owner proved it through a claim.
+    pub verified: bool,
     pub display_name: String,
     pub bio: String,
     pub profile_picture_url: Option<String>,
@@ -357,7 +652,12 @@ pub struct GuardedVenue {
     pub monitors: Option<String>,
     pub microphones: Option<String>,
     pub lights: Option<String>,
+    pub tech_rider: TechRider,
     pub top_performer_ids: Vec<String>,
-    pub bookings: Bookings<GuardedBooking>,
-    pub reviews: Reviews<GuardedReview>,
+    /// Included by default on v1, unless `include` or `fields` leaves it
+    /// out. v2 and exports only load it when it is named in `include`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookings: Option<Bookings<GuardedBooking>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviews: Option<Reviews<GuardedReview>>,
 }
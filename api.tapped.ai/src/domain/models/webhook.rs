@@ -0,0 +1,157 @@
+use super::{booking::GuardedBooking, review::GuardedReview};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEventType {
+    #[serde(rename = "booking.pending")]
+    BookingPending,
+    #[serde(rename = "booking.confirmed")]
+    BookingConfirmed,
+    #[serde(rename = "booking.canceled")]
+    BookingCanceled,
+    #[serde(rename = "review.created")]
+    ReviewCreated,
+}
+
+impl WebhookEventType {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::BookingPending => "booking.pending",
+            WebhookEventType::BookingConfirmed => "booking.confirmed",
+            WebhookEventType::BookingCanceled => "booking.canceled",
+            WebhookEventType::ReviewCreated => "review.created",
+        }
+    }
+}
+
+/// An HTTPS endpoint registered by an API key owner.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: String,
+    pub owner_id: String,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub secret: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl Webhook {
+    pub fn to_guarded(&self) -> GuardedWebhook {
+        GuardedWebhook {
+            id: self.id.clone(),
+            url: self.url.clone(),
+            event_types: self.event_types.clone(),
+            description: self.description.clone(),
+            active: self.active,
+            created_at: self.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedWebhook {
+    pub id: String,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+/// The body POSTed to a webhook endpoint.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub created_at: String,
+    pub data: WebhookEventData,
+}
+
+/// Event payloads reuse the REST shapes so consumers only need one model.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum WebhookEventData {
+    Booking(GuardedBooking),
+    Review(GuardedReview),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    /// Every attempt failed; the delivery sits in the dead-letter list
+    /// until it is redelivered by hand.
+    DeadLettered,
+}
+
+/// One event sent to one webhook, including every attempt made so far.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub owner_id: String,
+    pub event_id: String,
+    pub event_type: WebhookEventType,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    #[serde(default)]
+    pub attempts: u32,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    #[serde(with = "firestore::serialize_as_optional_timestamp", default)]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    #[serde(with = "firestore::serialize_as_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    pub fn to_guarded(&self) -> GuardedWebhookDelivery {
+        GuardedWebhookDelivery {
+            id: self.id.clone(),
+            webhook_id: self.webhook_id.clone(),
+            event_id: self.event_id.clone(),
+            event_type: self.event_type,
+            payload: self.payload.clone(),
+            status: self.status,
+            attempts: self.attempts,
+            last_status_code: self.last_status_code,
+            last_error: self.last_error.clone(),
+            next_attempt_at: self.next_attempt_at.map(|at| at.to_rfc3339()),
+            created_at: self.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardedWebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub event_type: WebhookEventType,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: Option<String>,
+    pub created_at: String,
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::search::UserSearchOptionsBuilder,
+    domain::models::user::{TicketRange, UserModel},
+    state::AppStateDyn,
+};
+
+/// Booking rates are stored in US dollars.
+pub const CURRENCY: &str = "USD";
+/// Fewer samples than this don't make a range worth quoting.
+const MIN_SAMPLES: usize = 3;
+/// How many performers in the same genres are searched for comparisons.
+const COMPARABLES: u64 = 10;
+/// A comparable gig's rate is scaled by at most this factor either way to
+/// account for the size of the room.
+const MAX_SCALE: f64 = 2.0;
+const MAX_HISTORY_CONFIDENCE: f64 = 0.9;
+const MAX_COMPARABLE_CONFIDENCE: f64 = 0.6;
+const CATEGORY_CONFIDENCE: f64 = 0.1;
+
+/// One past gig's rate, and the capacity of the venue it was at when known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateSample {
+    pub rate: f64,
+    pub capacity: Option<u32>,
+}
+
+/// What an estimate was worked out from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RateBasis {
+    /// The performer's own confirmed bookings.
+    History,
+    /// Bookings of performers in the same category and genres, scaled to
+    /// the performer's draw.
+    Comparables,
+    /// The fixed range for the performer's category.
+    Category,
+}
+
+/// A likely booking rate for a performer, from the middle half of the rates
+/// it's based on.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateEstimate {
+    pub min: f64,
+    pub max: f64,
+    pub currency: String,
+    /// From 0 to 1, growing with the number of samples.
+    pub confidence: f64,
+    pub basis: RateBasis,
+    pub sample_size: usize,
+}
+
+impl RateEstimate {
+    fn new(rates: &[f64], basis: RateBasis, confidence: f64) -> Self {
+        let mut rates = rates.to_vec();
+        rates.sort_by(f64::total_cmp);
+
+        Self {
+            min: percentile(&rates, 0.25).round(),
+            max: percentile(&rates, 0.75).round(),
+            currency: CURRENCY.to_string(),
+            confidence: round(confidence),
+            basis,
+            sample_size: rates.len(),
+        }
+    }
+
+    fn from_category(range: &TicketRange) -> Self {
+        Self {
+            min: range.min as f64 / 100.0,
+            max: range.max as f64 / 100.0,
+            currency: CURRENCY.to_string(),
+            confidence: CATEGORY_CONFIDENCE,
+            basis: RateBasis::Category,
+            sample_size: 0,
+        }
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Linear interpolation between the closest ranks of `sorted`.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let position = p * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// A gig's rate as if it had been played to `draw` people. Rooms of unknown
+/// size are taken as they are.
+fn scaled(sample: &RateSample, draw: u32) -> f64 {
+    match sample.capacity.filter(|capacity| *capacity > 0) {
+        Some(capacity) => {
+            let scale = (draw.max(1) as f64 / capacity as f64).clamp(1.0 / MAX_SCALE, MAX_SCALE);
+            sample.rate * scale
+        }
+        None => sample.rate,
+    }
+}
+
+/// Estimates a performer's rate from their own gigs when they have enough of
+/// them, then from comparable performers' gigs scaled to the performer's
+/// `draw`, and otherwise from the `fallback` range for their category.
+pub fn estimate(
+    history: &[RateSample],
+    comparables: &[RateSample],
+    draw: u32,
+    fallback: &TicketRange,
+) -> RateEstimate {
+    if history.len() >= MIN_SAMPLES {
+        let rates: Vec<f64> = history.iter().map(|sample| sample.rate).collect();
+        let confidence = (0.5 + 0.05 * rates.len() as f64).min(MAX_HISTORY_CONFIDENCE);
+        return RateEstimate::new(&rates, RateBasis::History, confidence);
+    }
+
+    let rates: Vec<f64> = history
+        .iter()
+        .chain(comparables)
+        .map(|sample| scaled(sample, draw))
+        .collect();
+    if rates.len() >= MIN_SAMPLES {
+        let confidence = (0.2 + 0.04 * rates.len() as f64).min(MAX_COMPARABLE_CONFIDENCE);
+        return RateEstimate::new(&rates, RateBasis::Comparables, confidence);
+    }
+
+    RateEstimate::from_category(fallback)
+}
+
+/// The paid, confirmed gigs of a performer. Venues are looked up once per
+/// request through `venues`.
+async fn samples(
+    state: &AppStateDyn,
+    performer_id: &str,
+    venues: &mut HashMap<String, Option<u32>>,
+) -> Result<Vec<RateSample>> {
+    let bookings = state
+        .database
+        .get_bookings_by_performer_id(performer_id)
+        .await?;
+
+    let mut samples = Vec::new();
+    for booking in bookings.into_iter().filter(|booking| booking.rate > 0.0) {
+        let capacity = match &booking.venue_id {
+            Some(venue_id) => match venues.get(venue_id) {
+                Some(capacity) => *capacity,
+                None => {
+                    let capacity = state
+                        .database
+                        .get_user_by_id(venue_id)
+                        .await
+                        .ok()
+                        .and_then(|venue| venue.capacity());
+                    venues.insert(venue_id.clone(), capacity);
+                    capacity
+                }
+            },
+            None => None,
+        };
+        samples.push(RateSample {
+            rate: booking.rate,
+            capacity,
+        });
+    }
+
+    Ok(samples)
+}
+
+/// Performers in the same category sharing a genre with `performer`.
+async fn comparable_performers(
+    state: &AppStateDyn,
+    performer: &UserModel,
+) -> Result<Vec<UserModel>> {
+    let genres = performer.performer_genres();
+    if genres.is_empty() {
+        return Ok(vec![]);
+    }
+    let options = UserSearchOptionsBuilder::default()
+        .hits_per_page(Some(COMPARABLES))
+        .genres(Some(genres.to_vec()))
+        .build()?;
+    let category = performer.performer_category();
+
+    Ok(state
+        .search
+        .search_users(String::new(), options)
+        .await?
+        .into_iter()
+        .filter(|candidate| {
+            candidate.id != performer.id
+                && candidate.is_performer()
+                && !candidate.is_deleted()
+                && candidate.performer_category() == category
+        })
+        .collect())
+}
+
+/// Estimates `performer`'s booking rate. Comparable performers are only
+/// looked up when the performer's own history is too thin.
+pub async fn estimate_rate(state: &AppStateDyn, performer: &UserModel) -> Result<RateEstimate> {
+    let mut venues = HashMap::new();
+    let history = samples(state, &performer.id, &mut venues).await?;
+
+    let mut comparables = Vec::new();
+    if history.len() < MIN_SAMPLES {
+        for comparable in comparable_performers(state, performer).await? {
+            comparables.extend(samples(state, &comparable.id, &mut venues).await?);
+        }
+    }
+
+    Ok(estimate(
+        &history,
+        &comparables,
+        performer.average_attendance(),
+        &performer.performer_category().ticket_price_range(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(rate: f64, capacity: Option<u32>) -> RateSample {
+        RateSample { rate, capacity }
+    }
+
+    fn fallback() -> TicketRange {
+        TicketRange {
+            min: 1000,
+            max: 2000,
+        }
+    }
+
+    #[test]
+    fn enough_history_gives_the_middle_of_the_performers_own_rates() {
+        let history = [400.0, 500.0, 600.0, 700.0, 800.0].map(|rate| sample(rate, Some(100)));
+        let estimate = estimate(&history, &[sample(10_000.0, None)], 100, &fallback());
+
+        assert_eq!(RateBasis::History, estimate.basis);
+        assert_eq!((500.0, 700.0), (estimate.min, estimate.max));
+        assert_eq!("USD", estimate.currency);
+        assert_eq!(0.75, estimate.confidence);
+        assert_eq!(5, estimate.sample_size);
+    }
+
+    #[test]
+    fn comparable_rates_are_scaled_to_the_performers_draw() {
+        // A room twice the performer's draw halves the rate, and the scale
+        // is capped for rooms far too big or small.
+        let comparables = [
+            sample(1000.0, Some(200)),
+            sample(1000.0, Some(10_000)),
+            sample(300.0, Some(10)),
+            sample(800.0, None),
+        ];
+        let estimate = estimate(&[], &comparables, 100, &fallback());
+
+        assert_eq!(RateBasis::Comparables, estimate.basis);
+        assert_eq!(4, estimate.sample_size);
+        assert_eq!((500.0, 650.0), (estimate.min, estimate.max));
+        assert_eq!(0.36, estimate.confidence);
+    }
+
+    #[test]
+    fn too_few_samples_fall_back_to_the_category_range() {
+        let estimate = estimate(&[sample(700.0, None)], &[], 100, &fallback());
+
+        assert_eq!(RateBasis::Category, estimate.basis);
+        assert_eq!((10.0, 20.0), (estimate.min, estimate.max));
+        assert_eq!(0, estimate.sample_size);
+    }
+}
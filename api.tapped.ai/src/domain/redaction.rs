@@ -0,0 +1,205 @@
+use crate::domain::{
+    auth::Caller,
+    models::{
+        api_key::ApiScope,
+        user::{PrivacySettings, UserModel},
+    },
+};
+
+/// A profile field that isn't shown to every key holder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Display name, bio and profile picture.
+    Profile,
+    Location,
+    /// Handles and links in the social following. Follower counts are
+    /// always shown.
+    SocialHandles,
+    PressKit,
+    BookingEmail,
+}
+
+/// Who a field is shown to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Audience {
+    /// Anyone with an API key.
+    Anyone,
+    /// Keys with the `contacts:read` scope, and only for claimed users who
+    /// take booking requests by email.
+    Contacts,
+}
+
+struct Rule {
+    field: Field,
+    audience: Audience,
+    /// The privacy setting that lets the user hide the field, if any.
+    allowed_by: Option<fn(&PrivacySettings) -> bool>,
+}
+
+/// Who sees what. Deleted users show none of these fields to anyone but
+/// admins, who only get to read them with `?includeDeleted=true`.
+const POLICY: &[Rule] = &[
+    Rule {
+        field: Field::Profile,
+        audience: Audience::Anyone,
+        allowed_by: None,
+    },
+    Rule {
+        field: Field::Location,
+        audience: Audience::Anyone,
+        allowed_by: Some(|privacy| privacy.show_location),
+    },
+    Rule {
+        field: Field::SocialHandles,
+        audience: Audience::Anyone,
+        allowed_by: Some(|privacy| privacy.show_social_handles),
+    },
+    Rule {
+        field: Field::PressKit,
+        audience: Audience::Anyone,
+        allowed_by: None,
+    },
+    Rule {
+        field: Field::BookingEmail,
+        audience: Audience::Contacts,
+        allowed_by: Some(|privacy| privacy.show_contact_details),
+    },
+];
+
+/// What the caller's API key lets them see.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Viewer {
+    contacts: bool,
+    admin: bool,
+}
+
+impl Viewer {
+    pub fn of(caller: &Caller) -> Self {
+        Self {
+            contacts: caller.has_scope(ApiScope::ContactsRead),
+            admin: caller.has_scope(ApiScope::Admin),
+        }
+    }
+
+    /// Which of `user`'s fields this viewer may see.
+    pub fn redaction(&self, user: &UserModel) -> Redaction {
+        let shown = POLICY
+            .iter()
+            .filter(|_| self.admin || !user.is_deleted())
+            .filter(|rule| match rule.audience {
+                Audience::Anyone => true,
+                Audience::Contacts => {
+                    self.contacts && !user.is_unclaimed() && user.takes_booking_requests_by_email()
+                }
+            })
+            .filter(|rule| {
+                rule.allowed_by
+                    .is_none_or(|allowed_by| allowed_by(user.privacy()))
+            })
+            .map(|rule| rule.field)
+            .collect();
+
+        Redaction { shown }
+    }
+}
+
+/// The fields of one user that one viewer may see.
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    shown: Vec<Field>,
+}
+
+impl Redaction {
+    pub fn shows(&self, field: Field) -> bool {
+        self.shown.contains(&field)
+    }
+
+    /// `value`, or `None` when the field is redacted.
+    pub fn keep<T>(&self, field: Field, value: Option<T>) -> Option<T> {
+        value.filter(|_| self.shows(field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn viewer(scopes: Vec<ApiScope>) -> Viewer {
+        Viewer::of(&Caller {
+            api_key_id: "key".into(),
+            user_id: "caller".into(),
+            scopes,
+        })
+    }
+
+    fn venue() -> UserModel {
+        UserModel::fixture(
+            "user",
+            json!({ "emailNotifications": { "bookingRequests": true } }),
+        )
+    }
+
+    #[test]
+    fn contacts_need_the_contacts_scope() {
+        let venue = venue();
+
+        assert!(!viewer(vec![]).redaction(&venue).shows(Field::BookingEmail));
+        assert!(!viewer(vec![ApiScope::Admin])
+            .redaction(&venue)
+            .shows(Field::BookingEmail));
+        assert!(viewer(vec![ApiScope::ContactsRead])
+            .redaction(&venue)
+            .shows(Field::BookingEmail));
+    }
+
+    #[test]
+    fn users_can_keep_fields_to_themselves() {
+        let private = UserModel::fixture(
+            "user",
+            json!({
+                "emailNotifications": { "bookingRequests": true },
+                "privacy": { "showLocation": false, "showContactDetails": false },
+            }),
+        );
+        let redaction = viewer(vec![ApiScope::ContactsRead]).redaction(&private);
+
+        assert!(!redaction.shows(Field::Location));
+        assert!(!redaction.shows(Field::BookingEmail));
+        assert!(redaction.shows(Field::SocialHandles));
+        assert!(redaction.shows(Field::Profile));
+
+        // Nobody to email booking requests to.
+        let quiet = UserModel::fixture("user", json!({}));
+        assert!(!viewer(vec![ApiScope::ContactsRead])
+            .redaction(&quiet)
+            .shows(Field::BookingEmail));
+    }
+
+    #[test]
+    fn unclaimed_and_deleted_users_are_redacted_for_everyone() {
+        let contacts = viewer(vec![ApiScope::ContactsRead]);
+        let unclaimed = UserModel::fixture(
+            "user",
+            json!({
+                "unclaimed": true,
+                "emailNotifications": { "bookingRequests": true },
+            }),
+        );
+        let deleted = UserModel::fixture("user", json!({ "deleted": true }));
+
+        let redaction = contacts.redaction(&unclaimed);
+        assert!(!redaction.shows(Field::BookingEmail));
+        assert!(redaction.shows(Field::Profile));
+
+        let redaction = contacts.redaction(&deleted);
+        for rule in POLICY {
+            assert!(!redaction.shows(rule.field), "{:?}", rule.field);
+        }
+        assert_eq!(None, redaction.keep(Field::PressKit, Some("kit")));
+
+        let redaction = viewer(vec![ApiScope::Admin]).redaction(&deleted);
+        assert!(redaction.shows(Field::Profile));
+        assert!(!redaction.shows(Field::BookingEmail));
+    }
+}
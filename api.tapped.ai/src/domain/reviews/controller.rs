@@ -0,0 +1,143 @@
+use crate::{
+    domain::{
+        auth::Caller,
+        models::{
+            booking::{Booking, BookingStatus},
+            review::{GuardedReview, ModerationStatus, Review, ReviewType},
+        },
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const MIN_RATING: f64 = 1.0;
+pub const MAX_RATING: f64 = 5.0;
+pub const MAX_TEXT_CHARS: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReviewRequest {
+    booking_id: String,
+    rating: f64,
+    text: String,
+}
+
+/// New reviews are held for moderation and left out of every list until
+/// they are approved.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedReview {
+    #[serde(flatten)]
+    review: GuardedReview,
+    moderation_status: ModerationStatus,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn validate(request: &CreateReviewRequest) -> Result<(), AppError> {
+    if !(MIN_RATING..=MAX_RATING).contains(&request.rating) {
+        return Err(AppError::new(&format!(
+            "rating must be between {MIN_RATING} and {MAX_RATING}"
+        )));
+    }
+    if request.text.trim().is_empty() {
+        return Err(AppError::new("text is required"));
+    }
+    if request.text.chars().count() > MAX_TEXT_CHARS {
+        return Err(AppError::new(&format!(
+            "text must be at most {MAX_TEXT_CHARS} characters"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Bookers review the performer and performers review the booker.
+fn review_type(booking: &Booking, caller: &Caller) -> Option<ReviewType> {
+    if booking.requester_id.as_deref() == Some(caller.user_id.as_str()) {
+        Some(ReviewType::Performer)
+    } else if booking.requestee_id == caller.user_id {
+        Some(ReviewType::Booker)
+    } else {
+        None
+    }
+}
+
+pub async fn create_review(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<CreateReviewRequest>,
+) -> Result<(StatusCode, Json<SubmittedReview>), AppError> {
+    validate(&request)?;
+
+    let booking = state
+        .database
+        .get_booking_by_id(&request.booking_id)
+        .await
+        .map_err(|error| {
+            tracing::warn!("{error}");
+            AppError::new("booking not found").with_status(StatusCode::NOT_FOUND)
+        })?;
+    let review_type = review_type(&booking, &caller).ok_or_else(|| {
+        AppError::new("only the booker or performer can review a booking")
+            .with_status(StatusCode::FORBIDDEN)
+    })?;
+    if booking.status != BookingStatus::Confirmed {
+        return Err(AppError::new("only confirmed bookings can be reviewed")
+            .with_status(StatusCode::CONFLICT));
+    }
+    if booking.end_time > Utc::now() {
+        return Err(
+            AppError::new("bookings can only be reviewed once they have ended")
+                .with_status(StatusCode::UNPROCESSABLE_ENTITY),
+        );
+    }
+    let Some(booker_id) = booking.requester_id.clone() else {
+        return Err(AppError::new("booking has no booker").with_status(StatusCode::CONFLICT));
+    };
+
+    let existing = state
+        .database
+        .get_reviews_by_booking_id(&booking.id)
+        .await
+        .map_err(internal_error)?;
+    if existing
+        .iter()
+        .any(|review| review.review_type == review_type)
+    {
+        return Err(AppError::new("this booking has already been reviewed")
+            .with_status(StatusCode::CONFLICT));
+    }
+
+    let review = Review {
+        id: Uuid::new_v4().to_string(),
+        booker_id,
+        performer_id: booking.requestee_id,
+        booking_id: booking.id,
+        timestamp: Utc::now(),
+        overall_rating: request.rating,
+        overall_review: request.text.trim().to_string(),
+        review_type,
+        moderation_status: ModerationStatus::Pending,
+    };
+    state
+        .database
+        .create_review(&review)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SubmittedReview {
+            review: review.to_guarded(),
+            moderation_status: review.moderation_status,
+        }),
+    ))
+}
@@ -0,0 +1,204 @@
+use std::cmp::Reverse;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    domain::{
+        auth::Caller,
+        controller::{include_deleted, SearchFilters},
+        fieldset::{Fieldset, Relation},
+        models::user::UserModel,
+        redaction::Viewer,
+        v2::dto::{BookingV2, Page, PageParams, PerformerV2, ReviewV2},
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+
+/// `?include=bookings,reviews`. Unlike `/v1`, relations are left out unless
+/// they are asked for.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformerParams {
+    include: Option<String>,
+    /// Admins only: also return a soft-deleted performer.
+    include_deleted: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct SearchQuery {
+    query: Option<String>,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn not_found(error: anyhow::Error) -> AppError {
+    tracing::warn!("{error}");
+    AppError::user_not_found("performer", &error)
+}
+
+/// Newest first, so the first page is the most relevant.
+async fn performer_bookings(state: &AppStateDyn, id: &str) -> Result<Vec<BookingV2>, AppError> {
+    let mut bookings = state
+        .database
+        .get_bookings_by_performer_id(id)
+        .await
+        .map_err(internal_error)?;
+    bookings.sort_by_key(|booking| Reverse(booking.start_time));
+
+    Ok(bookings.into_iter().map(BookingV2::from).collect())
+}
+
+async fn performer_reviews(state: &AppStateDyn, id: &str) -> Result<Vec<ReviewV2>, AppError> {
+    let mut reviews = state
+        .database
+        .get_reviews_by_performer_id(id)
+        .await
+        .map_err(internal_error)?;
+    reviews.sort_by_key(|review| Reverse(review.timestamp));
+
+    Ok(reviews.into_iter().map(ReviewV2::from).collect())
+}
+
+async fn to_performer(
+    state: &AppStateDyn,
+    user: UserModel,
+    fieldset: &Fieldset,
+    viewer: &Viewer,
+) -> Result<PerformerV2, AppError> {
+    let bookings = if fieldset.includes(Relation::Bookings) {
+        let bookings = performer_bookings(state, &user.id).await?;
+        Some(Page::paginate(bookings, PageParams::default())?)
+    } else {
+        None
+    };
+    let reviews = if fieldset.includes(Relation::Reviews) {
+        let reviews = performer_reviews(state, &user.id).await?;
+        Some(Page::paginate(reviews, PageParams::default())?)
+    } else {
+        None
+    };
+
+    Ok(PerformerV2::new(
+        user.to_guarded_performer(viewer, None, None),
+        bookings,
+        reviews,
+    ))
+}
+
+fn parse_include(params: &PerformerParams) -> Result<Fieldset, AppError> {
+    let invalid = || AppError::new("include may only name bookings and reviews");
+    let fieldset = Fieldset::include_only(params.include.as_deref()).map_err(|_| invalid())?;
+    if fieldset.includes(Relation::RateEstimate) {
+        return Err(invalid());
+    }
+
+    Ok(fieldset)
+}
+
+pub async fn get_performer(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Query(params): Query<PerformerParams>,
+) -> Result<Json<PerformerV2>, AppError> {
+    let fieldset = parse_include(&params)?;
+    let user = if include_deleted(&caller, params.include_deleted)? {
+        state.database.get_user_by_id_including_deleted(&id)
+    } else {
+        state.database.get_user_by_id(&id)
+    }
+    .await
+    .map_err(not_found)?;
+
+    Ok(Json(
+        to_performer(&state, user, &fieldset, &Viewer::of(&caller)).await?,
+    ))
+}
+
+pub async fn get_performer_username(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(username): Path<String>,
+    Query(params): Query<PerformerParams>,
+) -> Result<Json<PerformerV2>, AppError> {
+    let fieldset = parse_include(&params)?;
+    let user = if include_deleted(&caller, params.include_deleted)? {
+        state
+            .database
+            .get_user_by_username_including_deleted(&username)
+    } else {
+        state.database.get_user_by_username(&username)
+    }
+    .await
+    .map_err(not_found)?;
+
+    Ok(Json(
+        to_performer(&state, user, &fieldset, &Viewer::of(&caller)).await?,
+    ))
+}
+
+pub async fn get_performer_bookings(
+    State(state): State<AppStateDyn>,
+    Path(id): Path<String>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<BookingV2>>, AppError> {
+    state
+        .database
+        .get_user_by_id(&id)
+        .await
+        .map_err(not_found)?;
+    let bookings = performer_bookings(&state, &id).await?;
+
+    Ok(Json(Page::paginate(bookings, page)?))
+}
+
+pub async fn get_performer_reviews(
+    State(state): State<AppStateDyn>,
+    Path(id): Path<String>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<ReviewV2>>, AppError> {
+    state
+        .database
+        .get_user_by_id(&id)
+        .await
+        .map_err(not_found)?;
+    let reviews = performer_reviews(&state, &id).await?;
+
+    Ok(Json(Page::paginate(reviews, page)?))
+}
+
+pub async fn search_performers(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Query(search): Query<SearchQuery>,
+    Query(filters): Query<SearchFilters>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<PerformerV2>>, AppError> {
+    let options = filters
+        .to_options()
+        .build()
+        .map_err(|error| internal_error(error.into()))?;
+    let users = state
+        .search
+        .search_users(search.query.unwrap_or_default(), options)
+        .await
+        .map_err(internal_error)?;
+
+    let viewer = Viewer::of(&caller);
+    let performers = users
+        .iter()
+        .map(|user| PerformerV2::new(user.to_guarded_performer(&viewer, None, None), None, None))
+        .collect();
+
+    Ok(Json(Page::paginate(performers, page)?))
+}
@@ -0,0 +1,228 @@
+//! The `/v2` response shapes. These are built from the domain models but
+//! never shared with them, so a v2 change can't leak into `/v1` or the
+//! Firestore documents.
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::models::{
+        booking::{Booking, BookingStatus},
+        review::Review,
+        user::{GuardedPerformer, Location, SocialFollowing, TicketRange},
+    },
+    errors::AppError,
+};
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// `?page=2&pageSize=50`. Pages start at 1.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageParams {
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+impl PageParams {
+    fn validate(&self) -> Result<(usize, usize), AppError> {
+        let page = self.page.unwrap_or(1);
+        let page_size = self.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        if page == 0 {
+            return Err(AppError::new("page starts at 1"));
+        }
+        if page_size == 0 || page_size > MAX_PAGE_SIZE {
+            return Err(AppError::new(&format!(
+                "pageSize must be between 1 and {MAX_PAGE_SIZE}"
+            )));
+        }
+
+        Ok((page, page_size))
+    }
+}
+
+/// One page of a list, with enough to fetch the rest.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The number of items across every page.
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    pub fn paginate(items: Vec<T>, params: PageParams) -> Result<Self, AppError> {
+        let (page, page_size) = params.validate()?;
+        let total = items.len();
+        let start = (page - 1).saturating_mul(page_size);
+        let items: Vec<T> = items.into_iter().skip(start).take(page_size).collect();
+
+        Ok(Self {
+            has_more: start + items.len() < total,
+            items,
+            total,
+            page,
+            page_size,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingV2 {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub booker_id: Option<String>,
+    pub performer_id: String,
+    pub status: BookingStatus,
+    pub rate: f64,
+    pub location: Option<Location>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub flier_url: Option<String>,
+    pub event_url: Option<String>,
+    pub venue_id: Option<String>,
+    pub reference_event_id: Option<String>,
+}
+
+impl From<Booking> for BookingV2 {
+    fn from(booking: Booking) -> Self {
+        Self {
+            id: booking.id,
+            title: booking.name,
+            description: booking.note,
+            booker_id: booking.requester_id,
+            performer_id: booking.requestee_id,
+            status: booking.status,
+            rate: booking.rate,
+            location: booking.location,
+            start_time: booking.start_time,
+            end_time: booking.end_time,
+            flier_url: booking.flier_url,
+            event_url: booking.event_url,
+            venue_id: booking.venue_id,
+            reference_event_id: booking.reference_event_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewV2 {
+    pub id: String,
+    pub performer_id: String,
+    pub booker_id: String,
+    pub booking_id: String,
+    pub rating: f64,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Review> for ReviewV2 {
+    fn from(review: Review) -> Self {
+        Self {
+            id: review.id,
+            performer_id: review.performer_id,
+            booker_id: review.booker_id,
+            booking_id: review.booking_id,
+            rating: review.overall_rating,
+            text: review.overall_review,
+            created_at: review.timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformerV2 {
+    pub id: String,
+    pub username: String,
+    /// Whether someone runs the profile.
+    pub claimed: bool,
+    /// Whether the owner proved it through a claim.
+    pub verified: bool,
+    pub display_name: String,
+    pub bio: String,
+    pub profile_picture_url: Option<String>,
+    pub location: Option<Location>,
+    pub social_following: SocialFollowing,
+    pub press_kit_url: Option<String>,
+    pub genres: Vec<String>,
+    pub spotify_id: Option<String>,
+    pub average_ticket_range: TicketRange,
+    pub average_attendance: u32,
+    /// The first page of bookings, when requested with `include`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookings: Option<Page<BookingV2>>,
+    /// The first page of reviews, when requested with `include`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviews: Option<Page<ReviewV2>>,
+}
+
+impl PerformerV2 {
+    pub fn new(
+        performer: GuardedPerformer,
+        bookings: Option<Page<BookingV2>>,
+        reviews: Option<Page<ReviewV2>>,
+    ) -> Self {
+        Self {
+            id: performer.id,
+            username: performer.username,
+            claimed: performer.claimed,
+            verified: performer.verified,
+            display_name: performer.display_name,
+            bio: performer.bio,
+            profile_picture_url: performer.profile_picture_url,
+            location: performer.location,
+            social_following: performer.social_following,
+            press_kit_url: performer.press_kit_url,
+            genres: performer.genres,
+            spotify_id: performer.spotify_id,
+            average_ticket_range: performer.average_ticket_range,
+            average_attendance: performer.average_attendance,
+            bookings,
+            reviews,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page: usize, page_size: usize) -> PageParams {
+        PageParams {
+            page: Some(page),
+            page_size: Some(page_size),
+        }
+    }
+
+    #[test]
+    fn pages_slice_the_list_and_report_what_is_left() {
+        let first = Page::paginate((1..=5).collect(), page(1, 2)).unwrap();
+        assert_eq!(vec![1, 2], first.items);
+        assert_eq!(5, first.total);
+        assert!(first.has_more);
+
+        let last = Page::paginate((1..=5).collect(), page(3, 2)).unwrap();
+        assert_eq!(vec![5], last.items);
+        assert!(!last.has_more);
+
+        let past_the_end = Page::paginate((1..=5).collect(), page(9, 2)).unwrap();
+        assert!(past_the_end.items.is_empty());
+        assert!(!past_the_end.has_more);
+    }
+
+    #[test]
+    fn out_of_range_pages_are_rejected() {
+        assert!(Page::paginate(vec![1], page(0, 2)).is_err());
+        assert!(Page::paginate(vec![1], page(1, 0)).is_err());
+        assert!(Page::paginate(vec![1], page(1, MAX_PAGE_SIZE + 1)).is_err());
+    }
+}
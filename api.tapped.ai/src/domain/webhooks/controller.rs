@@ -0,0 +1,273 @@
+use crate::{
+    domain::{
+        auth::Caller,
+        models::webhook::{
+            GuardedWebhook, GuardedWebhookDelivery, Webhook, WebhookDeliveryStatus,
+            WebhookEventType,
+        },
+    },
+    errors::AppError,
+    state::AppStateDyn,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    url: String,
+    event_types: Vec<WebhookEventType>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookRequest {
+    url: Option<String>,
+    event_types: Option<Vec<WebhookEventType>>,
+    description: Option<String>,
+    active: Option<bool>,
+}
+
+/// The signing secret is only ever returned when the webhook is created.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    webhook: GuardedWebhook,
+    secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryParams {
+    status: Option<WebhookDeliveryStatus>,
+}
+
+fn internal_error(error: anyhow::Error) -> AppError {
+    tracing::error!("{error}");
+    AppError::new("internal server error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+fn validate_event_types(event_types: &[WebhookEventType]) -> Result<(), AppError> {
+    if event_types.is_empty() {
+        return Err(AppError::new("at least one event type is required"));
+    }
+
+    Ok(())
+}
+
+async fn owned_webhook(
+    state: &AppStateDyn,
+    caller: &Caller,
+    id: &str,
+) -> Result<Webhook, AppError> {
+    let webhook = state
+        .database
+        .get_webhook_by_id(id)
+        .await
+        .map_err(|error| {
+            tracing::error!("{error}");
+            AppError::new("webhook not found").with_status(StatusCode::NOT_FOUND)
+        })?;
+
+    // Other owners' webhooks are indistinguishable from missing ones.
+    if webhook.owner_id != caller.user_id {
+        return Err(AppError::new("webhook not found").with_status(StatusCode::NOT_FOUND));
+    }
+
+    Ok(webhook)
+}
+
+pub async fn create_webhook(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), AppError> {
+    let url = state
+        .webhooks
+        .policy()
+        .validate_destination(&request.url)
+        .await
+        .map_err(|error| AppError::new(&format!("invalid url: {error}")))?;
+    validate_event_types(&request.event_types)?;
+
+    let webhook = Webhook {
+        id: Uuid::new_v4().to_string(),
+        owner_id: caller.user_id,
+        url: url.to_string(),
+        event_types: request.event_types,
+        secret: generate_secret(),
+        description: request.description,
+        active: true,
+        timestamp: Utc::now(),
+    };
+    state
+        .database
+        .create_webhook(&webhook)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedWebhook {
+            webhook: webhook.to_guarded(),
+            secret: webhook.secret,
+        }),
+    ))
+}
+
+pub async fn list_webhooks(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<GuardedWebhook>>, AppError> {
+    let webhooks = state
+        .database
+        .get_webhooks_by_owner_id(&caller.user_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(
+        webhooks
+            .into_iter()
+            .map(|webhook| webhook.to_guarded())
+            .collect(),
+    ))
+}
+
+pub async fn get_webhook(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<Json<GuardedWebhook>, AppError> {
+    let webhook = owned_webhook(&state, &caller, &id).await?;
+
+    Ok(Json(webhook.to_guarded()))
+}
+
+pub async fn update_webhook(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateWebhookRequest>,
+) -> Result<Json<GuardedWebhook>, AppError> {
+    let mut webhook = owned_webhook(&state, &caller, &id).await?;
+
+    if let Some(url) = request.url {
+        webhook.url = state
+            .webhooks
+            .policy()
+            .validate_destination(&url)
+            .await
+            .map_err(|error| AppError::new(&format!("invalid url: {error}")))?
+            .to_string();
+    }
+    if let Some(event_types) = request.event_types {
+        validate_event_types(&event_types)?;
+        webhook.event_types = event_types;
+    }
+    if let Some(description) = request.description {
+        webhook.description = Some(description);
+    }
+    if let Some(active) = request.active {
+        webhook.active = active;
+    }
+
+    state
+        .database
+        .update_webhook(&webhook)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(webhook.to_guarded()))
+}
+
+pub async fn delete_webhook(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let webhook = owned_webhook(&state, &caller, &id).await?;
+
+    state
+        .database
+        .delete_webhook(&webhook.id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The delivery log of a webhook, newest first. Filter on
+/// `status=deadLettered` for the dead-letter list.
+pub async fn list_webhook_deliveries(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<String>,
+    Query(params): Query<DeliveryParams>,
+) -> Result<Json<Vec<GuardedWebhookDelivery>>, AppError> {
+    let webhook = owned_webhook(&state, &caller, &id).await?;
+
+    let deliveries = state
+        .database
+        .get_webhook_deliveries_by_webhook_id(&webhook.id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(
+        deliveries
+            .into_iter()
+            .filter(|delivery| params.status.is_none_or(|status| delivery.status == status))
+            .map(|delivery| delivery.to_guarded())
+            .collect(),
+    ))
+}
+
+pub async fn redeliver_webhook_delivery(
+    State(state): State<AppStateDyn>,
+    Extension(caller): Extension<Caller>,
+    Path((id, delivery_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<GuardedWebhookDelivery>), AppError> {
+    let webhook = owned_webhook(&state, &caller, &id).await?;
+
+    let delivery = state
+        .database
+        .get_webhook_delivery_by_id(&delivery_id)
+        .await
+        .ok()
+        .filter(|delivery| delivery.webhook_id == webhook.id)
+        .ok_or_else(|| {
+            AppError::new("webhook delivery not found").with_status(StatusCode::NOT_FOUND)
+        })?;
+
+    // Pending deliveries are still being retried, unless whoever was
+    // retrying them is gone.
+    if delivery.status == WebhookDeliveryStatus::Pending
+        && !state.webhooks.policy().is_stalled(&delivery, Utc::now())
+    {
+        return Err(AppError::new("webhook delivery is still in progress")
+            .with_status(StatusCode::CONFLICT));
+    }
+
+    let delivery = state
+        .webhooks
+        .redeliver(delivery)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((StatusCode::ACCEPTED, Json(delivery.to_guarded())))
+}
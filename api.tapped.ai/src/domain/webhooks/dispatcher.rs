@@ -0,0 +1,658 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    data::{
+        change_feed::{BookingChange, ChangeFeed},
+        database::Database,
+    },
+    domain::models::{
+        booking::BookingStatus,
+        review::GuardedReview,
+        webhook::{
+            Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent, WebhookEventData,
+            WebhookEventType,
+        },
+    },
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::{future, StreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect, Url,
+};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+pub const SIGNATURE_HEADER: &str = "tapped-signature";
+pub const EVENT_HEADER: &str = "tapped-event";
+pub const DELIVERY_HEADER: &str = "tapped-delivery";
+
+/// Bookings whose last status is remembered to skip unchanged edits. The
+/// oldest is forgotten first, at worst re-sending one unchanged status.
+const TRACKED_BOOKINGS: usize = 10_000;
+
+/// Controls which endpoints may be registered and how hard deliveries are
+/// retried before they are dead-lettered.
+#[derive(Debug, Clone)]
+pub struct WebhookPolicy {
+    pub require_https: bool,
+    /// Lets webhooks reach loopback, private and link-local addresses. Only
+    /// for local development and tests.
+    pub allow_private_hosts: bool,
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for WebhookPolicy {
+    fn default() -> Self {
+        Self {
+            require_https: true,
+            allow_private_hosts: false,
+            max_attempts: 6,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(60 * 60),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl WebhookPolicy {
+    /// How long to wait after the given (1-based) failed attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Whether a pending delivery has been left behind, e.g. by an instance
+    /// that restarted while it was retrying. A live delivery is attempted by
+    /// `next_attempt_at` and recorded again right after, so one that is still
+    /// waiting `max_delay` past that has nobody working on it.
+    pub fn is_stalled(&self, delivery: &WebhookDelivery, now: DateTime<Utc>) -> bool {
+        let due = delivery.next_attempt_at.unwrap_or(delivery.timestamp);
+
+        delivery.status == WebhookDeliveryStatus::Pending && due + self.max_delay <= now
+    }
+
+    pub fn validate_url(&self, url: &str) -> Result<Url> {
+        let url = Url::parse(url)?;
+
+        match url.scheme() {
+            "https" => {}
+            "http" if !self.require_https => {}
+            scheme => anyhow::bail!("unsupported webhook url scheme `{scheme}`"),
+        }
+        if url.host_str().is_none() {
+            anyhow::bail!("webhook url must have a host");
+        }
+
+        Ok(url)
+    }
+
+    /// [`Self::validate_url`], then makes sure the host only resolves to
+    /// public addresses.
+    pub async fn validate_destination(&self, url: &str) -> Result<Url> {
+        let url = self.validate_url(url)?;
+        self.check_destination(&url).await?;
+
+        Ok(url)
+    }
+
+    /// Refuses hosts that resolve to anything but public addresses, so
+    /// webhooks can't be pointed at our own network or the cloud metadata
+    /// service.
+    pub async fn check_destination(&self, url: &Url) -> Result<()> {
+        if self.allow_private_hosts {
+            return Ok(());
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("webhook url must have a host"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        resolve_public(host, url.port_or_known_default().unwrap_or(443)).await?;
+
+        Ok(())
+    }
+}
+
+/// Whether the address is routable on the public internet.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        // Includes 169.254.169.254, the metadata service.
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network", carrier-grade NAT, IETF protocol assignments,
+        // benchmarking and the reserved 240.0.0.0/4.
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let [first, second, ..] = ip.segments();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, e.g. fd00:ec2::254, and link-local.
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        // Documentation.
+        || (first == 0x2001 && second == 0x0db8))
+}
+
+/// Resolves the host, failing if any of its addresses isn't public.
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+
+    if addrs.is_empty() {
+        anyhow::bail!("webhook host `{host}` does not resolve");
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+        anyhow::bail!(
+            "webhook host `{host}` resolves to the non-public address {}",
+            addr.ip()
+        );
+    }
+
+    Ok(addrs)
+}
+
+/// Checks every address the webhook client connects to, so a host that
+/// passed at registration can't be re-pointed at a private address later.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str(), 0).await?;
+            let addrs: Addrs = Box::new(addrs.into_iter());
+
+            Ok(addrs)
+        })
+    }
+}
+
+/// The last status seen for the most recently changed bookings.
+#[derive(Default)]
+struct BookingStatuses {
+    statuses: HashMap<String, BookingStatus>,
+    order: VecDeque<String>,
+}
+
+impl BookingStatuses {
+    /// Records the status, returning the one it replaced.
+    fn insert(&mut self, booking_id: &str, status: BookingStatus) -> Option<BookingStatus> {
+        let previous = self.statuses.insert(booking_id.to_string(), status);
+        if previous.is_none() {
+            self.order.push_back(booking_id.to_string());
+            if self.order.len() > TRACKED_BOOKINGS {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.statuses.remove(&oldest);
+                }
+            }
+        }
+
+        previous
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `{timestamp}.{body}`, keyed with the webhook
+/// secret. Sent as `t={timestamp},v1={signature}` in [`SIGNATURE_HEADER`].
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// The id of a webhook's delivery of an event. Every instance sees the same
+/// events, so deriving it rather than picking one lets only the first to
+/// record a delivery send it.
+pub fn delivery_id(event_id: &str, webhook_id: &str) -> String {
+    hex::encode(Sha256::digest(format!("{event_id}/{webhook_id}")))
+}
+
+/// Named after the change, so every instance gives it the same id.
+pub fn booking_event(change: &BookingChange) -> WebhookEvent {
+    let event_type = match change.status {
+        BookingStatus::Pending => WebhookEventType::BookingPending,
+        BookingStatus::Confirmed => WebhookEventType::BookingConfirmed,
+        BookingStatus::Canceled => WebhookEventType::BookingCanceled,
+    };
+
+    WebhookEvent {
+        id: format!("booking-{}-{}", change.booking.id, change.id),
+        event_type,
+        created_at: change.timestamp.to_rfc3339(),
+        data: WebhookEventData::Booking(change.booking.clone()),
+    }
+}
+
+pub fn review_event(review: GuardedReview) -> WebhookEvent {
+    WebhookEvent {
+        id: format!("review-{}", review.id),
+        event_type: WebhookEventType::ReviewCreated,
+        created_at: Utc::now().to_rfc3339(),
+        data: WebhookEventData::Review(review),
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    database: Arc<dyn Database>,
+    client: reqwest::Client,
+    policy: WebhookPolicy,
+}
+
+impl WebhookDispatcher {
+    pub fn new(database: Arc<dyn Database>, policy: WebhookPolicy) -> Self {
+        // Redirects are not followed, they could lead anywhere.
+        let mut client = reqwest::Client::builder().redirect(redirect::Policy::none());
+        if !policy.allow_private_hosts {
+            client = client.dns_resolver(Arc::new(PublicResolver));
+        }
+
+        Self {
+            database,
+            client: client.build().expect("Failed to build the webhook client"),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &WebhookPolicy {
+        &self.policy
+    }
+
+    /// Subscribes to the change feed and dispatches an event for every
+    /// booking status transition and every new review. Stalled deliveries
+    /// left behind by earlier instances are picked up first.
+    pub async fn start(self, change_feed: Arc<dyn ChangeFeed>) -> Result<JoinHandle<()>> {
+        let mut last_status = BookingStatuses::default();
+        let bookings = change_feed
+            .subscribe(None)
+            .await?
+            .filter_map(move |change| {
+                // Edits that keep the status unchanged are not interesting
+                // to webhook consumers.
+                let previous = last_status.insert(&change.booking.id, change.status);
+                future::ready((previous != Some(change.status)).then(|| booking_event(&change)))
+            });
+        let reviews = change_feed.subscribe_reviews().await?.map(review_event);
+
+        let mut events = futures::stream::select(bookings, reviews);
+
+        if let Err(error) = self.resume_stalled().await {
+            tracing::error!("failed to resume stalled webhook deliveries: {error}");
+        }
+
+        Ok(tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                if let Err(error) = self.dispatch(event).await {
+                    tracing::error!("failed to dispatch webhook event: {error}");
+                }
+            }
+        }))
+    }
+
+    /// Records a pending delivery for every webhook subscribed to the event
+    /// and sends them in the background. Deliveries another instance already
+    /// recorded are left to it.
+    pub async fn dispatch(&self, event: WebhookEvent) -> Result<Vec<WebhookDelivery>> {
+        let webhooks = self
+            .database
+            .get_webhooks_by_event_type(event.event_type)
+            .await?;
+        let payload = serde_json::to_value(&event)?;
+
+        tracing::info!(
+            "dispatching {} to {} webhooks",
+            event.event_type.as_str(),
+            webhooks.len()
+        );
+
+        let mut deliveries = vec![];
+        for webhook in webhooks {
+            let delivery = WebhookDelivery {
+                id: delivery_id(&event.id, &webhook.id),
+                webhook_id: webhook.id.clone(),
+                owner_id: webhook.owner_id.clone(),
+                event_id: event.id.clone(),
+                event_type: event.event_type,
+                payload: payload.clone(),
+                status: WebhookDeliveryStatus::Pending,
+                attempts: 0,
+                last_status_code: None,
+                last_error: None,
+                next_attempt_at: Some(Utc::now()),
+                timestamp: Utc::now(),
+            };
+            if !self.database.create_webhook_delivery(&delivery).await? {
+                tracing::info!("webhook delivery {} is already recorded", delivery.id);
+                continue;
+            }
+
+            self.deliver(webhook, delivery.clone());
+            deliveries.push(delivery);
+        }
+
+        Ok(deliveries)
+    }
+
+    /// Starts a fresh round of attempts for a delivery, e.g. one taken off the
+    /// dead-letter list.
+    pub async fn redeliver(&self, mut delivery: WebhookDelivery) -> Result<WebhookDelivery> {
+        let webhook = self
+            .database
+            .get_webhook_by_id(&delivery.webhook_id)
+            .await?;
+
+        delivery.status = WebhookDeliveryStatus::Pending;
+        delivery.attempts = 0;
+        delivery.next_attempt_at = Some(Utc::now());
+        self.database.upsert_webhook_delivery(&delivery).await?;
+
+        self.deliver(webhook, delivery.clone());
+
+        Ok(delivery)
+    }
+
+    /// Carries on with every stalled delivery where its attempts left off.
+    /// Each is claimed first, so only one instance resumes it.
+    pub async fn resume_stalled(&self) -> Result<Vec<WebhookDelivery>> {
+        let now = Utc::now();
+        let stalled: Vec<WebhookDelivery> = self
+            .database
+            .get_pending_webhook_deliveries()
+            .await?
+            .into_iter()
+            .filter(|delivery| self.policy.is_stalled(delivery, now))
+            .collect();
+
+        tracing::info!("resuming {} stalled webhook deliveries", stalled.len());
+
+        let mut resumed = vec![];
+        for stalled in stalled {
+            let webhook = match self.database.get_webhook_by_id(&stalled.webhook_id).await {
+                Ok(webhook) => webhook,
+                Err(error) => {
+                    tracing::warn!("not resuming webhook delivery {}: {}", stalled.id, error);
+                    continue;
+                }
+            };
+            let Some(delivery) = self.database.claim_webhook_delivery(&stalled, now).await? else {
+                tracing::info!("webhook delivery {} was claimed elsewhere", stalled.id);
+                continue;
+            };
+
+            self.deliver(webhook, delivery.clone());
+            resumed.push(delivery);
+        }
+
+        Ok(resumed)
+    }
+
+    fn deliver(&self, webhook: Webhook, delivery: WebhookDelivery) -> JoinHandle<()> {
+        let dispatcher = self.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = dispatcher.deliver_with_retries(&webhook, delivery).await {
+                tracing::error!("failed to record webhook delivery: {error}");
+            }
+        })
+    }
+
+    async fn deliver_with_retries(
+        &self,
+        webhook: &Webhook,
+        mut delivery: WebhookDelivery,
+    ) -> Result<()> {
+        while delivery.attempts < self.policy.max_attempts {
+            delivery.attempts += 1;
+
+            match self.attempt(webhook, &delivery).await {
+                Ok(status) => {
+                    delivery.status = WebhookDeliveryStatus::Delivered;
+                    delivery.last_status_code = Some(status);
+                    delivery.last_error = None;
+                    delivery.next_attempt_at = None;
+                    self.database.upsert_webhook_delivery(&delivery).await?;
+
+                    return Ok(());
+                }
+                Err((status, error)) => {
+                    tracing::warn!(
+                        "webhook delivery {} attempt {} failed: {}",
+                        delivery.id,
+                        delivery.attempts,
+                        error
+                    );
+                    delivery.last_status_code = status;
+                    delivery.last_error = Some(error);
+
+                    if delivery.attempts < self.policy.max_attempts {
+                        let delay = self.policy.backoff(delivery.attempts);
+                        delivery.next_attempt_at = Some(Utc::now() + delay);
+                        self.database.upsert_webhook_delivery(&delivery).await?;
+
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+            }
+        }
+
+        tracing::error!(
+            "webhook delivery {} dead-lettered after {} attempts",
+            delivery.id,
+            delivery.attempts
+        );
+        delivery.status = WebhookDeliveryStatus::DeadLettered;
+        delivery.next_attempt_at = None;
+        self.database.upsert_webhook_delivery(&delivery).await?;
+
+        Ok(())
+    }
+
+    /// Sends the delivery once, returning the response status on success.
+    async fn attempt(
+        &self,
+        webhook: &Webhook,
+        delivery: &WebhookDelivery,
+    ) -> Result<u16, (Option<u16>, String)> {
+        // IP literals never reach the resolver, so they are checked here.
+        let url = Url::parse(&webhook.url).map_err(|e| (None, e.to_string()))?;
+        self.policy
+            .check_destination(&url)
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| (None, e.to_string()))?;
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&webhook.secret, timestamp, &body);
+
+        let response = self
+            .client
+            .post(url)
+            .timeout(self.policy.request_timeout)
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, delivery.event_type.as_str())
+            .header(DELIVERY_HEADER, &delivery.id)
+            .header(SIGNATURE_HEADER, format!("t={timestamp},v1={signature}"))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            Err((
+                Some(status.as_u16()),
+                format!("endpoint responded with {status}"),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_the_cap() {
+        let policy = WebhookPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        assert_eq!(Duration::from_secs(1), policy.backoff(1));
+        assert_eq!(Duration::from_secs(2), policy.backoff(2));
+        assert_eq!(Duration::from_secs(4), policy.backoff(3));
+        assert_eq!(Duration::from_secs(5), policy.backoff(4));
+        assert_eq!(Duration::from_secs(5), policy.backoff(100));
+    }
+
+    #[test]
+    fn pending_deliveries_stall_max_delay_after_they_were_due() {
+        let policy = WebhookPolicy::default();
+        let now = Utc::now();
+        let mut delivery = WebhookDelivery {
+            id: "delivery".into(),
+            webhook_id: "webhook".into(),
+            owner_id: "owner".into(),
+            event_id: "event".into(),
+            event_type: WebhookEventType::BookingConfirmed,
+            payload: serde_json::Value::Null,
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 1,
+            last_status_code: None,
+            last_error: None,
+            next_attempt_at: Some(now),
+            timestamp: now,
+        };
+
+        assert!(!policy.is_stalled(&delivery, now));
+        assert!(!policy.is_stalled(&delivery, now + policy.max_delay / 2));
+        assert!(policy.is_stalled(&delivery, now + policy.max_delay));
+
+        delivery.status = WebhookDeliveryStatus::DeadLettered;
+        assert!(!policy.is_stalled(&delivery, now + policy.max_delay));
+    }
+
+    #[test]
+    fn https_is_required_by_default() {
+        let policy = WebhookPolicy::default();
+
+        assert!(policy.validate_url("https://example.com/hook").is_ok());
+        assert!(policy.validate_url("http://example.com/hook").is_err());
+        assert!(policy.validate_url("not a url").is_err());
+    }
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for ip in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn private_destinations_are_refused() {
+        let policy = WebhookPolicy::default();
+
+        for url in [
+            "https://127.0.0.1/hook",
+            "https://localhost/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.1:8443/hook",
+            "https://[::1]/hook",
+        ] {
+            assert!(policy.validate_destination(url).await.is_err(), "{url}");
+        }
+
+        let policy = WebhookPolicy {
+            allow_private_hosts: true,
+            ..Default::default()
+        };
+        assert!(policy
+            .validate_destination("https://127.0.0.1/hook")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn only_recent_booking_statuses_are_remembered() {
+        let mut statuses = BookingStatuses::default();
+
+        assert_eq!(None, statuses.insert("first", BookingStatus::Pending));
+        assert_eq!(
+            Some(BookingStatus::Pending),
+            statuses.insert("first", BookingStatus::Confirmed)
+        );
+        for i in 0..TRACKED_BOOKINGS {
+            statuses.insert(&i.to_string(), BookingStatus::Pending);
+        }
+
+        assert_eq!(TRACKED_BOOKINGS, statuses.statuses.len());
+        assert_eq!(None, statuses.insert("first", BookingStatus::Confirmed));
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signature = sign_payload("secret", 1, b"{}");
+
+        assert_eq!(64, signature.len());
+        assert_eq!(signature, sign_payload("secret", 1, b"{}"));
+        assert_ne!(signature, sign_payload("secret", 2, b"{}"));
+        assert_ne!(signature, sign_payload("other", 1, b"{}"));
+    }
+}
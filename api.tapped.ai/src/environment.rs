@@ -2,6 +2,8 @@ use color_eyre::eyre::{self, Result};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
 pub enum Environment {
+    /// Runs offline against the Firestore emulator.
+    Local,
     Stage,
     Production,
 }
@@ -10,6 +12,7 @@ impl Environment {
     #[must_use]
     pub fn as_str(&self) -> &'static str {
         match self {
+            Environment::Local => "local",
             Environment::Stage => "stage",
             Environment::Production => "production",
         }
@@ -21,10 +24,11 @@ impl TryFrom<String> for Environment {
 
     fn try_from(s: String) -> Result<Self> {
         match s.to_lowercase().as_str() {
+            "local" => Ok(Self::Local),
             "stage" => Ok(Self::Stage),
             "production" => Ok(Self::Production),
             other => Err(eyre::eyre!(
-                "{} is not a supported environment. Use either `stage` or `production`",
+                "{} is not a supported environment. Use either `local`, `stage` or `production`",
                 other
             )),
         }
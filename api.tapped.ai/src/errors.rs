@@ -1,15 +1,18 @@
+use aide::OperationOutput;
 use axum::{http::StatusCode, response::IntoResponse};
 use schemars::JsonSchema;
 use serde::Serialize;
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::{data::soft_delete::is_user_deleted, request_id::current_request_id};
+
 /// A default error response for most API errors.
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct AppError {
     /// An error message.
     pub error: String,
-    /// A unique error ID.
+    /// The id of the request that failed, also sent as `x-request-id`.
     pub error_id: Uuid,
     #[serde(skip)]
     pub status: StatusCode,
@@ -22,7 +25,7 @@ impl AppError {
     pub fn new(error: &str) -> Self {
         Self {
             error: error.to_string(),
-            error_id: Uuid::new_v4(),
+            error_id: current_request_id().unwrap_or_else(Uuid::new_v4),
             status: StatusCode::BAD_REQUEST,
             error_details: None,
         }
@@ -37,6 +40,28 @@ impl AppError {
         self.error_details = Some(details);
         self
     }
+
+    /// A failed lookup of a user, called `what` in the message.
+    pub fn user_not_found(what: &str, error: &anyhow::Error) -> Self {
+        let status = user_lookup_status(error);
+        let error = if status == StatusCode::GONE {
+            format!("{what} has been deleted")
+        } else {
+            format!("{what} not found")
+        };
+
+        Self::new(&error).with_status(status)
+    }
+}
+
+/// 410 Gone when a user lookup failed because the user was deleted,
+/// otherwise 404.
+pub fn user_lookup_status(error: &anyhow::Error) -> StatusCode {
+    if is_user_deleted(error) {
+        StatusCode::GONE
+    } else {
+        StatusCode::NOT_FOUND
+    }
 }
 
 impl IntoResponse for AppError {
@@ -47,3 +72,8 @@ impl IntoResponse for AppError {
         res
     }
 }
+
+/// Documented once for every route by the default response in `api_docs`.
+impl OperationOutput for AppError {
+    type Inner = Self;
+}
@@ -1,12 +1,16 @@
 #[macro_use]
 extern crate derive_builder;
 
+pub mod configuration;
 pub mod data;
 pub mod docs;
 pub mod domain;
 pub mod environment;
 pub mod errors;
 pub mod extractors;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
 pub mod routes;
 pub mod startup;
 pub mod state;
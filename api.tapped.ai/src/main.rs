@@ -1,8 +1,8 @@
-use color_eyre::eyre::{Result, WrapErr};
+use color_eyre::eyre::Result;
 use tapped_api_rs::{
-    environment::Environment,
+    configuration::get_configuration,
     startup::Application,
-    tracing::{get_subscriber, init_subscriber},
+    tracing::{get_subscriber, init_subscriber, shutdown_tracer},
 };
 
 #[tokio::main]
@@ -16,15 +16,11 @@ async fn main() -> Result<()> {
     let subscriber = get_subscriber("tapped-api".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
-    let port: u16 = std::env::var("PORT")
-        .unwrap_or_else(|_| "3000".into())
-        .parse()
-        .wrap_err("failed to parse PORT")?;
-    let env = Environment::try_from(std::env::var("APP_ENVIRONMENT").unwrap_or("stage".into()))?;
-    let project_id = std::env::var("PROJECT_ID").wrap_err("Failed to parse PROJECT_ID")?;
+    let settings = get_configuration()?;
 
-    let app = Application::build(port, project_id, env).await?;
+    let app = Application::build(settings).await?;
     app.run_until_stopped().await?;
+    shutdown_tracer();
 
     Ok(())
 }
@@ -0,0 +1,212 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+use crate::state::AppStateDyn;
+
+/// The Prometheus metrics of one running application. Each instance has its
+/// own registry, so tests don't share counters.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    database_calls: IntCounterVec,
+    database_duration: HistogramVec,
+    search_duration: HistogramVec,
+    cache_lookups: IntCounterVec,
+    auth_failures: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn outcome(ok: bool) -> &'static str {
+    if ok {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time to produce an HTTP response",
+            ),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let database_calls = IntCounterVec::new(
+            Opts::new("database_calls_total", "Calls to each Database method"),
+            &["method", "outcome"],
+        )
+        .unwrap();
+        let database_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "database_call_duration_seconds",
+                "Duration of each Database method",
+            ),
+            &["method"],
+        )
+        .unwrap();
+        let search_duration = HistogramVec::new(
+            HistogramOpts::new("search_duration_seconds", "Duration of user searches"),
+            &["backend", "outcome"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by result"),
+            &["cache", "result"],
+        )
+        .unwrap();
+        let auth_failures = IntCounterVec::new(
+            Opts::new("auth_failures_total", "Requests rejected for their API key"),
+            &["reason"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(database_calls.clone()),
+            Box::new(database_duration.clone()),
+            Box::new(search_duration.clone()),
+            Box::new(cache_lookups.clone()),
+            Box::new(auth_failures.clone()),
+        ] {
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            database_calls,
+            database_duration,
+            search_duration,
+            cache_lookups,
+            auth_failures,
+        }
+    }
+
+    pub fn observe_http(&self, method: &str, path: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, path, status.as_str()];
+
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_database(&self, method: &str, ok: bool, elapsed: Duration) {
+        self.database_calls
+            .with_label_values(&[method, outcome(ok)])
+            .inc();
+        self.database_duration
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_search(&self, backend: &str, ok: bool, elapsed: Duration) {
+        self.search_duration
+            .with_label_values(&[backend, outcome(ok)])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_cache_lookup(&self, cache: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+
+        self.cache_lookups.with_label_values(&[cache, result]).inc();
+    }
+
+    pub fn record_auth_failure(&self, reason: &str) {
+        self.auth_failures.with_label_values(&[reason]).inc();
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("failed to encode metrics");
+
+        String::from_utf8(buffer).expect("metrics are not UTF-8")
+    }
+}
+
+/// The route template a nested router matched, passed back up to
+/// [`track_http`] on the response.
+#[derive(Debug, Clone)]
+struct RouteTemplate(String);
+
+/// Records the count and latency of every request by route template, so
+/// `/v1/performer/:id` is one series however many performers are fetched.
+pub async fn track_http(State(metrics): State<Metrics>, req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let matched_path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+
+    let response = next.run(req).await;
+    let path = response
+        .extensions()
+        .get::<RouteTemplate>()
+        .map(|template| template.0.clone())
+        .or(matched_path)
+        .unwrap_or_else(|| "unmatched".to_string());
+    metrics.observe_http(
+        &method,
+        &path,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+
+    response
+}
+
+/// A `route_layer` for routers nested as services, whose routes the outer
+/// router can't see. Reports the full template, e.g. `/v1/performer/:id`.
+pub async fn report_route_template(req: Request, next: Next) -> Response {
+    let template = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| RouteTemplate(path.as_str().to_string()));
+
+    let mut response = next.run(req).await;
+    if let Some(template) = template {
+        response.extensions_mut().insert(template);
+    }
+
+    response
+}
+
+pub async fn metrics_handler(State(state): State<AppStateDyn>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, TextEncoder::new().format_type().to_string())],
+        state.metrics.render(),
+    )
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{configuration::RateLimitSettings, domain::auth::Caller};
+
+/// Buckets kept at most. Past this the least recently used are dropped, so
+/// a caller making up keys can't grow the map.
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// Position in `Buckets::recency`.
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    entries: HashMap<String, Bucket>,
+    /// Keys by when they were last used, oldest first.
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+/// A token bucket per caller: `burst` requests at once, refilled at
+/// `requests_per_second`. Kept in process memory, so every instance limits
+/// on its own.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &RateLimitSettings) -> Self {
+        Self {
+            rate: f64::from(settings.requests_per_second),
+            burst: f64::from(settings.burst),
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        }
+    }
+
+    /// Takes a token for `key`, or says how long until one is available.
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.clock += 1;
+        let used = buckets.clock;
+
+        let mut bucket = match buckets.entries.remove(key) {
+            Some(bucket) => {
+                buckets.recency.remove(&bucket.used);
+                self.refill(bucket, now)
+            }
+            None => Bucket {
+                tokens: self.burst,
+                updated_at: now,
+                used,
+            },
+        };
+        bucket.used = used;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        };
+
+        buckets.entries.insert(key.to_string(), bucket);
+        buckets.recency.insert(used, key.to_string());
+        while buckets.entries.len() > MAX_BUCKETS {
+            let Some((_, oldest)) = buckets.recency.pop_first() else {
+                break;
+            };
+            buckets.entries.remove(&oldest);
+        }
+
+        result
+    }
+
+    fn refill(&self, bucket: Bucket, now: Instant) -> Bucket {
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+
+        Bucket {
+            tokens: (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.burst),
+            updated_at: now,
+            ..bucket
+        }
+    }
+}
+
+/// Answers 429 with `Retry-After` once a caller is over its limit. Runs
+/// after [`verify_api_token`](crate::domain::auth::verify_api_token), so
+/// authenticated requests are limited by the id of their key. Routes without
+/// a key are limited by the peer address; behind a proxy that is the
+/// proxy's, so anonymous callers share one bucket.
+pub async fn rate_limit(State(limiter): State<RateLimiter>, req: Request, next: Next) -> Response {
+    let key = match req.extensions().get::<Caller>() {
+        Some(caller) => format!("key:{}", caller.api_key_id),
+        None => format!(
+            "ip:{}",
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map_or_else(|| "unknown".to_string(), |info| info.0.ip().to_string())
+        ),
+    };
+
+    if let Err(retry_after) = limiter.check(&key, Instant::now()) {
+        tracing::warn!("rate limited {key} for {retry_after:?}");
+        let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, HeaderValue::from(seconds))],
+        )
+            .into_response();
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(&RateLimitSettings {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    #[test]
+    fn bursts_are_allowed_then_refilled_at_the_rate() {
+        let limiter = limiter(2, 3);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check("key", now).is_ok());
+        }
+        assert_eq!(Err(Duration::from_millis(500)), limiter.check("key", now));
+        assert!(limiter.check("other", now).is_ok());
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check("key", later).is_ok());
+        assert!(limiter.check("key", later).is_err());
+    }
+
+    #[test]
+    fn the_least_recently_used_buckets_are_dropped_past_the_limit() {
+        let limiter = limiter(1, 1);
+        let now = Instant::now();
+        limiter.check("active", now).unwrap();
+        for i in 0..MAX_BUCKETS {
+            let _ = limiter.check(&i.to_string(), now);
+            // Still empty, since it is kept in use.
+            assert!(limiter.check("active", now).is_err());
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(MAX_BUCKETS, buckets.entries.len());
+        assert_eq!(MAX_BUCKETS, buckets.recency.len());
+        assert!(buckets.entries.contains_key("active"));
+        assert!(!buckets.entries.contains_key("0"));
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The id of the request being handled. Inserted into the request
+/// extensions by [`assign_request_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestId(pub Uuid);
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: Uuid;
+}
+
+/// The id of the request the current task is handling, if any.
+pub fn current_request_id() -> Option<Uuid> {
+    CURRENT_REQUEST_ID.try_with(|id| *id).ok()
+}
+
+/// Takes the caller's `x-request-id`, or generates one, and echoes it on the
+/// response. Ids must be UUIDs so they can double as `AppError::error_id`;
+/// anything else is replaced.
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| Uuid::parse_str(header).ok())
+        .unwrap_or_else(Uuid::new_v4);
+    req.extensions_mut().insert(RequestId(id));
+
+    let mut response = CURRENT_REQUEST_ID.scope(id, next.run(req)).await;
+    response.headers_mut().insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&id.to_string()).expect("a UUID is a valid header value"),
+    );
+
+    response
+}
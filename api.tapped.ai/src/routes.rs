@@ -1,25 +1,180 @@
-use aide::axum::ApiRouter;
-use axum::{middleware, routing::get};
+use aide::axum::{routing::get_with, ApiRouter};
+use axum::{
+    middleware,
+    routing::{delete, get, post},
+    Extension,
+};
 
 use crate::{
     domain::{
-        auth::verify_api_token,
+        api_keys::controller::{issue_api_key, list_api_keys, revoke_api_key, rotate_api_key},
+        auth::{require_admin, verify_api_token},
+        booking_stream::stream_bookings,
+        bookings::controller::{
+            create_booking, get_availability, get_calendar, issue_calendar_feed,
+            list_calendar_feeds, revoke_calendar_feed,
+        },
+        claims::controller::{start_claim, verify_claim},
         controller::{get_location, get_performer, get_performer_username, search_performers},
+        events::controller::{get_event, search_events},
+        export::controller::{
+            create_performers_export, create_venues_export, download_export_job, export_performers,
+            export_venues, get_export_job,
+        },
+        graphql::{build_schema, graphql_handler},
+        matchmaking::controller::{
+            get_recommended_performers, get_recommended_venues, get_similar_performers,
+        },
+        reviews::controller::create_review,
+        v2,
+        webhooks::controller::{
+            create_webhook, delete_webhook, get_webhook, list_webhook_deliveries, list_webhooks,
+            redeliver_webhook_delivery, update_webhook,
+        },
     },
+    metrics::{metrics_handler, report_route_template},
+    rate_limit::{rate_limit, RateLimiter},
     state::AppStateDyn,
 };
 
-pub fn v1_routes(state: AppStateDyn) -> ApiRouter {
-    let router = ApiRouter::new()
+pub fn v1_routes(state: AppStateDyn, limiter: RateLimiter) -> ApiRouter {
+    ApiRouter::new()
         .route("/performer/search", get(search_performers))
         .route("/performer/:id", get(get_performer))
+        .route("/performer/:id/availability", get(get_availability))
+        .route(
+            "/performer/:id/calendar-feeds",
+            get(list_calendar_feeds).post(issue_calendar_feed),
+        )
+        .route(
+            "/performer/:id/recommended-venues",
+            get(get_recommended_venues),
+        )
+        .route("/performer/:id/similar", get(get_similar_performers))
         .route("/performer/username/:username", get(get_performer_username))
+        .route(
+            "/venue/:id/recommended-performers",
+            get(get_recommended_performers),
+        )
         .route("/location/:latlng", get(get_location))
+        .route("/bookings", post(create_booking))
+        .route("/calendar-feeds/:id", delete(revoke_calendar_feed))
+        .route("/bookings/stream", get(stream_bookings))
+        .route("/claims", post(start_claim))
+        .route("/claims/:id/verify", post(verify_claim))
+        .route("/events", get(search_events))
+        .route("/events/:id", get(get_event))
+        .route("/graphql", post(graphql_handler))
+        .route("/reviews", post(create_review))
+        .route(
+            "/export/performers",
+            get(export_performers).post(create_performers_export),
+        )
+        .route(
+            "/export/venues",
+            get(export_venues).post(create_venues_export),
+        )
+        .route("/export/jobs/:id", get(get_export_job))
+        .route("/export/jobs/:id/download", get(download_export_job))
+        .route("/webhooks", get(list_webhooks).post(create_webhook))
+        .route(
+            "/webhooks/:id",
+            get(get_webhook)
+                .patch(update_webhook)
+                .delete(delete_webhook),
+        )
+        .route("/webhooks/:id/deliveries", get(list_webhook_deliveries))
+        .route(
+            "/webhooks/:id/deliveries/:delivery_id/redeliver",
+            post(redeliver_webhook_delivery),
+        )
+        .merge(admin_routes(state.clone()))
+        .route_layer(middleware::from_fn_with_state(limiter.clone(), rate_limit))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             verify_api_token,
         ))
-        .with_state(state);
+        .merge(calendar_routes(limiter))
+        .route_layer(middleware::from_fn(report_route_template))
+        .layer(Extension(build_schema()))
+        .with_state(state)
+}
+
+/// Merged into `/v1` before the API key is verified, so the admin check runs
+/// after it.
+fn admin_routes(state: AppStateDyn) -> ApiRouter<AppStateDyn> {
+    ApiRouter::new()
+        .route(
+            "/admin/users/:user_id/api-keys",
+            get(list_api_keys).post(issue_api_key),
+        )
+        .route("/admin/api-keys/:id/rotate", post(rotate_api_key))
+        .route("/admin/api-keys/:id", delete(revoke_api_key))
+        .route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+/// Calendar apps can't send the API key header, so the feed checks its own
+/// token and is limited by the caller's address.
+fn calendar_routes(limiter: RateLimiter) -> ApiRouter<AppStateDyn> {
+    ApiRouter::new()
+        .route("/performer/:id/calendar.ics", get(get_calendar))
+        .route_layer(middleware::from_fn_with_state(limiter, rate_limit))
+}
 
-    router
+/// Scraped by the metrics collector with an admin key, since the counters
+/// name routes and callers' failure reasons.
+pub fn metrics_routes(state: AppStateDyn, limiter: RateLimiter) -> ApiRouter<AppStateDyn> {
+    ApiRouter::new()
+        .route("/metrics", get(metrics_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+        .route_layer(middleware::from_fn_with_state(limiter, rate_limit))
+        .route_layer(middleware::from_fn_with_state(state, verify_api_token))
+}
+
+/// Typed routes, so the `/v2` OpenAPI document is generated from the
+/// handlers themselves.
+pub fn v2_routes(state: AppStateDyn, limiter: RateLimiter) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/performer/search",
+            get_with(v2::controller::search_performers, |op| {
+                op.description("Search performers, one page at a time.")
+                    .security_requirement("ApiKey")
+            }),
+        )
+        .api_route(
+            "/performer/:id",
+            get_with(v2::controller::get_performer, |op| {
+                op.description("A performer, with the first page of each included relation.")
+                    .security_requirement("ApiKey")
+            }),
+        )
+        .api_route(
+            "/performer/:id/bookings",
+            get_with(v2::controller::get_performer_bookings, |op| {
+                op.description("A performer's bookings, newest first.")
+                    .security_requirement("ApiKey")
+            }),
+        )
+        .api_route(
+            "/performer/:id/reviews",
+            get_with(v2::controller::get_performer_reviews, |op| {
+                op.description("A performer's reviews, newest first.")
+                    .security_requirement("ApiKey")
+            }),
+        )
+        .api_route(
+            "/performer/username/:username",
+            get_with(v2::controller::get_performer_username, |op| {
+                op.description("A performer by username.")
+                    .security_requirement("ApiKey")
+            }),
+        )
+        .route_layer(middleware::from_fn_with_state(limiter, rate_limit))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            verify_api_token,
+        ))
+        .route_layer(middleware::from_fn(report_route_template))
+        .with_state(state)
 }
@@ -1,90 +1,344 @@
 use crate::{
-    data::{database::Firestore, search::Algolia},
+    configuration::{CorsSettings, FirestoreSettings, HttpSettings, SearchBackend, Settings},
+    data::{
+        cache::{CachedDatabase, CachedSearch},
+        change_feed::{ChangeFeed, FirestoreChangeFeed, DEFAULT_REPLAY_CAPACITY},
+        database::{Database, Firestore},
+        export_files, mailer,
+        metered::{MeteredDatabase, MeteredSearch},
+        search::{Algolia, FirestoreSearch, Search},
+    },
     docs::docs_routes,
-    environment::Environment,
+    domain::{
+        export::jobs::ExportJobs,
+        health,
+        webhooks::dispatcher::{WebhookDispatcher, WebhookPolicy},
+    },
     errors::AppError,
-    routes::v1_routes,
+    metrics::{track_http, Metrics},
+    rate_limit::RateLimiter,
+    request_id::{assign_request_id, RequestId, REQUEST_ID_HEADER},
+    routes::{metrics_routes, v1_routes, v2_routes},
     state::AppStateDyn,
 };
 use aide::{
     axum::ApiRouter,
-    openapi::{OpenApi, Tag},
+    openapi::{OpenApi, Server, Tag},
     transform::TransformOpenApi,
 };
-use axum::serve::Serve;
 use axum::Router;
 use axum::{
+    error_handling::HandleErrorLayer,
     extract::MatchedPath,
-    http::{Request, StatusCode},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE, LOCATION},
+        HeaderName, HeaderValue, Method, Request, StatusCode,
+    },
+    middleware,
     response::Html,
     routing::get,
     Extension, Json,
 };
+use axum::{
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    middleware::AddExtension,
+    serve::Serve,
+};
 use axum_swagger_ui::swagger_ui;
-use color_eyre::eyre::Result;
+use chrono::Utc;
 use color_eyre::eyre::WrapErr;
+use color_eyre::eyre::{eyre, Result};
 use firestore::{FirestoreDb, FirestoreDbOptions};
+use gcloud_sdk::{ExternalJwtFunctionSource, Token, TokenSourceType};
 use serde_json::{json, Value};
-use std::sync::Arc;
+use std::{future::Future, future::IntoFuture, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
-use tower_http::trace::TraceLayer;
+use tokio_util::sync::CancellationToken;
+use tower::{
+    limit::GlobalConcurrencyLimitLayer, load_shed::LoadShedLayer, BoxError, ServiceBuilder,
+};
+use tower_http::{
+    compression::{
+        predicate::{DefaultPredicate, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    cors::{AllowOrigin, CorsLayer},
+    limit::RequestBodyLimitLayer,
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
 use tracing::info_span;
 use uuid::Uuid;
 
+/// Served with the peer address of each connection, which rate limits
+/// routes without an API key.
+type AppServer = Serve<
+    IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    AddExtension<Router, ConnectInfo<SocketAddr>>,
+>;
+
 pub struct Application {
     port: u16,
-    server: Serve<Router, Router>,
+    server: AppServer,
+    shutdown_timeout: Duration,
+    change_feed: Arc<dyn ChangeFeed>,
 }
 
 impl Application {
-    pub async fn build(port: u16, project_id: String, env: Environment) -> Result<Self> {
-        let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await.wrap_err(
+    pub async fn build(settings: Settings) -> Result<Self> {
+        let state = build_state(&settings).await?;
+
+        Self::build_with_state(&settings, state).await
+    }
+
+    /// Builds the application around an already assembled state, e.g. one
+    /// backed by in-memory data sources.
+    pub async fn build_with_state(settings: &Settings, state: AppStateDyn) -> Result<Self> {
+        let listener = TcpListener::bind(settings.server.address()).await.wrap_err(
             "Failed to bind to the port. Make sure you have the correct permissions to bind to the port",
         )?;
+        let port = listener.local_addr()?.port();
 
-        let server = run(listener, project_id, env).await?;
+        let change_feed = state.change_feed.clone();
+        let server = run(listener, settings, state).await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            shutdown_timeout: settings.server.shutdown_timeout(),
+            change_feed,
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// Serves until SIGTERM or ctrl-c, then drains in-flight requests.
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        self.run_until(shutdown_signal()).await
+    }
+
+    /// Serves until `signal` resolves. New connections are refused from then
+    /// on, and open ones get the shutdown timeout to finish before the
+    /// server stops regardless. Long-lived streams never finish on their own.
+    /// The change feed is stopped last.
+    pub async fn run_until(
+        self,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<(), std::io::Error> {
+        let shutting_down = CancellationToken::new();
+        let server = self.server.with_graceful_shutdown({
+            let shutting_down = shutting_down.clone();
+            async move {
+                signal.await;
+                tracing::info!("shutting down, draining open connections");
+                shutting_down.cancel();
+            }
+        });
+        let drain_timeout = async {
+            shutting_down.cancelled().await;
+            tokio::time::sleep(self.shutdown_timeout).await;
+        };
+
+        let result = tokio::select! {
+            result = server.into_future() => result,
+            _ = drain_timeout => {
+                tracing::warn!(
+                    "connections still open after {:?}, stopping anyway",
+                    self.shutdown_timeout
+                );
+                Ok(())
+            }
+        };
+
+        if let Err(error) = self.change_feed.shutdown().await {
+            tracing::error!("failed to stop the change feed: {error}");
+        }
+
+        result
     }
 }
 
-async fn run(
-    listener: TcpListener,
-    project_id: String,
-    env: Environment,
-) -> Result<Serve<Router, Router>> {
-    let firestore_instance = match env {
-        Environment::Stage => {
-            FirestoreDb::with_options_service_account_key_file(
-                FirestoreDbOptions::new(project_id),
-                "./credentials.json".into(),
-            )
-            .await?
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to listen for ctrl-c: {error}");
+            std::future::pending::<()>().await;
         }
-        Environment::Production => FirestoreDb::new(project_id).await?,
     };
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                tracing::error!("failed to listen for SIGTERM: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Connects to Firestore with the configured credentials, or to the
+/// emulator when one is configured.
+pub async fn connect_firestore(settings: &FirestoreSettings) -> Result<FirestoreDb> {
+    let options = FirestoreDbOptions::new(settings.project_id.clone());
 
-    let db = Firestore::new(firestore_instance);
-    let search = Algolia::default();
-    let state = AppStateDyn {
-        database: Arc::new(db.clone()),
-        search: Arc::new(search.clone()),
+    if let Some(emulator_host) = &settings.emulator_host {
+        // The emulator accepts any bearer token, `owner` grants full access.
+        let token_source = ExternalJwtFunctionSource::new(|| async {
+            Ok(Token::new(
+                "Bearer".into(),
+                "owner".into(),
+                Utc::now() + chrono::Duration::days(365),
+            ))
+        });
+
+        return FirestoreDb::with_options_token_source(
+            FirestoreDbOptions {
+                firebase_api_url: Some(format!("http://{emulator_host}")),
+                ..options
+            },
+            vec![],
+            TokenSourceType::ExternalSource(Box::new(token_source)),
+        )
+        .await
+        .wrap_err_with(|| {
+            format!("Failed to connect to the Firestore emulator at {emulator_host}")
+        });
+    }
+
+    match &settings.credentials_path {
+        Some(credentials_path) => {
+            FirestoreDb::with_options_service_account_key_file(options, credentials_path.clone())
+                .await
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to connect to Firestore with {}",
+                        credentials_path.display()
+                    )
+                })
+        }
+        None => Ok(FirestoreDb::with_options(options).await?),
+    }
+}
+
+async fn build_state(settings: &Settings) -> Result<AppStateDyn> {
+    let firestore_instance = connect_firestore(&settings.firestore).await?;
+
+    let change_feed = FirestoreChangeFeed::start(&firestore_instance, DEFAULT_REPLAY_CAPACITY)
+        .await
+        .map_err(|error| eyre!("Failed to start the booking change feed: {error}"))?;
+    let metrics = Metrics::new();
+    let search: Arc<dyn Search> = match settings.search.backend {
+        SearchBackend::Algolia => Arc::new(MeteredSearch::new(
+            Arc::new(Algolia::new(&settings.algolia)),
+            "algolia",
+            metrics.clone(),
+        )),
+        SearchBackend::Firestore => Arc::new(MeteredSearch::new(
+            Arc::new(FirestoreSearch::new(firestore_instance.clone())),
+            "firestore",
+            metrics.clone(),
+        )),
+    };
+    let search = Arc::new(CachedSearch::new(search, &settings.cache, metrics.clone()));
+    // Metered inside the cache, so cache hits aren't counted as queries.
+    let db: Arc<dyn Database> = Arc::new(CachedDatabase::new(
+        Arc::new(MeteredDatabase::new(
+            Arc::new(Firestore::new(firestore_instance)),
+            metrics.clone(),
+        )),
+        &settings.cache,
+        metrics.clone(),
+    ));
+    let webhooks = WebhookDispatcher::new(db.clone(), WebhookPolicy::default());
+    let export_files = export_files::from_settings(&settings.exports)
+        .await
+        .map_err(|error| eyre!("Failed to set up export storage: {error}"))?;
+    let exports = ExportJobs::new(db.clone(), export_files, settings.exports.retention());
+    let mailer = mailer::from_settings(&settings.mail)
+        .map_err(|error| eyre!("Failed to set up the mailer: {error}"))?;
+
+    Ok(AppStateDyn {
+        database: db,
+        search,
+        change_feed: Arc::new(change_feed),
+        webhooks,
+        exports,
+        mailer,
+        metrics,
+    })
+}
+
+/// Lets browsers on the configured origins call the API. With no origins
+/// configured, no CORS headers are sent and browsers are refused.
+fn cors_layer(settings: &CorsSettings) -> CorsLayer {
+    let origins = &settings.allowed_origins;
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
     };
 
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+        .allow_headers([
+            HeaderName::from_static("tapped-api-key"),
+            CONTENT_TYPE,
+            REQUEST_ID_HEADER,
+        ])
+        .expose_headers([REQUEST_ID_HEADER, LOCATION, CONTENT_DISPOSITION])
+        .max_age(Duration::from_secs(settings.max_age_secs))
+}
+
+fn compression_layer(settings: &HttpSettings) -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .gzip(settings.compression)
+        .br(settings.compression)
+        .compress_when(DefaultPredicate::new().and(SizeAbove::new(settings.compression_min_bytes)))
+}
+
+async fn run(listener: TcpListener, settings: &Settings, state: AppStateDyn) -> Result<AppServer> {
+    state
+        .webhooks
+        .clone()
+        .start(state.change_feed.clone())
+        .await
+        .map_err(|error| eyre!("Failed to start the webhook dispatcher: {error}"))?;
+
     aide::gen::on_error(|error| {
         tracing::error!("{error}");
     });
 
     aide::gen::extract_schemas(true);
     let mut api = OpenApi::default();
+    // Each version gets its own document, so /v2 can change shapes without
+    // touching what /v1 clients were generated from.
+    let mut v2_api = OpenApi::default();
+    // Shared by every version, so a key has one limit across them.
+    let limiter = RateLimiter::new(&settings.rate_limit);
+    let v2 = v2_routes(state.clone(), limiter.clone()).finish_api_with(&mut v2_api, |api| {
+        api_docs(api)
+            .title("Tapped API v2 Docs")
+            .version("2.0.0")
+            .server(Server {
+                url: "/v2".into(),
+                ..Default::default()
+            })
+    });
+
     let app = ApiRouter::new()
         .route(
             "/swagger",
@@ -97,9 +351,20 @@ async fn run(
         .route("/", get(root))
         .route("/version", get(version))
         .route("/health", get(health))
-        .nest_api_service("/v1", v1_routes(state.clone()))
-        .nest_api_service("/docs", docs_routes(state.clone()))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready))
+        .merge(metrics_routes(state.clone(), limiter.clone()))
+        .nest_api_service("/v1", v1_routes(state.clone(), limiter))
+        .nest_service("/v2", v2)
+        .nest_api_service("/docs", docs_routes(state.clone(), Arc::new(v2_api)))
         .finish_api_with(&mut api, api_docs)
+        .layer(RequestBodyLimitLayer::new(settings.http.body_limit_bytes))
+        .layer(TimeoutLayer::new(settings.http.request_timeout()))
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            track_http,
+        ))
+        .layer(Extension(settings.similarity.clone()))
         .layer(Extension(Arc::new(api))) // Arc is very important here or you will face massive memory and performance issues
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
@@ -109,19 +374,43 @@ async fn run(
                     .extensions()
                     .get::<MatchedPath>()
                     .map(MatchedPath::as_str);
+                let request_id = request
+                    .extensions()
+                    .get::<RequestId>()
+                    .map(|RequestId(id)| id.to_string());
 
                 info_span!(
                     "http_request",
                     method = ?request.method(),
                     matched_path,
-                    some_other_field = tracing::field::Empty,
+                    request_id,
                 )
             }),
         )
+        .layer(compression_layer(&settings.http))
+        .layer(cors_layer(&settings.cors))
+        // Outside the trace layer, so the id is there when the span is made.
+        .layer(middleware::from_fn(assign_request_id))
+        // A limit shared by every route, shedding requests past it rather
+        // than queueing them.
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(|_: BoxError| async {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }))
+                .layer(LoadShedLayer::new())
+                .layer(GlobalConcurrencyLimitLayer::new(
+                    settings.http.concurrency_limit,
+                )),
+        )
         .with_state(state);
 
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    let server = axum::serve(listener, app);
+    // The peer address keys the rate limit of routes without an API key.
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    );
 
     Ok(server)
 }
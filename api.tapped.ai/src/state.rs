@@ -1,8 +1,17 @@
-use crate::data::{database::Database, search::Search};
+use crate::{
+    data::{change_feed::ChangeFeed, database::Database, mailer::Mailer, search::Search},
+    domain::{export::jobs::ExportJobs, webhooks::dispatcher::WebhookDispatcher},
+    metrics::Metrics,
+};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppStateDyn {
     pub database: Arc<dyn Database>,
     pub search: Arc<dyn Search>,
+    pub change_feed: Arc<dyn ChangeFeed>,
+    pub webhooks: WebhookDispatcher,
+    pub exports: ExportJobs,
+    pub mailer: Arc<dyn Mailer>,
+    pub metrics: Metrics,
 }
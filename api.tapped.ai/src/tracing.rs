@@ -1,3 +1,6 @@
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace::Tracer, Resource};
 use tracing::{subscriber::set_global_default, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
@@ -15,14 +18,56 @@ where
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
 
+    let telemetry_layer =
+        otlp_tracer(&name).map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
 
     Registry::default()
         .with(env_filter)
+        .with(telemetry_layer)
         .with(JsonStorageLayer)
         .with(formatting_layer)
 }
 
+/// Exports spans over OTLP/gRPC when `OTEL_EXPORTER_OTLP_ENDPOINT` is set,
+/// e.g. `http://localhost:4317`. Must be called within a Tokio runtime.
+fn otlp_tracer(service_name: &str) -> Option<Tracer> {
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(
+            Resource::new([KeyValue::new("service.name", service_name.to_string())]),
+        ))
+        .install_batch(runtime::Tokio);
+
+    match provider {
+        Ok(provider) => {
+            let tracer = provider.tracer(service_name.to_string());
+            opentelemetry::global::set_tracer_provider(provider);
+
+            Some(tracer)
+        }
+        Err(error) => {
+            // The subscriber isn't installed yet, so there is nowhere else
+            // to report this.
+            eprintln!("failed to start OTLP trace export: {error}");
+
+            None
+        }
+    }
+}
+
+/// Flushes spans that haven't been exported yet. Call before exiting.
+pub fn shutdown_tracer() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
 pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
     LogTracer::init().expect("Failed to set logger");
 
@@ -0,0 +1,246 @@
+use chrono::{Duration, Utc};
+use reqwest::Method;
+use serde_json::{json, Value};
+use tapped_api_rs::{
+    data::database::Database,
+    domain::models::{api_key::ApiKey, user::UserModel},
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn issue(app: &TestApp, body: Value) -> Value {
+    let response = app
+        .admin(Method::POST, "/v1/admin/users/partner/api-keys")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(201, response.status().as_u16());
+
+    response.json().await.unwrap()
+}
+
+async fn status_with_key(app: &TestApp, key: &str) -> u16 {
+    app.api_client
+        .get(format!("{}/v1/performer/search", app.address))
+        .header("tapped-api-key", key)
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn admin_routes_require_the_admin_scope() {
+    let app = spawn_app().await;
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+
+    let response = app
+        .request(Method::GET, "/v1/admin/users/partner/api-keys")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn issued_keys_are_shown_once_and_work() {
+    let app = spawn_app().await;
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+
+    let issued = issue(&app, json!({ "name": "partner integration" })).await;
+    let key = issued["key"].as_str().unwrap();
+
+    assert!(key.starts_with("tapped_"));
+    assert_eq!("partner", issued["userId"]);
+    assert_eq!(&key[key.len() - 4..], issued["keyHint"]);
+    assert_eq!(200, status_with_key(&app, key).await);
+
+    let listed: Value = app
+        .admin(Method::GET, "/v1/admin/users/partner/api-keys")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, listed.as_array().unwrap().len());
+    assert_eq!(issued["id"], listed[0]["id"]);
+    assert!(listed[0].get("key").is_none());
+}
+
+#[tokio::test]
+async fn keys_cannot_be_issued_for_missing_users_or_already_expired() {
+    let app = spawn_app().await;
+
+    let response = app
+        .admin(Method::POST, "/v1/admin/users/missing/api-keys")
+        .json(&json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+    let response = app
+        .admin(Method::POST, "/v1/admin/users/partner/api-keys")
+        .json(&json!({ "expiresAt": (Utc::now() - Duration::hours(1)).to_rfc3339() }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn expired_keys_are_rejected() {
+    let app = spawn_app().await;
+    app.database.insert_api_key(ApiKey {
+        key: "expired-key".into(),
+        id: "expired".into(),
+        user_id: "partner".into(),
+        timestamp: Utc::now() - Duration::days(2),
+        expires_at: Some(Utc::now() - Duration::days(1)),
+        ..Default::default()
+    });
+
+    assert_eq!(401, status_with_key(&app, "expired-key").await);
+}
+
+#[tokio::test]
+async fn rotating_a_key_revokes_the_old_one() {
+    let app = spawn_app().await;
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+    let issued = issue(&app, json!({ "scopes": ["admin"] })).await;
+
+    let response = app
+        .admin(
+            Method::POST,
+            &format!(
+                "/v1/admin/api-keys/{}/rotate",
+                issued["id"].as_str().unwrap()
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    let rotated: Value = response.json().await.unwrap();
+
+    assert_ne!(issued["id"], rotated["id"]);
+    assert_eq!(json!(["admin"]), rotated["scopes"]);
+    assert_eq!(
+        401,
+        status_with_key(&app, issued["key"].as_str().unwrap()).await
+    );
+    assert_eq!(
+        200,
+        status_with_key(&app, rotated["key"].as_str().unwrap()).await
+    );
+
+    let response = app
+        .admin(
+            Method::POST,
+            &format!(
+                "/v1/admin/api-keys/{}/rotate",
+                issued["id"].as_str().unwrap()
+            ),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn revoked_keys_are_rejected_but_still_listed() {
+    let app = spawn_app().await;
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+    let issued = issue(&app, json!({})).await;
+    let path = format!("/v1/admin/api-keys/{}", issued["id"].as_str().unwrap());
+
+    for _ in 0..2 {
+        let response = app.admin(Method::DELETE, &path).send().await.unwrap();
+        assert_eq!(204, response.status().as_u16());
+    }
+
+    assert_eq!(
+        401,
+        status_with_key(&app, issued["key"].as_str().unwrap()).await
+    );
+    let listed: Value = app
+        .admin(Method::GET, "/v1/admin/users/partner/api-keys")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(listed[0]["revokedAt"].is_string());
+
+    let response = app
+        .admin(Method::DELETE, "/v1/admin/api-keys/missing")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn legacy_keys_without_an_id_can_be_managed() {
+    let app = spawn_app().await;
+    app.database
+        .insert_user(UserModel::fixture("partner", json!({})));
+    app.database.insert_legacy_api_key(ApiKey {
+        key: "legacy-partner-key".into(),
+        user_id: "partner".into(),
+        timestamp: Utc::now() - Duration::days(30),
+        ..Default::default()
+    });
+
+    let listed: Value = app
+        .admin(Method::GET, "/v1/admin/users/partner/api-keys")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = listed[0]["id"].as_str().unwrap();
+    assert!(id.starts_with("legacy-"));
+    assert_eq!("-key", listed[0]["keyHint"]);
+    assert_eq!(200, status_with_key(&app, "legacy-partner-key").await);
+
+    let response = app
+        .admin(Method::DELETE, &format!("/v1/admin/api-keys/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(401, status_with_key(&app, "legacy-partner-key").await);
+}
+
+#[tokio::test]
+async fn legacy_keys_are_moved_under_their_hash_when_used() {
+    let app = spawn_app().await;
+    app.database.insert_legacy_api_key(ApiKey {
+        key: "legacy-key".into(),
+        id: "legacy".into(),
+        user_id: "partner".into(),
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+
+    assert_eq!(200, status_with_key(&app, "legacy-key").await);
+
+    let api_key = app.database.get_api_key_by_id("legacy").await.unwrap();
+    assert_eq!(ApiKey::hash("legacy-key"), api_key.key_hash);
+    assert!(api_key.key.is_empty());
+    assert_eq!(200, status_with_key(&app, "legacy-key").await);
+}
@@ -0,0 +1,193 @@
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    user::{Location, UserModel},
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+fn booking(id: &str, day: u32, status: BookingStatus) -> Booking {
+    Booking {
+        id: id.into(),
+        name: format!("{id} show"),
+        requester_id: Some("booker".into()),
+        requestee_id: "performer".into(),
+        status,
+        start_time: Utc.with_ymd_and_hms(2030, 6, day, 21, 0, 0).unwrap(),
+        end_time: Utc.with_ymd_and_hms(2030, 6, day, 23, 0, 0).unwrap(),
+        ..Default::default()
+    }
+}
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "artistName": "The Performers" }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({ "artistName": "Room, Upstairs", "venueInfo": { "capacity": 200 } }),
+    ));
+    app.database.insert_booking(Booking {
+        venue_id: Some("venue".into()),
+        location: Some(Location {
+            place_id: "place".into(),
+            lat: 40.7,
+            lng: -74.0,
+        }),
+        event_url: Some("https://example.com/confirmed".into()),
+        ..booking("confirmed", 2, BookingStatus::Confirmed)
+    });
+    app.database
+        .insert_booking(booking("pending", 3, BookingStatus::Pending));
+    app.database
+        .insert_booking(booking("canceled", 4, BookingStatus::Canceled));
+    app.database
+        .insert_booking(booking("later", 20, BookingStatus::Confirmed));
+}
+
+#[tokio::test]
+async fn availability_lists_busy_intervals_in_the_window() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = app
+        .get("/v1/performer/performer/availability?from=2030-06-01T00:00:00Z&to=2030-06-10T00:00:00Z")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(
+        json!([
+            {
+                "startTime": "2030-06-02T21:00:00+00:00",
+                "endTime": "2030-06-02T23:00:00+00:00",
+                "status": "confirmed",
+            },
+            {
+                "startTime": "2030-06-03T21:00:00+00:00",
+                "endTime": "2030-06-03T23:00:00+00:00",
+                "status": "pending",
+            },
+        ]),
+        body["busy"]
+    );
+}
+
+#[tokio::test]
+async fn availability_windows_are_validated() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    for query in [
+        "from=2030-06-10T00:00:00Z&to=2030-06-01T00:00:00Z",
+        "from=2030-01-01T00:00:00Z&to=2031-06-01T00:00:00Z",
+    ] {
+        let response = app
+            .get(&format!("/v1/performer/performer/availability?{query}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(400, response.status().as_u16(), "{query}");
+    }
+
+    let response = app
+        .get("/v1/performer/missing/availability")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
+
+/// Issues a feed for the performer and returns its subscription url.
+async fn issue_feed(app: &TestApp, performer_id: &str) -> Value {
+    let response = app
+        .post(&format!("/v1/performer/{performer_id}/calendar-feeds"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(201, response.status().as_u16());
+
+    response.json().await.unwrap()
+}
+
+/// Fetches a calendar the way a calendar app would, without an API key.
+async fn subscribe(app: &TestApp, path: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn calendar_feed_lists_confirmed_gigs() {
+    let app = spawn_app().await;
+    seed(&app);
+    let feed = issue_feed(&app, "performer").await;
+
+    let response = subscribe(&app, feed["url"].as_str().unwrap()).await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "text/calendar; charset=utf-8",
+        response.headers()["content-type"]
+    );
+    let ics = response.text().await.unwrap();
+    assert!(ics.contains("X-WR-CALNAME:The Performers\r\n"));
+    assert_eq!(2, ics.matches("BEGIN:VEVENT").count());
+    assert!(ics.contains("UID:confirmed@tapped.ai\r\n"));
+    assert!(ics.contains("LOCATION:Room\\, Upstairs\r\n"));
+    assert!(ics.contains("GEO:40.700000;-74.000000\r\n"));
+    assert!(ics.contains("URL:https://example.com/confirmed\r\n"));
+    assert!(ics.contains("UID:later@tapped.ai\r\n"));
+    assert!(!ics.contains("pending@tapped.ai"));
+    assert!(!ics.contains("canceled@tapped.ai"));
+}
+
+#[tokio::test]
+async fn calendar_feeds_need_a_live_token_for_the_performer() {
+    let app = spawn_app().await;
+    seed(&app);
+    let feed = issue_feed(&app, "performer").await;
+    let token = feed["token"].as_str().unwrap();
+    assert!(feed["url"].as_str().unwrap().ends_with(token));
+
+    for path in [
+        "/v1/performer/performer/calendar.ics".to_string(),
+        "/v1/performer/performer/calendar.ics?token=wrong".to_string(),
+        format!("/v1/performer/venue/calendar.ics?token={token}"),
+    ] {
+        assert_eq!(
+            401,
+            subscribe(&app, &path).await.status().as_u16(),
+            "{path}"
+        );
+    }
+
+    let feeds: Vec<Value> = app
+        .get("/v1/performer/performer/calendar-feeds")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, feeds.len());
+    assert!(feeds[0].get("token").is_none());
+
+    let response = app
+        .request(
+            reqwest::Method::DELETE,
+            &format!("/v1/calendar-feeds/{}", feed["id"].as_str().unwrap()),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(204, response.status().as_u16());
+    let url = feed["url"].as_str().unwrap();
+    assert_eq!(401, subscribe(&app, url).await.status().as_u16());
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+use tapped_api_rs::{
+    data::change_feed::ChangeFeed,
+    domain::models::{
+        api_key::{ApiKey, ApiScope},
+        booking::{Booking, BookingStatus},
+    },
+};
+
+use crate::helpers::{spawn_app, TEST_USER_ID};
+
+/// The stream of the test key's own bookings as a performer.
+fn own_stream() -> String {
+    format!("/v1/bookings/stream?performerId={TEST_USER_ID}")
+}
+
+#[derive(Debug)]
+struct SseEvent {
+    id: String,
+    event: String,
+    data: Value,
+}
+
+fn booking(id: &str, performer_id: &str, status: BookingStatus) -> Booking {
+    Booking {
+        id: id.into(),
+        requester_id: Some("booker".into()),
+        requestee_id: performer_id.into(),
+        venue_id: Some("venue".into()),
+        status,
+        ..Default::default()
+    }
+}
+
+async fn read_events(response: &mut reqwest::Response, count: usize) -> Vec<SseEvent> {
+    let mut buffer = String::new();
+    let mut events = vec![];
+
+    while events.len() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .expect("Timed out waiting for an event")
+            .expect("Failed to read from stream")
+            .expect("Stream ended early");
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+        while let Some(end) = buffer.find("\n\n") {
+            let raw: String = buffer.drain(..end + 2).collect();
+            let mut id = String::new();
+            let mut event = String::new();
+            let mut data = String::new();
+            for line in raw.lines() {
+                if let Some(value) = line.strip_prefix("id:") {
+                    id = value.trim().into();
+                } else if let Some(value) = line.strip_prefix("event:") {
+                    event = value.trim().into();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push_str(value.trim());
+                }
+            }
+
+            // Keep-alive comments carry no data.
+            if !data.is_empty() {
+                events.push(SseEvent {
+                    id,
+                    event,
+                    data: serde_json::from_str(&data).unwrap(),
+                });
+            }
+        }
+    }
+
+    events
+}
+
+#[tokio::test]
+async fn stream_requires_an_api_key() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/v1/bookings/stream", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+}
+
+#[tokio::test]
+async fn stream_emits_booking_changes() {
+    let app = spawn_app().await;
+
+    let mut response = app
+        .get(&own_stream())
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert!(response.status().is_success());
+    assert_eq!(
+        Some("text/event-stream"),
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+    );
+
+    app.change_feed
+        .publish(&booking(
+            "booking-1",
+            TEST_USER_ID,
+            BookingStatus::Confirmed,
+        ))
+        .await
+        .unwrap();
+
+    let events = read_events(&mut response, 1).await;
+    assert_eq!("1", events[0].id);
+    assert_eq!("booking.confirmed", events[0].event);
+    assert_eq!("confirmed", events[0].data["status"]);
+    assert_eq!("booking-1", events[0].data["booking"]["id"]);
+    assert_eq!(TEST_USER_ID, events[0].data["booking"]["performerId"]);
+}
+
+#[tokio::test]
+async fn stream_filters_by_performer() {
+    let app = spawn_app().await;
+
+    let mut response = app
+        .get(&own_stream())
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    app.change_feed
+        .publish(&booking("booking-1", "other", BookingStatus::Confirmed))
+        .await
+        .unwrap();
+    app.change_feed
+        .publish(&booking("booking-2", TEST_USER_ID, BookingStatus::Canceled))
+        .await
+        .unwrap();
+
+    let events = read_events(&mut response, 1).await;
+    assert_eq!("2", events[0].id);
+    assert_eq!("booking.canceled", events[0].event);
+    assert_eq!("booking-2", events[0].data["booking"]["id"]);
+}
+
+#[tokio::test]
+async fn stream_resumes_after_last_event_id() {
+    let app = spawn_app().await;
+
+    for id in ["booking-1", "booking-2", "booking-3"] {
+        app.change_feed
+            .publish(&booking(id, TEST_USER_ID, BookingStatus::Pending))
+            .await
+            .unwrap();
+    }
+
+    let mut response = app
+        .get(&own_stream())
+        .header("Last-Event-ID", "1")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    let events = read_events(&mut response, 2).await;
+    assert_eq!(
+        vec!["2", "3"],
+        events.iter().map(|e| e.id.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!("booking.pending", events[0].event);
+}
+
+#[tokio::test]
+async fn stream_rejects_malformed_last_event_id() {
+    let app = spawn_app().await;
+
+    let response = app
+        .get(&own_stream())
+        .header("Last-Event-ID", "not-a-number")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(reqwest::StatusCode::BAD_REQUEST, response.status());
+}
+
+#[tokio::test]
+async fn streams_of_other_users_bookings_need_the_bookings_scope() {
+    let app = spawn_app().await;
+
+    for path in [
+        "/v1/bookings/stream",
+        "/v1/bookings/stream?performerId=someone-else",
+        "/v1/bookings/stream?bookerId=someone-else",
+    ] {
+        let response = app.get(path).send().await.unwrap();
+        assert_eq!(reqwest::StatusCode::FORBIDDEN, response.status(), "{path}");
+    }
+
+    app.database.insert_api_key(ApiKey {
+        key: "bookings-api-key".into(),
+        id: "bookings-api-key-id".into(),
+        user_id: "partner".into(),
+        scopes: vec![ApiScope::BookingsRead],
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+    let mut response = app
+        .api_client
+        .get(format!("{}/v1/bookings/stream", app.address))
+        .header("tapped-api-key", "bookings-api-key")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    app.change_feed
+        .publish(&booking(
+            "booking-1",
+            "someone-else",
+            BookingStatus::Confirmed,
+        ))
+        .await
+        .unwrap();
+    let events = read_events(&mut response, 1).await;
+    assert_eq!("booking-1", events[0].data["booking"]["id"]);
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_USER_ID};
+
+/// `hours` from a whole hour tomorrow.
+fn tomorrow(hours: i64) -> DateTime<Utc> {
+    (Utc::now() + Duration::days(1))
+        .duration_trunc(Duration::hours(1))
+        .unwrap()
+        + Duration::hours(hours)
+}
+
+fn seed(app: &TestApp) {
+    app.database
+        .insert_user(UserModel::fixture("performer", json!({})));
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({ "venueInfo": { "capacity": 300 } }),
+    ));
+    app.database.insert_booking(Booking {
+        id: "confirmed".into(),
+        requester_id: Some("another-venue".into()),
+        requestee_id: "performer".into(),
+        status: BookingStatus::Confirmed,
+        start_time: tomorrow(20),
+        end_time: tomorrow(23),
+        ..Default::default()
+    });
+}
+
+fn request(start: i64, end: i64) -> Value {
+    json!({
+        "performerId": "performer",
+        "title": "Friday late show",
+        "startTime": tomorrow(start).to_rfc3339(),
+        "endTime": tomorrow(end).to_rfc3339(),
+        "rate": 500.0,
+        "venueId": "venue",
+        "location": { "placeId": "place", "lat": 40.7, "lng": -74.0 },
+    })
+}
+
+async fn create(app: &TestApp, body: Value) -> reqwest::Response {
+    app.post("/v1/bookings")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn booking_requests_are_created_pending() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = create(&app, request(23, 25)).await;
+
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("Friday late show", body["title"]);
+    assert_eq!(TEST_USER_ID, body["bookerId"]);
+    assert_eq!("performer", body["performerId"]);
+    assert_eq!("venue", body["venueId"]);
+
+    let stored = app
+        .database
+        .bookings()
+        .into_iter()
+        .find(|booking| booking.id == body["id"])
+        .unwrap();
+    assert_eq!(BookingStatus::Pending, stored.status);
+}
+
+#[tokio::test]
+async fn requests_overlapping_a_confirmed_booking_are_rejected() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = create(&app, request(22, 24)).await;
+
+    assert_eq!(409, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(
+        tomorrow(20).to_rfc3339(),
+        body["error_details"]["conflicts"][0]["startTime"]
+    );
+    assert!(body["error_details"]["conflicts"][0].get("id").is_none());
+}
+
+#[tokio::test]
+async fn pending_requests_do_not_block_each_other() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    assert_eq!(201, create(&app, request(12, 14)).await.status().as_u16());
+    assert_eq!(201, create(&app, request(12, 14)).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_requests_are_rejected() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let with = |field: &str, value: Value| {
+        let mut body = request(10, 12);
+        body[field] = value;
+        body
+    };
+    for body in [
+        request(12, 10),
+        with("rate", json!(-1.0)),
+        with("rate", json!(2_000_000.0)),
+        with(
+            "location",
+            json!({ "placeId": "place", "lat": 91.0, "lng": 0.0 }),
+        ),
+        with(
+            "location",
+            json!({ "placeId": " ", "lat": 0.0, "lng": 0.0 }),
+        ),
+        with("venueId", json!("performer")),
+        with("venueId", json!("missing")),
+        with(
+            "startTime",
+            json!((Utc::now() - Duration::hours(1)).to_rfc3339()),
+        ),
+        with("performerId", json!(TEST_USER_ID)),
+    ] {
+        let response = create(&app, body.clone()).await;
+        assert_eq!(400, response.status().as_u16(), "{body}");
+    }
+
+    let response = create(&app, with("performerId", json!("missing"))).await;
+    assert_eq!(404, response.status().as_u16());
+    assert!(!app.database.calls().contains(&"create_booking"));
+}
@@ -0,0 +1,270 @@
+use chrono::Duration;
+use reqwest::Method;
+use serde_json::{json, Value};
+use tapped_api_rs::{
+    data::database::Database,
+    domain::{claims::controller::CLAIM_COOLDOWN_MINUTES, models::user::UserModel},
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_USER_ID};
+
+async fn app_with_unclaimed_profiles() -> TestApp {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "unclaimed-performer",
+        json!({
+            "email": "band@example.com",
+            "unclaimed": true,
+            "performerInfo": { "genres": ["rock"] },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "unclaimed-venue",
+        json!({
+            "email": "",
+            "unclaimed": true,
+            "venueInfo": { "bookingEmail": "bookings@venue.example" },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "claimed-performer",
+        json!({ "performerInfo": { "genres": ["rock"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "no-contact",
+        json!({ "email": "", "unclaimed": true }),
+    ));
+
+    app
+}
+
+async fn start_claim(app: &TestApp, user_id: &str) -> reqwest::Response {
+    app.post("/v1/claims")
+        .json(&json!({ "userId": user_id }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn verify(app: &TestApp, claim_id: &str, token: &str) -> reqwest::Response {
+    app.post(&format!("/v1/claims/{claim_id}/verify"))
+        .json(&json!({ "token": token }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+/// The token is the only 32 character hex word of the email.
+fn emailed_token(app: &TestApp) -> String {
+    let email = app.mailer.sent().pop().expect("No email was sent");
+
+    email
+        .body
+        .split_whitespace()
+        .find(|word| word.len() == 32 && word.chars().all(|c| c.is_ascii_hexdigit()))
+        .expect("No token in the email")
+        .to_string()
+}
+
+async fn performer(app: &TestApp, id: &str) -> Value {
+    app.get(&format!("/v1/performer/{id}"))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn verified_claims_link_the_profile_to_the_caller() {
+    let app = app_with_unclaimed_profiles().await;
+    let before = performer(&app, "unclaimed-performer").await;
+    assert_eq!(
+        (json!(false), json!(false)),
+        (before["claimed"].clone(), before["verified"].clone())
+    );
+
+    let response = start_claim(&app, "unclaimed-performer").await;
+    assert_eq!(202, response.status().as_u16());
+    let claim: Value = response.json().await.unwrap();
+    assert_eq!("pending", claim["status"]);
+    assert_eq!("b***@example.com", claim["sentTo"]);
+    assert_eq!("band@example.com", app.mailer.sent()[0].to);
+
+    let response = verify(&app, claim["id"].as_str().unwrap(), &emailed_token(&app)).await;
+    assert_eq!(200, response.status().as_u16());
+    let claim: Value = response.json().await.unwrap();
+    assert_eq!("verified", claim["status"]);
+    assert!(claim["verifiedAt"].is_string());
+
+    let after = performer(&app, "unclaimed-performer").await;
+    assert_eq!(
+        (json!(true), json!(true)),
+        (after["claimed"].clone(), after["verified"].clone())
+    );
+    let linked = app
+        .database
+        .get_user_by_id("unclaimed-performer")
+        .await
+        .unwrap();
+    assert_eq!(Some(TEST_USER_ID), linked.claimed_by());
+
+    // The profile can't be claimed twice.
+    assert_eq!(409, start_claim(&app, "unclaimed-performer").await.status());
+}
+
+#[tokio::test]
+async fn venue_claims_are_sent_to_the_booking_email() {
+    let app = app_with_unclaimed_profiles().await;
+
+    let response = start_claim(&app, "unclaimed-venue").await;
+
+    assert_eq!(202, response.status().as_u16());
+    let sent = app.mailer.sent();
+    assert_eq!(1, sent.len());
+    assert_eq!("bookings@venue.example", sent[0].to);
+    assert!(sent[0].subject.contains("@unclaimed-venue"));
+}
+
+#[tokio::test]
+async fn claims_are_only_started_for_unclaimed_profiles_with_a_contact() {
+    let app = app_with_unclaimed_profiles().await;
+    app.database.insert_user(UserModel::fixture(
+        "deleted-performer",
+        json!({ "email": "gone@example.com", "unclaimed": true, "deleted": true }),
+    ));
+
+    assert_eq!(409, start_claim(&app, "claimed-performer").await.status());
+    assert_eq!(422, start_claim(&app, "no-contact").await.status());
+    assert_eq!(410, start_claim(&app, "deleted-performer").await.status());
+    assert_eq!(404, start_claim(&app, "missing").await.status());
+    assert!(app.mailer.sent().is_empty());
+}
+
+#[tokio::test]
+async fn too_many_wrong_tokens_fail_the_claim() {
+    let app = app_with_unclaimed_profiles().await;
+    let claim: Value = start_claim(&app, "unclaimed-performer")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = claim["id"].as_str().unwrap();
+    let token = emailed_token(&app);
+
+    for _ in 0..5 {
+        assert_eq!(400, verify(&app, id, "not-the-token").await.status());
+    }
+
+    assert_eq!(410, verify(&app, id, &token).await.status());
+    let profile = performer(&app, "unclaimed-performer").await;
+    assert_eq!(false, profile["claimed"]);
+}
+
+#[tokio::test]
+async fn claims_can_only_be_verified_by_their_claimant() {
+    let app = app_with_unclaimed_profiles().await;
+    let claim: Value = start_claim(&app, "unclaimed-performer")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = claim["id"].as_str().unwrap();
+
+    let response = app
+        .admin(Method::POST, &format!("/v1/claims/{id}/verify"))
+        .json(&json!({ "token": emailed_token(&app) }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(404, response.status().as_u16());
+    assert_eq!(404, verify(&app, "missing", "token").await.status());
+}
+
+#[tokio::test]
+async fn claims_are_throttled_per_profile() {
+    let app = app_with_unclaimed_profiles().await;
+    let first: Value = start_claim(&app, "unclaimed-performer")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = first["id"].as_str().unwrap();
+    let first_token = emailed_token(&app);
+
+    // Starting again hands back the open claim without another email.
+    let response = start_claim(&app, "unclaimed-performer").await;
+    assert_eq!(202, response.status().as_u16());
+    let again: Value = response.json().await.unwrap();
+    assert_eq!(id, again["id"]);
+    assert_eq!(1, app.mailer.sent().len());
+
+    // Nobody else gets an email sent until the cooldown is over.
+    let response = app
+        .admin(Method::POST, "/v1/claims")
+        .json(&json!({ "userId": "unclaimed-performer" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!(1, app.mailer.sent().len());
+
+    let mut claim = app.database.get_profile_claim_by_id(id).await.unwrap();
+    claim.timestamp -= Duration::minutes(CLAIM_COOLDOWN_MINUTES);
+    app.database.update_profile_claim(&claim).await.unwrap();
+
+    // After it, the open claim gets a fresh token.
+    let resent: Value = start_claim(&app, "unclaimed-performer")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(id, resent["id"]);
+    assert_eq!(2, app.mailer.sent().len());
+    assert_eq!(400, verify(&app, id, &first_token).await.status());
+    assert_eq!(200, verify(&app, id, &emailed_token(&app)).await.status());
+}
+
+#[tokio::test]
+async fn only_one_of_two_racing_claims_wins() {
+    let app = app_with_unclaimed_profiles().await;
+    let admin_claim: Value = app
+        .admin(Method::POST, "/v1/claims")
+        .json(&json!({ "userId": "unclaimed-performer" }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+    let admin_id = admin_claim["id"].as_str().unwrap();
+    let admin_token = emailed_token(&app);
+
+    let mut claim = app
+        .database
+        .get_profile_claim_by_id(admin_id)
+        .await
+        .unwrap();
+    claim.timestamp -= Duration::minutes(CLAIM_COOLDOWN_MINUTES);
+    app.database.update_profile_claim(&claim).await.unwrap();
+
+    let user_claim: Value = start_claim(&app, "unclaimed-performer")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let user_id = user_claim["id"].as_str().unwrap();
+    let user_token = emailed_token(&app);
+
+    let admin_verify = app
+        .admin(Method::POST, &format!("/v1/claims/{admin_id}/verify"))
+        .json(&json!({ "token": admin_token }))
+        .send();
+    let (admin, user) = tokio::join!(admin_verify, verify(&app, user_id, &user_token));
+    let mut statuses = vec![admin.unwrap().status().as_u16(), user.status().as_u16()];
+    statuses.sort();
+
+    assert_eq!(vec![200, 409], statuses);
+}
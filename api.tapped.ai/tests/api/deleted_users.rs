@@ -0,0 +1,160 @@
+use reqwest::Method;
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::user::UserModel;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn app_with_deleted_performer() -> TestApp {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "live",
+        json!({ "artistName": "Live Act", "performerInfo": { "genres": ["rock"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "gone",
+        json!({
+            "artistName": "Gone Act",
+            "deleted": true,
+            "performerInfo": { "genres": ["rock"] },
+        }),
+    ));
+
+    app
+}
+
+async fn status(request: reqwest::RequestBuilder) -> u16 {
+    request
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn deleted_profiles_are_gone() {
+    let app = app_with_deleted_performer().await;
+
+    for path in [
+        "/v1/performer/gone",
+        "/v1/performer/username/gone",
+        "/v2/performer/gone",
+        "/v2/performer/username/gone",
+        "/v2/performer/gone/bookings",
+        "/v1/performer/gone/availability",
+    ] {
+        assert_eq!(410, status(app.get(path)).await, "{path}");
+    }
+    assert_eq!(404, status(app.get("/v1/performer/missing")).await);
+    assert_eq!(404, status(app.get("/v2/performer/missing")).await);
+    assert_eq!(200, status(app.get("/v1/performer/live")).await);
+
+    let body: Value = app
+        .get("/v2/performer/gone")
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("performer has been deleted", body["error"]);
+}
+
+#[tokio::test]
+async fn deleted_users_are_left_out_of_batch_reads() {
+    let app = app_with_deleted_performer().await;
+
+    let response: Value = app
+        .post("/v1/graphql")
+        .json(&json!({
+            "query": r#"{
+                live: performer(id: "live") { displayName }
+                gone: performer(id: "gone") { displayName }
+                byUsername: performerByUsername(username: "gone") { displayName }
+            }"#
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("Live Act", response["data"]["live"]["displayName"]);
+    assert_eq!(Value::Null, response["data"]["gone"]);
+    assert_eq!(Value::Null, response["data"]["byUsername"]);
+}
+
+#[tokio::test]
+async fn admins_can_read_deleted_profiles() {
+    let app = app_with_deleted_performer().await;
+
+    for path in [
+        "/v1/performer/gone?includeDeleted=true",
+        "/v1/performer/username/gone?includeDeleted=true",
+        "/v2/performer/gone?includeDeleted=true",
+    ] {
+        let response = app
+            .admin(Method::GET, path)
+            .send()
+            .await
+            .expect("Failed to execute request");
+        assert_eq!(200, response.status().as_u16(), "{path}");
+
+        let body: Value = response.json().await.unwrap();
+        assert_eq!("Gone Act", body["displayName"], "{path}");
+    }
+
+    // Without asking, admins get the same answer as everyone else.
+    assert_eq!(
+        410,
+        status(app.admin(Method::GET, "/v1/performer/gone")).await
+    );
+}
+
+#[tokio::test]
+async fn include_deleted_needs_the_admin_scope() {
+    let app = app_with_deleted_performer().await;
+
+    for path in [
+        "/v1/performer/gone?includeDeleted=true",
+        "/v2/performer/gone?includeDeleted=true",
+        "/v2/performer/live?includeDeleted=true",
+    ] {
+        assert_eq!(403, status(app.get(path)).await, "{path}");
+    }
+    assert_eq!(
+        200,
+        status(app.get("/v1/performer/live?includeDeleted=false")).await
+    );
+}
+
+#[tokio::test]
+async fn deleted_top_performers_are_left_out_of_locations() {
+    let app = app_with_deleted_performer().await;
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({
+            "location": { "placeId": "place", "lat": 40.0, "lng": -74.0 },
+            "venueInfo": { "topPerformerIds": ["gone", "live", "missing", "live"] },
+        }),
+    ));
+
+    let response = app
+        .get("/v1/location/40.0,-74.0")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    let ids: Vec<_> = body["top_performers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|performer| performer["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["live"], ids);
+
+    assert_eq!(400, status(app.get("/v1/location/40.0")).await);
+}
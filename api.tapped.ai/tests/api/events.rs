@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use serde_json::Value;
+use tapped_api_rs::data::fixtures::Fixtures;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn seeded() -> TestApp {
+    let app = spawn_app().await;
+    Fixtures::load(Path::new("fixtures"))
+        .expect("Failed to load fixtures")
+        .seed_memory(&app.database);
+
+    app
+}
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.get(path)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+fn ids(events: &Value) -> Vec<&str> {
+    events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["id"].as_str().unwrap())
+        .collect()
+}
+
+const JUNE: &str = "from=2024-06-01T00:00:00Z&to=2024-07-01T00:00:00Z";
+
+#[tokio::test]
+async fn events_are_listed_soonest_first_within_the_window() {
+    let app = seeded().await;
+
+    let events: Value = get(&app, &format!("/v1/events?{JUNE}"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec!["event-canal-summer-opener", "event-warehouse-solstice"],
+        ids(&events)
+    );
+    assert_eq!("2024-06-14T00:00:00+00:00", events[0]["startTime"]);
+    assert_eq!(
+        "https://tickets.example/canal-summer-opener",
+        events[0]["ticketUrl"]
+    );
+
+    let later: Value = get(
+        &app,
+        "/v1/events?from=2024-06-15T00:00:00Z&to=2024-07-01T00:00:00Z",
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(vec!["event-warehouse-solstice"], ids(&later));
+}
+
+#[tokio::test]
+async fn events_can_be_searched_by_genre_and_radius() {
+    let app = seeded().await;
+
+    let techno: Value = get(&app, &format!("/v1/events?{JUNE}&genres=Techno,ambient"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["event-warehouse-solstice"], ids(&techno));
+
+    // The opener has no location of its own, so its venue's is used.
+    let richmond: Value = get(
+        &app,
+        &format!("/v1/events?{JUNE}&lat=37.54&lng=-77.44&radius=10000"),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(vec!["event-canal-summer-opener"], ids(&richmond));
+}
+
+#[tokio::test]
+async fn invalid_searches_are_rejected() {
+    let app = seeded().await;
+
+    for query in [
+        "from=2024-07-01T00:00:00Z&to=2024-06-01T00:00:00Z",
+        "from=2024-01-01T00:00:00Z&to=2025-06-01T00:00:00Z",
+        "lat=37.54",
+        "radius=1000",
+    ] {
+        let response = get(&app, &format!("/v1/events?{query}")).await;
+        assert_eq!(400, response.status().as_u16(), "{query}");
+    }
+}
+
+#[tokio::test]
+async fn an_event_has_its_billed_and_booked_performers_as_its_lineup() {
+    let app = seeded().await;
+
+    let event: Value = get(&app, "/v1/events/event-canal-summer-opener")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("Summer Opener", event["name"]);
+    assert_eq!("venue-the-canal-club", event["venueId"]);
+    let lineup: Vec<&str> = event["lineup"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|performer| performer["displayName"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["Blue Hour Trio", "Midnight Echo"], lineup);
+    assert!(event["lineup"][0].get("bookings").is_none());
+}
+
+#[tokio::test]
+async fn unknown_events_are_not_found() {
+    let app = seeded().await;
+
+    let response = get(&app, "/v1/events/no-such-event").await;
+
+    assert_eq!(404, response.status().as_u16());
+}
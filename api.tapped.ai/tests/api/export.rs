@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use serde_json::{json, Value};
+use tapped_api_rs::{
+    data::database::Database,
+    domain::{
+        export::{ExportFormat, ExportResource, EXPORT_PAGE_SIZE},
+        models::{
+            export_job::{ExportJob, ExportJobStatus},
+            user::UserModel,
+        },
+    },
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_USER_ID};
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "jazz-performer",
+        json!({ "artistName": "Jazz Trio", "performerInfo": { "genres": ["jazz"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "rock-performer",
+        json!({ "artistName": "Rock, Paper \"Scissors\"", "performerInfo": { "genres": ["rock"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "small-venue",
+        json!({ "artistName": "The Basement", "venueInfo": { "capacity": 80 } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "big-venue",
+        json!({ "artistName": "The Arena", "venueInfo": { "capacity": 5000 } }),
+    ));
+}
+
+fn ndjson(body: &str) -> Vec<Value> {
+    body.lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn performers_are_exported_as_ndjson() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = app
+        .get("/v1/export/performers?genres=rock")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert!(response.status().is_success());
+    assert_eq!("application/x-ndjson", response.headers()[CONTENT_TYPE]);
+    let rows = ndjson(&response.text().await.unwrap());
+    assert_eq!(1, rows.len());
+    assert_eq!("rock-performer", rows[0]["id"]);
+    // Relations are only embedded in exports when included.
+    assert!(rows[0].get("bookings").is_none());
+    assert!(app
+        .database
+        .calls()
+        .iter()
+        .all(|call| !call.contains("bookings")));
+}
+
+#[tokio::test]
+async fn venues_are_exported_as_csv() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body = app
+        .get("/v1/export/venues?format=csv&fields=id,displayName,capacity&minCapacity=1000")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        "id,displayName,capacity\r\nbig-venue,The Arena,5000\r\n",
+        body
+    );
+
+    let body = app
+        .get("/v1/export/performers?format=csv&fields=id,displayName,genres&genres=rock")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        "id,displayName,genres\r\nrock-performer,\"Rock, Paper \"\"Scissors\"\"\",\"[\"\"rock\"\"]\"\r\n",
+        body
+    );
+}
+
+#[tokio::test]
+async fn exports_page_through_every_search_result() {
+    let app = spawn_app().await;
+    let total = EXPORT_PAGE_SIZE as usize + 5;
+    for i in 0..total {
+        app.database.insert_user(UserModel::fixture(
+            &format!("performer-{i:04}"),
+            json!({ "performerInfo": { "genres": ["pop"] } }),
+        ));
+    }
+
+    let body = app
+        .get("/v1/export/performers?fields=id")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    let rows = ndjson(&body);
+    assert_eq!(total, rows.len());
+    assert_eq!(json!({ "id": "performer-0000" }), rows[0]);
+}
+
+#[tokio::test]
+async fn large_exports_run_as_jobs() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = app
+        .post("/v1/export/venues?format=csv&fields=id")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::ACCEPTED, response.status());
+    let location = response.headers()[LOCATION].to_str().unwrap().to_string();
+    let job: Value = response.json().await.unwrap();
+    assert_eq!(
+        format!("/v1/export/jobs/{}", job["id"].as_str().unwrap()),
+        location
+    );
+
+    let mut job = job;
+    for _ in 0..100 {
+        if job["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        job = app
+            .get(&location)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    }
+    assert_eq!("completed", job["status"]);
+    assert_eq!(2, job["rows"]);
+
+    let response = app
+        .get(&format!("{location}/download"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!("text/csv; charset=utf-8", response.headers()[CONTENT_TYPE]);
+    assert_eq!(
+        "id\r\nbig-venue\r\nsmall-venue\r\n",
+        response.text().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn unknown_export_jobs_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = app.get("/v1/export/jobs/nope").send().await.unwrap();
+
+    assert_eq!(reqwest::StatusCode::NOT_FOUND, response.status());
+}
+
+#[tokio::test]
+async fn old_jobs_are_pruned_and_abandoned_ones_failed() {
+    let app = spawn_app().await;
+    seed(&app);
+    // Older than the test app's one minute retention.
+    let old = |id: &str, status| ExportJob {
+        id: id.into(),
+        owner_id: TEST_USER_ID.into(),
+        resource: ExportResource::Venues,
+        format: ExportFormat::Csv,
+        status,
+        rows: 0,
+        error: None,
+        file_name: format!("{id}.csv"),
+        timestamp: Utc::now() - chrono::Duration::hours(1),
+        completed_at: None,
+    };
+    for job in [
+        old("abandoned", ExportJobStatus::Running),
+        old("finished", ExportJobStatus::Completed),
+    ] {
+        app.database.create_export_job(&job).await.unwrap();
+    }
+
+    let response = app
+        .post("/v1/export/venues?format=csv&fields=id")
+        .send()
+        .await
+        .unwrap();
+    let location = response.headers()[LOCATION].to_str().unwrap().to_string();
+    for _ in 0..100 {
+        let job: Value = app
+            .get(&location)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let abandoned: Value = app
+        .get("/v1/export/jobs/abandoned")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("failed", abandoned["status"]);
+    let finished = app.get("/v1/export/jobs/finished").send().await.unwrap();
+    assert_eq!(reqwest::StatusCode::NOT_FOUND, finished.status());
+}
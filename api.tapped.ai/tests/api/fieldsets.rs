@@ -0,0 +1,139 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    review::{ModerationStatus, Review, ReviewType},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({
+            "artistName": "The Performer",
+            "bio": "loud",
+            "performerInfo": { "genres": ["rock"] },
+        }),
+    ));
+    app.database.insert_booking(Booking {
+        id: "booking".into(),
+        requester_id: Some("venue".into()),
+        requestee_id: "performer".into(),
+        status: BookingStatus::Confirmed,
+        ..Default::default()
+    });
+    app.database.insert_review(Review {
+        id: "review".into(),
+        booker_id: "venue".into(),
+        performer_id: "performer".into(),
+        booking_id: "booking".into(),
+        timestamp: Utc::now(),
+        overall_rating: 5.0,
+        overall_review: "great".into(),
+        review_type: ReviewType::Performer,
+        moderation_status: ModerationStatus::Approved,
+    });
+}
+
+async fn get_performer(app: &TestApp, query: &str) -> reqwest::Response {
+    app.get(&format!("/v1/performer/performer{query}"))
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+fn relation_calls(app: &TestApp) -> Vec<&'static str> {
+    app.database
+        .calls()
+        .into_iter()
+        .filter(|call| call.starts_with("get_bookings") || call.starts_with("get_reviews"))
+        .collect()
+}
+
+#[tokio::test]
+async fn performers_embed_every_relation_by_default() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body: Value = get_performer(&app, "").await.json().await.unwrap();
+
+    assert_eq!("The Performer", body["displayName"]);
+    assert_eq!(1, body["bookings"]["count"]);
+    assert_eq!(1, body["reviews"]["count"]);
+    assert_eq!(
+        vec![
+            "get_bookings_by_performer_id",
+            "get_reviews_by_performer_id"
+        ],
+        relation_calls(&app)
+    );
+}
+
+#[tokio::test]
+async fn relations_that_are_not_included_are_not_fetched() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body: Value = get_performer(&app, "?include=reviews")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert!(body.get("bookings").is_none());
+    assert_eq!(5.0, body["reviews"]["rating"]);
+    assert_eq!(vec!["get_reviews_by_performer_id"], relation_calls(&app));
+
+    let body: Value = get_performer(&app, "?include=").await.json().await.unwrap();
+
+    assert!(body.get("bookings").is_none());
+    assert!(body.get("reviews").is_none());
+    assert_eq!(vec!["get_reviews_by_performer_id"], relation_calls(&app));
+}
+
+#[tokio::test]
+async fn sparse_fieldsets_shape_the_response() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body: Value = get_performer(&app, "?fields=id,displayName,genres")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        json!({ "id": "performer", "displayName": "The Performer", "genres": ["rock"] }),
+        body
+    );
+    assert!(relation_calls(&app).is_empty());
+
+    let body: Value = get_performer(&app, "?fields=id,bookings&include=bookings,reviews")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("performer", body["id"]);
+    assert_eq!(1, body["bookings"]["count"]);
+    assert!(body.get("reviews").is_none());
+    assert_eq!(vec!["get_bookings_by_performer_id"], relation_calls(&app));
+}
+
+#[tokio::test]
+async fn unknown_fields_and_relations_are_rejected() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    for query in ["?include=bookings,friends", "?fields=id,shoeSize"] {
+        let response = get_performer(&app, query).await;
+
+        assert_eq!(
+            reqwest::StatusCode::BAD_REQUEST,
+            response.status(),
+            "{query}"
+        );
+    }
+}
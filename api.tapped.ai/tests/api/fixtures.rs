@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use serde_json::Value;
+use tapped_api_rs::data::fixtures::Fixtures;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn checked_in_fixtures_are_served() {
+    let app = spawn_app().await;
+    let fixtures = Fixtures::load(Path::new("fixtures")).expect("Failed to load fixtures");
+    assert!(!fixtures.users.is_empty());
+    fixtures.seed_memory(&app.database);
+
+    let performer: Value = app
+        .api_client
+        .get(format!(
+            "{}/v1/performer/performer-midnight-echo",
+            app.address
+        ))
+        .header("tapped-api-key", "local-api-key")
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("Midnight Echo", performer["displayName"]);
+    assert_eq!(1, performer["bookings"]["count"]);
+}
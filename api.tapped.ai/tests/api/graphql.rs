@@ -0,0 +1,288 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use tapped_api_rs::domain::{
+    graphql::MAX_LIMIT,
+    models::{
+        booking::{Booking, BookingStatus},
+        review::{ModerationStatus, Review, ReviewType},
+        user::UserModel,
+    },
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn query(app: &TestApp, query: &str) -> Value {
+    let response = app
+        .post("/v1/graphql")
+        .json(&json!({ "query": query }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert!(response.status().is_success());
+
+    response.json().await.unwrap()
+}
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({
+            "artistName": "The Venue",
+            "venueInfo": { "capacity": 300, "topPerformerIds": ["performer", "missing"] },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({
+            "artistName": "The Performer",
+            "performerInfo": { "genres": ["jazz"] },
+        }),
+    ));
+    app.database.insert_booking(Booking {
+        id: "booking".into(),
+        requester_id: Some("venue".into()),
+        requestee_id: "performer".into(),
+        status: BookingStatus::Confirmed,
+        ..Default::default()
+    });
+    app.database.insert_review(Review {
+        id: "review".into(),
+        booker_id: "venue".into(),
+        performer_id: "performer".into(),
+        booking_id: "booking".into(),
+        timestamp: Utc::now(),
+        overall_rating: 4.0,
+        overall_review: "tight set".into(),
+        review_type: ReviewType::Performer,
+        moderation_status: ModerationStatus::Approved,
+    });
+}
+
+#[tokio::test]
+async fn graphql_requires_an_api_key() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/v1/graphql", &app.address))
+        .json(&json!({ "query": "{ performer(id: \"performer\") { id } }" }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+}
+
+#[tokio::test]
+async fn graphql_resolves_performers_with_relations() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body = query(
+        &app,
+        r#"{
+            performer(id: "performer") {
+                id
+                displayName
+                genres
+                bookings { count items { id performerId } }
+                reviews { count rating items { text } }
+            }
+        }"#,
+    )
+    .await;
+
+    assert_eq!(
+        json!({
+            "performer": {
+                "id": "performer",
+                "displayName": "The Performer",
+                "genres": ["jazz"],
+                "bookings": { "count": 1, "items": [{ "id": "booking", "performerId": "performer" }] },
+                "reviews": { "count": 1, "rating": 4.0, "items": [{ "text": "tight set" }] },
+            }
+        }),
+        body["data"]
+    );
+}
+
+#[tokio::test]
+async fn graphql_joins_venues_to_top_performers() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body = query(
+        &app,
+        r#"{
+            venue(id: "venue") {
+                displayName
+                capacity
+                topPerformers { id reviews { rating } }
+            }
+            missing: performer(id: "nobody") { id }
+        }"#,
+    )
+    .await;
+
+    assert!(body.get("errors").is_none(), "{body}");
+    assert_eq!(
+        json!({
+            "venue": {
+                "displayName": "The Venue",
+                "capacity": 300,
+                "topPerformers": [{ "id": "performer", "reviews": { "rating": 4.0 } }],
+            },
+            "missing": null,
+        }),
+        body["data"]
+    );
+}
+
+#[tokio::test]
+async fn graphql_rejects_overly_deep_queries() {
+    let app = spawn_app().await;
+
+    let body = query(
+        &app,
+        r#"{
+            __schema {
+                types {
+                    fields {
+                        type { ofType { ofType { ofType { ofType { name } } } } }
+                    }
+                }
+            }
+        }"#,
+    )
+    .await;
+
+    assert!(body["data"].is_null(), "{body}");
+    let message = body["errors"][0]["message"].as_str().unwrap();
+    assert!(message.contains("nested too deep"), "{message}");
+}
+
+#[tokio::test]
+async fn graphql_rejects_overly_complex_queries() {
+    let app = spawn_app().await;
+
+    let body = query(
+        &app,
+        r#"{
+            searchPerformers(limit: 500) {
+                reviews { items { id text rating } }
+            }
+        }"#,
+    )
+    .await;
+
+    let message = body["errors"][0]["message"].as_str().unwrap();
+    assert!(message.contains("complex"), "{message}");
+}
+
+#[tokio::test]
+async fn graphql_limits_are_clamped() {
+    let app = spawn_app().await;
+    for i in 0..MAX_LIMIT + 5 {
+        app.database.insert_user(UserModel::fixture(
+            &format!("performer-{i}"),
+            json!({ "performerInfo": { "genres": ["jazz"] } }),
+        ));
+    }
+
+    for limit in [1_000, i32::MAX as usize] {
+        let body = query(
+            &app,
+            &format!("{{ searchPerformers(limit: {limit}) {{ id }} }}"),
+        )
+        .await;
+
+        let performers = body["data"]["searchPerformers"].as_array();
+        assert_eq!(Some(MAX_LIMIT), performers.map(Vec::len), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn graphql_lists_the_first_relations_but_counts_all_of_them() {
+    let app = spawn_app().await;
+    seed(&app);
+    for i in 0..3 {
+        app.database.insert_booking(Booking {
+            id: format!("booking-{i}"),
+            requester_id: Some("venue".into()),
+            requestee_id: "performer".into(),
+            status: BookingStatus::Confirmed,
+            ..Default::default()
+        });
+    }
+
+    let body = query(
+        &app,
+        r#"{
+            performer(id: "performer") {
+                bookings(first: 2) { count items { id } }
+                reviews(first: 0) { count rating items { id } }
+            }
+            nobody: performerByUsername(username: "nobody") { id }
+        }"#,
+    )
+    .await;
+
+    assert!(body.get("errors").is_none(), "{body}");
+    let performer = &body["data"]["performer"];
+    assert_eq!(4, performer["bookings"]["count"]);
+    assert_eq!(2, performer["bookings"]["items"].as_array().unwrap().len());
+    assert_eq!(
+        json!({ "count": 1, "rating": 4.0, "items": [] }),
+        performer["reviews"]
+    );
+    assert!(body["data"]["nobody"].is_null());
+}
+
+#[tokio::test]
+async fn graphql_loads_relations_of_many_users_in_one_query() {
+    let app = spawn_app().await;
+    seed(&app);
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({
+            "venueInfo": { "topPerformerIds": ["performer", "other", "another"] },
+        }),
+    ));
+    for id in ["other", "another"] {
+        app.database
+            .insert_user(UserModel::fixture(id, json!({ "performerInfo": {} })));
+    }
+    app.database.clear_calls();
+
+    let body = query(
+        &app,
+        r#"{
+            venue(id: "venue") {
+                bookings { count }
+                topPerformers { id bookings { count } reviews { count } }
+            }
+        }"#,
+    )
+    .await;
+
+    assert!(body.get("errors").is_none(), "{body}");
+    assert_eq!(
+        json!([
+            { "id": "performer", "bookings": { "count": 1 }, "reviews": { "count": 1 } },
+            { "id": "other", "bookings": { "count": 0 }, "reviews": { "count": 0 } },
+            { "id": "another", "bookings": { "count": 0 }, "reviews": { "count": 0 } },
+        ]),
+        body["data"]["venue"]["topPerformers"]
+    );
+    let calls = app.database.calls();
+    for method in [
+        "get_bookings_by_performer_ids",
+        "get_reviews_by_performer_ids",
+    ] {
+        assert_eq!(
+            1,
+            calls.iter().filter(|call| **call == method).count(),
+            "{calls:?}"
+        );
+    }
+}
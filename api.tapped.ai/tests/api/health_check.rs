@@ -1,4 +1,9 @@
-use crate::helpers::spawn_app;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::helpers::{spawn_app, TEST_USER_ID};
 
 #[tokio::test]
 async fn health_check_works() {
@@ -7,7 +12,7 @@ async fn health_check_works() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(&format!("{}/health", &app.address))
+        .get(format!("{}/health", &app.address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -15,3 +20,84 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(15), response.content_length());
 }
+
+#[tokio::test]
+async fn liveness_does_not_touch_dependencies() {
+    let app = spawn_app().await;
+    app.database.set_unreachable(true);
+
+    let response = app.get("/health/live").send().await.unwrap();
+
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    assert!(app.database.calls().is_empty());
+}
+
+#[tokio::test]
+async fn readiness_reports_each_dependency() {
+    let app = spawn_app().await;
+
+    let response = app.get("/health/ready").send().await.unwrap();
+
+    assert_eq!(reqwest::StatusCode::OK, response.status());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("ok", body["status"]);
+    assert_eq!("ok", body["dependencies"]["database"]["status"]);
+    assert_eq!("ok", body["dependencies"]["search"]["status"]);
+    assert!(body["dependencies"]["database"]["latencyMs"].is_u64());
+    assert_eq!(vec!["ping"], app.database.calls());
+}
+
+#[tokio::test]
+async fn readiness_fails_when_the_database_is_unreachable() {
+    let app = spawn_app().await;
+    app.database.set_unreachable(true);
+
+    let response = app.get("/health/ready").send().await.unwrap();
+
+    assert_eq!(reqwest::StatusCode::SERVICE_UNAVAILABLE, response.status());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("unavailable", body["status"]);
+    assert_eq!("unavailable", body["dependencies"]["database"]["status"]);
+    assert_eq!(
+        "database is unreachable",
+        body["dependencies"]["database"]["error"]
+    );
+    assert_eq!("ok", body["dependencies"]["search"]["status"]);
+}
+
+#[tokio::test]
+async fn shutdown_stops_accepting_connections() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let live = format!("{}/health/live", app.address);
+
+    app.shutdown.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(5), app.server)
+        .await
+        .expect("Server did not stop");
+
+    assert!(result.unwrap().is_ok());
+    assert!(client.get(live).send().await.is_err());
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_on_open_streams_after_the_timeout() {
+    let app = spawn_app().await;
+    let stream = app
+        .get(&format!("/v1/bookings/stream?bookerId={TEST_USER_ID}"))
+        .send()
+        .await
+        .unwrap();
+    assert!(stream.status().is_success());
+
+    let started = Instant::now();
+    app.shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), app.server)
+        .await
+        .expect("Server did not stop")
+        .unwrap()
+        .unwrap();
+
+    // The test app drains for one second.
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
@@ -1,9 +1,32 @@
+use chrono::Utc;
 use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
 use tapped_api_rs::{
-    environment::Environment,
+    configuration::{get_configuration, Settings},
+    data::{
+        change_feed::InMemoryChangeFeed,
+        export_files::LocalExportFiles,
+        mailer::InMemoryMailer,
+        memory::InMemoryDatabase,
+        metered::{MeteredDatabase, MeteredSearch},
+        search::InMemorySearch,
+    },
+    domain::{
+        export::jobs::ExportJobs,
+        models::api_key::{ApiKey, ApiScope},
+        webhooks::dispatcher::{WebhookDispatcher, WebhookPolicy},
+    },
+    metrics::Metrics,
     startup::Application,
+    state::AppStateDyn,
     tracing::{get_subscriber, init_subscriber},
 };
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+pub const TEST_API_KEY: &str = "test-api-key";
+pub const TEST_USER_ID: &str = "test-user";
+pub const TEST_ADMIN_API_KEY: &str = "test-admin-api-key";
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter_level = "info".to_string();
@@ -22,32 +45,132 @@ pub struct TestApp {
     pub address: String,
     pub port: u16,
     pub api_client: reqwest::Client,
+    pub database: InMemoryDatabase,
+    pub change_feed: InMemoryChangeFeed,
+    pub mailer: InMemoryMailer,
+    /// Cancel to send the server its shutdown signal.
+    pub shutdown: CancellationToken,
+    pub server: JoinHandle<Result<(), std::io::Error>>,
 }
 
-impl TestApp {}
+impl TestApp {
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.api_client
+            .request(method, format!("{}{}", &self.address, path))
+            .header("tapped-api-key", TEST_API_KEY)
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, path)
+    }
+
+    /// A request made with a key that has the `admin` scope.
+    pub fn admin(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.api_client
+            .request(method, format!("{}{}", &self.address, path))
+            .header("tapped-api-key", TEST_ADMIN_API_KEY)
+    }
+}
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the app with settings adjusted by `configure`.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
-    let application = Application::build(3000, "in-the-loop-306520".into(), Environment::Stage)
+    let database = InMemoryDatabase::new();
+    database.insert_api_key(ApiKey {
+        key: TEST_API_KEY.into(),
+        id: "test-api-key-id".into(),
+        user_id: TEST_USER_ID.into(),
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+    database.insert_api_key(ApiKey {
+        key: TEST_ADMIN_API_KEY.into(),
+        id: "test-admin-api-key-id".into(),
+        user_id: "test-admin".into(),
+        scopes: vec![ApiScope::Admin],
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+    let change_feed = InMemoryChangeFeed::default();
+    let mailer = InMemoryMailer::new();
+
+    // Plain HTTP receivers on localhost and fast retries keep the webhook
+    // tests quick.
+    let webhook_policy = WebhookPolicy {
+        require_https: false,
+        allow_private_hosts: true,
+        max_attempts: 3,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(50),
+        request_timeout: Duration::from_secs(2),
+    };
+
+    let metrics = Metrics::new();
+    let state = AppStateDyn {
+        database: Arc::new(MeteredDatabase::new(
+            Arc::new(database.clone()),
+            metrics.clone(),
+        )),
+        search: Arc::new(MeteredSearch::new(
+            Arc::new(InMemorySearch::new(database.clone())),
+            "memory",
+            metrics.clone(),
+        )),
+        change_feed: Arc::new(change_feed.clone()),
+        webhooks: WebhookDispatcher::new(Arc::new(database.clone()), webhook_policy),
+        exports: ExportJobs::new(
+            Arc::new(database.clone()),
+            Arc::new(LocalExportFiles::new(
+                std::env::temp_dir().join(format!("tapped-exports-{}", uuid::Uuid::new_v4())),
+            )),
+            Duration::from_secs(60),
+        ),
+        mailer: Arc::new(mailer.clone()),
+        metrics,
+    };
+
+    let mut settings = get_configuration().expect("Failed to read configuration");
+    settings.server.port = 0;
+    settings.server.shutdown_timeout_secs = 1;
+    // Tests share one key and poll quickly; the limit has its own tests.
+    settings.rate_limit.requests_per_second = 10_000;
+    settings.rate_limit.burst = 10_000;
+    configure(&mut settings);
+
+    let application = Application::build_with_state(&settings, state)
         .await
         .expect("Failed to build application");
+    // Only count the calls made while serving the test's requests.
+    database.clear_calls();
 
     let application_port = application.port();
     let address = format!("http://localhost:{}", application_port);
 
-    let _ = tokio::spawn(application.run_until_stopped());
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(application.run_until(shutdown.clone().cancelled_owned()));
 
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
         .build()
         .unwrap();
 
-    let test_app = TestApp {
+    TestApp {
         address,
         port: application_port,
         api_client: client,
-    };
-
-    test_app
+        database,
+        change_feed,
+        mailer,
+        shutdown,
+        server,
+    }
 }
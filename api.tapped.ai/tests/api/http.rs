@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::{
+    header::{
+        ACCEPT_ENCODING, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_HEADERS,
+        ACCESS_CONTROL_REQUEST_METHOD, CONTENT_ENCODING, ORIGIN,
+    },
+    Method, StatusCode,
+};
+use serde_json::json;
+use tapped_api_rs::domain::models::{api_key::ApiKey, user::UserModel};
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp, TEST_API_KEY};
+
+const PARTNER_ORIGIN: &str = "https://dashboard.partner.example";
+
+fn seed_performers(app: &TestApp) {
+    for i in 0..10 {
+        app.database.insert_user(UserModel::fixture(
+            &format!("performer-{i}"),
+            json!({
+                "artistName": format!("Performer {i}"),
+                "bio": "A band that plays long sets of loud, loud music. ".repeat(4),
+                "performerInfo": { "genres": ["rock"] },
+            }),
+        ));
+    }
+}
+
+/// Sends requests with our own `Accept-Encoding`, leaving responses as they
+/// came over the wire.
+async fn get_encoded(app: &TestApp, path: &str, accept_encoding: &str) -> reqwest::Response {
+    reqwest::Client::builder()
+        .no_gzip()
+        .no_brotli()
+        .build()
+        .unwrap()
+        .get(format!("{}{}", app.address, path))
+        .header("tapped-api-key", TEST_API_KEY)
+        .header(ACCEPT_ENCODING, accept_encoding)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+async fn preflight(app: &TestApp, origin: &str) -> reqwest::Response {
+    app.api_client
+        .request(
+            Method::OPTIONS,
+            format!("{}/v1/performer/search", app.address),
+        )
+        .header(ORIGIN, origin)
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .header(ACCESS_CONTROL_REQUEST_HEADERS, "tapped-api-key")
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn preflights_from_allowed_origins_succeed() {
+    let app = spawn_app_with(|settings| {
+        settings.cors.allowed_origins = vec![PARTNER_ORIGIN.into()];
+    })
+    .await;
+
+    let response = preflight(&app, PARTNER_ORIGIN).await;
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        PARTNER_ORIGIN,
+        response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]
+    );
+
+    let response = preflight(&app, "https://elsewhere.example").await;
+
+    assert!(response
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
+
+#[tokio::test]
+async fn api_keys_can_be_restricted_to_origins() {
+    let app = spawn_app_with(|settings| {
+        settings.cors.allowed_origins = vec!["*".into()];
+    })
+    .await;
+    app.database.insert_api_key(ApiKey {
+        key: "partner-key".into(),
+        user_id: "partner".into(),
+        allowed_origins: vec![PARTNER_ORIGIN.into()],
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+    let request = |origin: &str| {
+        app.api_client
+            .get(format!("{}/v1/performer/search", app.address))
+            .header("tapped-api-key", "partner-key")
+            .header(ORIGIN, origin)
+            .send()
+    };
+
+    let response = request(PARTNER_ORIGIN).await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!("*", response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]);
+
+    let response = request("https://elsewhere.example").await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[tokio::test]
+async fn large_responses_are_compressed() {
+    let app = spawn_app().await;
+    seed_performers(&app);
+
+    for encoding in ["gzip", "br"] {
+        let response = get_encoded(&app, "/v1/performer/search?genres=rock", encoding).await;
+
+        assert_eq!(encoding, response.headers()[CONTENT_ENCODING]);
+    }
+
+    // Too small to be worth it.
+    let response = get_encoded(&app, "/health/live", "gzip").await;
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn compression_can_be_disabled() {
+    let app = spawn_app_with(|settings| settings.http.compression = false).await;
+    seed_performers(&app);
+
+    let response = get_encoded(&app, "/v1/performer/search?genres=rock", "gzip, br").await;
+
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn slow_requests_time_out() {
+    let app = spawn_app_with(|settings| settings.http.request_timeout_secs = 1).await;
+    app.database.set_latency(Duration::from_secs(3));
+
+    let response = app.get("/v1/performer/someone").send().await.unwrap();
+
+    assert_eq!(StatusCode::REQUEST_TIMEOUT, response.status());
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_rejected() {
+    let app = spawn_app_with(|settings| settings.http.body_limit_bytes = 1024).await;
+
+    let response = app
+        .post("/v1/graphql")
+        .json(&json!({ "query": "x".repeat(2048) }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+}
+
+#[tokio::test]
+async fn requests_past_the_concurrency_limit_are_shed() {
+    let app = spawn_app_with(|settings| settings.http.concurrency_limit = 1).await;
+    app.database.set_latency(Duration::from_millis(500));
+
+    let (first, second) = tokio::join!(app.get("/v1/performer/someone").send(), async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        app.get("/v1/performer/someone").send().await
+    });
+
+    assert_ne!(StatusCode::SERVICE_UNAVAILABLE, first.unwrap().status());
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, second.unwrap().status());
+}
+
+#[tokio::test]
+async fn api_keys_over_their_rate_limit_are_turned_away() {
+    let app = spawn_app_with(|settings| {
+        settings.rate_limit.requests_per_second = 1;
+        settings.rate_limit.burst = 2;
+    })
+    .await;
+    seed_performers(&app);
+
+    for _ in 0..2 {
+        let response = app.get("/v1/performer/performer-0").send().await.unwrap();
+        assert_eq!(200, response.status().as_u16());
+    }
+    let response = app.get("/v1/performer/performer-0").send().await.unwrap();
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!("1", response.headers()["retry-after"]);
+
+    // Each key has its own limit.
+    let response = app
+        .admin(Method::GET, "/v1/performer/performer-0")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn calendar_feeds_are_limited_by_address() {
+    let app = spawn_app_with(|settings| {
+        settings.rate_limit.requests_per_second = 1;
+        settings.rate_limit.burst = 2;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let feed = format!("{}/v1/performer/performer-0/calendar.ics", app.address);
+
+    for _ in 0..2 {
+        let response = client.get(&feed).send().await.unwrap();
+        assert_eq!(401, response.status().as_u16());
+    }
+    let response = client.get(&feed).send().await.unwrap();
+    assert_eq!(429, response.status().as_u16());
+
+    // Keys are limited on their own, not by address.
+    let response = app.get("/v1/performer/performer-0").send().await.unwrap();
+    assert_ne!(429, response.status().as_u16());
+}
@@ -1,2 +1,23 @@
+pub mod api_keys;
+pub mod availability;
+pub mod booking_stream;
+pub mod bookings;
+pub mod claims;
+pub mod deleted_users;
+pub mod events;
+pub mod export;
+pub mod fieldsets;
+pub mod fixtures;
+pub mod graphql;
 pub mod health_check;
 pub mod helpers;
+pub mod http;
+pub mod matchmaking;
+pub mod metrics;
+pub mod pricing;
+pub mod redaction;
+pub mod request_id;
+pub mod reviews;
+pub mod tech_rider;
+pub mod versioning;
+pub mod webhooks;
@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tapped_api_rs::{
+    data::fixtures::Fixtures,
+    domain::models::{
+        booking::{Booking, BookingStatus},
+        user::UserModel,
+    },
+};
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+
+const NYC: (f64, f64) = (40.7128, -74.0060);
+
+fn at((lat, lng): (f64, f64)) -> Value {
+    json!({ "placeId": "place", "lat": lat, "lng": lng })
+}
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "venue",
+        json!({
+            "location": at(NYC),
+            "venueInfo": { "capacity": 200, "genres": ["rock"] },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "rock-band",
+        json!({
+            "location": at(NYC),
+            "socialFollowing": { "instagramFollowers": 50_000 },
+            "performerInfo": { "genres": ["rock"], "rating": 4.5, "reviewCount": 10 },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "jazz-trio",
+        json!({
+            "location": at((40.75, -73.98)),
+            "performerInfo": { "genres": ["jazz"] },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "far-away",
+        json!({
+            "location": at((34.0522, -118.2437)),
+            "performerInfo": { "genres": ["rock"] },
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "other-venue",
+        json!({
+            "location": at((40.72, -74.0)),
+            "venueInfo": { "capacity": 150, "genres": ["rock", "punk"] },
+        }),
+    ));
+    app.database.insert_booking(Booking {
+        id: "gig".into(),
+        requester_id: Some("other-venue".into()),
+        requestee_id: "rock-band".into(),
+        status: BookingStatus::Confirmed,
+        venue_id: Some("other-venue".into()),
+        ..Default::default()
+    });
+}
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.get(path)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn venues_get_nearby_performers_best_match_first() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = get(&app, "/v1/venue/venue/recommended-performers").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    let recommendations = body["recommendations"].as_array().unwrap();
+    let ids: Vec<&str> = recommendations
+        .iter()
+        .map(|r| r["performer"]["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["rock-band", "jazz-trio"], ids);
+
+    let best = &recommendations[0];
+    let factors: Vec<&str> = best["factors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["factor"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        vec!["genre", "audience", "distance", "history", "rating"],
+        factors
+    );
+    assert_eq!("shares rock", best["factors"][0]["reason"]);
+    assert_eq!(1.0, best["factors"][0]["score"]);
+    assert_eq!(0.2, best["factors"][3]["score"]);
+    assert!(best["score"].as_f64().unwrap() > recommendations[1]["score"].as_f64().unwrap());
+}
+
+#[tokio::test]
+async fn performers_get_nearby_venues() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = get(&app, "/v1/performer/rock-band/recommended-venues?limit=1").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("rock-band", body["id"]);
+    let recommendations = body["recommendations"].as_array().unwrap();
+    assert_eq!(1, recommendations.len());
+    assert_eq!("venue", recommendations[0]["venue"]["id"]);
+}
+
+#[tokio::test]
+async fn recommendations_need_a_known_venue_and_a_sane_limit() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    for (path, status) in [
+        ("/v1/venue/rock-band/recommended-performers", 404),
+        ("/v1/venue/missing/recommended-performers", 404),
+        ("/v1/performer/missing/recommended-venues", 404),
+        ("/v1/venue/venue/recommended-performers?limit=0", 400),
+        ("/v1/performer/rock-band/recommended-venues?limit=51", 400),
+    ] {
+        assert_eq!(status, get(&app, path).await.status().as_u16(), "{path}");
+    }
+}
+
+fn seed_fixtures(app: &TestApp) {
+    Fixtures::load(Path::new("fixtures"))
+        .expect("Failed to load fixtures")
+        .seed_memory(&app.database);
+}
+
+async fn similar_ids(app: &TestApp, id: &str) -> Vec<String> {
+    let body: Value = get(app, &format!("/v1/performer/{id}/similar"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    body["recommendations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["performer"]["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn similar_performers_are_ranked_on_fixture_data() {
+    let app = spawn_app().await;
+    seed_fixtures(&app);
+
+    // DJ Solstice draws a crowd of the same size a tier up; the jazz act is
+    // in town but unknown. Venues and deleted users are never suggested.
+    assert_eq!(
+        vec!["performer-dj-solstice", "performer-unclaimed-jazz"],
+        similar_ids(&app, "performer-midnight-echo").await
+    );
+
+    let body: Value = get(&app, "/v1/performer/performer-midnight-echo/similar")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let best = &body["recommendations"][0];
+    assert_eq!(0.35, best["score"]);
+    let factors: Vec<&str> = best["factors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["factor"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        vec!["genre", "label", "category", "audience", "distance"],
+        factors
+    );
+}
+
+#[tokio::test]
+async fn similarity_weights_are_configurable() {
+    let app = spawn_app_with(|settings| {
+        settings.similarity.genres = 0.0;
+        settings.similarity.label = 0.0;
+        settings.similarity.category = 0.0;
+        settings.similarity.audience = 0.0;
+        settings.similarity.location = 2.0;
+    })
+    .await;
+    seed_fixtures(&app);
+
+    assert_eq!(
+        vec!["performer-unclaimed-jazz", "performer-dj-solstice"],
+        similar_ids(&app, "performer-midnight-echo").await
+    );
+}
+
+#[tokio::test]
+async fn only_performers_have_similar_performers() {
+    let app = spawn_app().await;
+    seed_fixtures(&app);
+
+    for path in [
+        "/v1/performer/venue-the-canal-club/similar",
+        "/v1/performer/missing/similar",
+    ] {
+        assert_eq!(404, get(&app, path).await.status().as_u16(), "{path}");
+    }
+}
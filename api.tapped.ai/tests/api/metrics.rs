@@ -0,0 +1,98 @@
+use serde_json::json;
+use tapped_api_rs::domain::models::user::UserModel;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn metrics(app: &TestApp) -> String {
+    let response = app
+        .admin(reqwest::Method::GET, "/metrics")
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert!(response.status().is_success());
+
+    response.text().await.unwrap()
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_and_status() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": { "genres": ["jazz"] } }),
+    ));
+
+    app.get("/v1/performer/performer").send().await.unwrap();
+    app.get("/v1/performer/performer").send().await.unwrap();
+    app.get("/v1/performer/missing").send().await.unwrap();
+    app.get("/health/live").send().await.unwrap();
+
+    let body = metrics(&app).await;
+    assert!(body
+        .contains(r#"http_requests_total{method="GET",path="/v1/performer/:id",status="200"} 2"#));
+    assert!(body
+        .contains(r#"http_requests_total{method="GET",path="/v1/performer/:id",status="404"} 1"#));
+    assert!(
+        body.contains(r#"http_requests_total{method="GET",path="/health/live",status="200"} 1"#)
+    );
+    assert!(body.contains("http_request_duration_seconds_bucket"));
+}
+
+#[tokio::test]
+async fn database_calls_and_searches_are_timed() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": { "genres": ["jazz"] } }),
+    ));
+
+    app.get("/v1/performer/performer").send().await.unwrap();
+    app.get("/v1/performer/search?query=perf")
+        .send()
+        .await
+        .unwrap();
+
+    let body = metrics(&app).await;
+    assert!(body.contains(r#"database_calls_total{method="get_user_by_id",outcome="ok"} 1"#));
+    assert!(body.contains(r#"database_call_duration_seconds_count{method="get_user_by_id"} 1"#));
+    assert!(body.contains(r#"search_duration_seconds_count{backend="memory",outcome="ok"} 1"#));
+}
+
+#[tokio::test]
+async fn auth_failures_are_counted_by_reason() {
+    let app = spawn_app().await;
+
+    app.api_client
+        .get(format!("{}/v1/performer/performer", app.address))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .api_client
+        .get(format!("{}/v1/performer/performer", app.address))
+        .header("tapped-api-key", "not-a-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+
+    let body = metrics(&app).await;
+    assert!(body.contains(r#"auth_failures_total{reason="missing_key"} 1"#));
+    assert!(body.contains(r#"auth_failures_total{reason="invalid_key"} 1"#));
+}
+
+#[tokio::test]
+async fn metrics_require_an_admin_key() {
+    let app = spawn_app().await;
+
+    let anonymous = app
+        .api_client
+        .get(format!("{}/metrics", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, anonymous.status());
+
+    let response = app.get("/metrics").send().await.unwrap();
+    assert_eq!(reqwest::StatusCode::FORBIDDEN, response.status());
+}
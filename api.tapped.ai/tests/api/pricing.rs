@@ -0,0 +1,117 @@
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+fn gig(id: &str, performer_id: &str, rate: f64) -> Booking {
+    Booking {
+        id: id.into(),
+        requester_id: Some("venue".into()),
+        requestee_id: performer_id.into(),
+        status: BookingStatus::Confirmed,
+        rate,
+        ..Default::default()
+    }
+}
+
+async fn get_performer(app: &TestApp, id: &str, query: &str) -> Value {
+    let response = app
+        .get(&format!("/v1/performer/{id}{query}"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(200, response.status().as_u16());
+
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn rate_estimates_are_only_computed_when_included() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": { "genres": ["rock"], "category": "emerging" } }),
+    ));
+
+    let body = get_performer(&app, "performer", "").await;
+
+    assert!(body.get("rateEstimate").is_none());
+    assert_eq!(
+        json!({ "min": 1000, "max": 2000 }),
+        body["averageTicketRange"]
+    );
+}
+
+#[tokio::test]
+async fn performers_with_enough_gigs_are_estimated_from_their_own_rates() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": { "genres": ["rock"] } }),
+    ));
+    for (id, rate) in [("a", 400.0), ("b", 600.0), ("c", 800.0)] {
+        app.database.insert_booking(gig(id, "performer", rate));
+    }
+
+    let body = get_performer(&app, "performer", "?include=rateEstimate").await;
+
+    assert_eq!(
+        json!({
+            "min": 500.0,
+            "max": 700.0,
+            "currency": "USD",
+            "confidence": 0.65,
+            "basis": "history",
+            "sampleSize": 3,
+        }),
+        body["rateEstimate"]
+    );
+}
+
+#[tokio::test]
+async fn new_performers_are_compared_with_acts_in_their_category_and_genres() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "newcomer",
+        json!({ "performerInfo": { "genres": ["rock"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "peer",
+        json!({ "performerInfo": { "genres": ["rock", "punk"] } }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "headliner",
+        json!({ "performerInfo": { "genres": ["rock"], "category": "mainstream" } }),
+    ));
+    for id in ["a", "b", "c"] {
+        app.database.insert_booking(gig(id, "peer", 300.0));
+    }
+    app.database
+        .insert_booking(gig("arena", "headliner", 50_000.0));
+
+    let body = get_performer(&app, "newcomer", "?include=rateEstimate").await;
+
+    assert_eq!("comparables", body["rateEstimate"]["basis"]);
+    assert_eq!(3, body["rateEstimate"]["sampleSize"]);
+    assert_eq!(300.0, body["rateEstimate"]["min"]);
+    assert_eq!(300.0, body["rateEstimate"]["max"]);
+}
+
+#[tokio::test]
+async fn performers_with_nothing_to_go_on_get_the_category_range() {
+    let app = spawn_app().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": { "genres": ["rock"], "category": "emerging" } }),
+    ));
+
+    let body = get_performer(&app, "performer", "?include=rateEstimate").await;
+
+    assert_eq!("category", body["rateEstimate"]["basis"]);
+    assert_eq!(10.0, body["rateEstimate"]["min"]);
+    assert_eq!(20.0, body["rateEstimate"]["max"]);
+    assert_eq!(0.1, body["rateEstimate"]["confidence"]);
+}
@@ -0,0 +1,153 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    api_key::{ApiKey, ApiScope},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_API_KEY};
+
+const CONTACTS_API_KEY: &str = "contacts-api-key";
+
+async fn app_with_venues() -> TestApp {
+    let app = spawn_app().await;
+    app.database.insert_api_key(ApiKey {
+        key: CONTACTS_API_KEY.into(),
+        id: "contacts-api-key-id".into(),
+        user_id: "booker".into(),
+        scopes: vec![ApiScope::ContactsRead],
+        timestamp: Utc::now(),
+        ..Default::default()
+    });
+
+    let venue = |fields: Value| {
+        let mut venue = json!({
+            "artistName": "The Venue",
+            "location": { "placeId": "nyc", "lat": 40.7, "lng": -74.0 },
+            "venueInfo": { "bookingEmail": "talent@venue.example", "capacity": 300 },
+            "emailNotifications": { "bookingRequests": true },
+        });
+        venue
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        venue
+    };
+    app.database
+        .insert_user(UserModel::fixture("venue", venue(json!({}))));
+    app.database.insert_user(UserModel::fixture(
+        "private-venue",
+        venue(json!({ "privacy": { "showLocation": false, "showContactDetails": false } })),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "unclaimed-venue",
+        venue(json!({ "unclaimed": true })),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "deleted-venue",
+        venue(json!({ "deleted": true })),
+    ));
+
+    app
+}
+
+/// The venues as returned by the venue export, by id.
+async fn venues(app: &TestApp, api_key: Option<&str>) -> Vec<Value> {
+    let body = app
+        .api_client
+        .get(format!("{}/v1/export/venues", app.address))
+        .header("tapped-api-key", api_key.unwrap_or(TEST_API_KEY))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .text()
+        .await
+        .unwrap();
+
+    let mut venues: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    venues.sort_by_key(|venue| venue["id"].as_str().unwrap().to_string());
+
+    venues
+}
+
+fn by_id<'a>(venues: &'a [Value], id: &str) -> &'a Value {
+    venues.iter().find(|venue| venue["id"] == id).unwrap()
+}
+
+#[tokio::test]
+async fn booking_emails_need_the_contacts_scope() {
+    let app = app_with_venues().await;
+
+    let public = venues(&app, None).await;
+    assert_eq!(Value::Null, by_id(&public, "venue")["bookingEmail"]);
+
+    let contacts = venues(&app, Some(CONTACTS_API_KEY)).await;
+    assert_eq!(
+        "talent@venue.example",
+        by_id(&contacts, "venue")["bookingEmail"]
+    );
+}
+
+#[tokio::test]
+async fn privacy_settings_hide_fields_from_every_key() {
+    let app = app_with_venues().await;
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({
+            "location": { "placeId": "nyc", "lat": 40.7, "lng": -74.0 },
+            "socialFollowing": { "instagramHandle": "theperformer", "instagramFollowers": 900 },
+            "performerInfo": { "genres": ["rock"] },
+            "privacy": { "showLocation": false, "showSocialHandles": false },
+        }),
+    ));
+
+    let performer: Value = app
+        .get("/v1/performer/performer")
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(Value::Null, performer["location"]);
+    assert_eq!(Value::Null, performer["socialFollowing"]["instagramHandle"]);
+    assert_eq!(900, performer["socialFollowing"]["instagramFollowers"]);
+
+    let venues = venues(&app, Some(CONTACTS_API_KEY)).await;
+    let private = by_id(&venues, "private-venue");
+    assert_eq!(Value::Null, private["location"]);
+    assert_eq!(Value::Null, private["bookingEmail"]);
+}
+
+#[tokio::test]
+async fn unclaimed_and_deleted_users_are_redacted_for_every_key() {
+    let app = app_with_venues().await;
+
+    let venues = venues(&app, Some(CONTACTS_API_KEY)).await;
+    let unclaimed = by_id(&venues, "unclaimed-venue");
+    assert_eq!(Value::Null, unclaimed["bookingEmail"]);
+    assert_eq!("The Venue", unclaimed["displayName"]);
+
+    let response: Value = app
+        .api_client
+        .post(format!("{}/v1/graphql", app.address))
+        .header("tapped-api-key", CONTACTS_API_KEY)
+        .json(&json!({
+            "query": r#"{
+                unclaimed: venue(id: "unclaimed-venue") { bookingEmail }
+                deleted: venue(id: "deleted-venue") { displayName bio bookingEmail location { lat } }
+            }"#
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(Value::Null, response["data"]["unclaimed"]["bookingEmail"]);
+    // Deleted users aren't read at all.
+    assert_eq!(Value::Null, response["data"]["deleted"]);
+}
@@ -0,0 +1,82 @@
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::helpers::spawn_app;
+
+fn request_id(response: &reqwest::Response) -> Uuid {
+    let header = response.headers()["x-request-id"].to_str().unwrap();
+
+    Uuid::parse_str(header).expect("x-request-id is not a UUID")
+}
+
+#[tokio::test]
+async fn a_request_id_is_generated_when_missing() {
+    let app = spawn_app().await;
+
+    let first = app.get("/health/live").send().await.unwrap();
+    let second = app.get("/health/live").send().await.unwrap();
+
+    assert_ne!(request_id(&first), request_id(&second));
+}
+
+#[tokio::test]
+async fn the_callers_request_id_is_echoed() {
+    let app = spawn_app().await;
+    let id = Uuid::new_v4();
+
+    let response = app
+        .get("/health/live")
+        .header("x-request-id", id.to_string())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(id, request_id(&response));
+}
+
+#[tokio::test]
+async fn request_ids_that_are_not_uuids_are_replaced() {
+    let app = spawn_app().await;
+
+    let response = app
+        .get("/health/live")
+        .header("x-request-id", "not-a-uuid")
+        .send()
+        .await
+        .unwrap();
+
+    request_id(&response);
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let app = spawn_app().await;
+    let id = Uuid::new_v4();
+
+    let response = app
+        .get("/v1/export/jobs/nope")
+        .header("x-request-id", id.to_string())
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(reqwest::StatusCode::NOT_FOUND, response.status());
+    assert_eq!(id, request_id(&response));
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(id.to_string(), body["error_id"]);
+}
+
+#[tokio::test]
+async fn unauthorized_requests_still_get_a_request_id() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/v1/performer/someone", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+    request_id(&response);
+}
@@ -0,0 +1,203 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    review::{ModerationStatus, Review, ReviewType},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_USER_ID};
+
+/// A confirmed booking that was played last week.
+fn played(id: &str, booker_id: &str, performer_id: &str) -> Booking {
+    let start_time = Utc::now() - Duration::days(7);
+
+    Booking {
+        id: id.into(),
+        requester_id: Some(booker_id.into()),
+        requestee_id: performer_id.into(),
+        status: BookingStatus::Confirmed,
+        start_time,
+        end_time: start_time + Duration::hours(2),
+        ..Default::default()
+    }
+}
+
+fn seed(app: &TestApp) {
+    app.database
+        .insert_user(UserModel::fixture("performer", json!({})));
+    app.database
+        .insert_booking(played("booking", TEST_USER_ID, "performer"));
+}
+
+async fn submit(app: &TestApp, body: Value) -> reqwest::Response {
+    app.post("/v1/reviews")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+fn review(booking_id: &str) -> Value {
+    json!({ "bookingId": booking_id, "rating": 4.5, "text": "tight set, great crowd" })
+}
+
+#[tokio::test]
+async fn bookers_can_review_the_performer_pending_moderation() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = submit(&app, review("booking")).await;
+
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("pending", body["moderationStatus"]);
+    assert_eq!("performer", body["performerId"]);
+    assert_eq!(TEST_USER_ID, body["bookerId"]);
+    assert_eq!(4.5, body["rating"]);
+
+    let performer: Value = app
+        .get("/v1/performer/performer")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(0, performer["reviews"]["count"]);
+}
+
+#[tokio::test]
+async fn each_side_of_a_booking_can_review_once() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    assert_eq!(201, submit(&app, review("booking")).await.status().as_u16());
+    assert_eq!(409, submit(&app, review("booking")).await.status().as_u16());
+
+    // The venue has reviewed this performer; the performer can still
+    // review the venue.
+    app.database
+        .insert_booking(played("gig", "venue", TEST_USER_ID));
+    app.database.insert_review(Review {
+        id: "venue-review".into(),
+        booker_id: "venue".into(),
+        performer_id: TEST_USER_ID.into(),
+        booking_id: "gig".into(),
+        timestamp: Utc::now(),
+        overall_rating: 5.0,
+        overall_review: "great".into(),
+        review_type: ReviewType::Performer,
+        moderation_status: ModerationStatus::Approved,
+    });
+    let response = submit(&app, review("gig")).await;
+    assert_eq!(201, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("venue", body["bookerId"]);
+}
+
+#[tokio::test]
+async fn only_parties_to_a_confirmed_booking_can_review_it() {
+    let app = spawn_app().await;
+    seed(&app);
+    app.database
+        .insert_booking(played("someone-elses", "venue", "performer"));
+    app.database.insert_booking(Booking {
+        status: BookingStatus::Pending,
+        ..played("pending", TEST_USER_ID, "performer")
+    });
+    app.database.insert_booking(Booking {
+        end_time: Utc::now() + Duration::hours(1),
+        ..played("tonight", TEST_USER_ID, "performer")
+    });
+
+    assert_eq!(
+        403,
+        submit(&app, review("someone-elses"))
+            .await
+            .status()
+            .as_u16()
+    );
+    assert_eq!(409, submit(&app, review("pending")).await.status().as_u16());
+    assert_eq!(422, submit(&app, review("tonight")).await.status().as_u16());
+    assert_eq!(404, submit(&app, review("missing")).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn ratings_and_text_are_validated() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    for body in [
+        json!({ "bookingId": "booking", "rating": 0.5, "text": "meh" }),
+        json!({ "bookingId": "booking", "rating": 5.5, "text": "wow" }),
+        json!({ "bookingId": "booking", "rating": 3.0, "text": "   " }),
+        json!({ "bookingId": "booking", "rating": 3.0, "text": "a".repeat(2001) }),
+    ] {
+        assert_eq!(400, submit(&app, body).await.status().as_u16());
+    }
+    assert!(!app.database.calls().contains(&"create_review"));
+}
+
+#[tokio::test]
+async fn review_summaries_have_no_rating_without_reviews() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let performer: Value = app
+        .get("/v1/performer/performer")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(0, performer["reviews"]["count"]);
+    assert!(performer["reviews"]["rating"].is_null());
+    assert!(performer["reviews"]["summary"]["bayesianAverage"].is_null());
+}
+
+#[tokio::test]
+async fn review_summaries_include_a_histogram_and_smoothed_averages() {
+    let app = spawn_app().await;
+    seed(&app);
+    for (id, rating) in [("first", 5.0), ("second", 4.0)] {
+        app.database.insert_review(Review {
+            id: id.into(),
+            booker_id: TEST_USER_ID.into(),
+            performer_id: "performer".into(),
+            booking_id: "booking".into(),
+            timestamp: Utc::now(),
+            overall_rating: rating,
+            overall_review: "great".into(),
+            review_type: ReviewType::Performer,
+            moderation_status: ModerationStatus::Approved,
+        });
+    }
+
+    let performer: Value = app
+        .get("/v1/performer/performer")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let reviews = &performer["reviews"];
+    assert_eq!(4.5, reviews["rating"]);
+    // (5 * 3 + 9) / 7
+    assert_eq!(3.43, reviews["summary"]["bayesianAverage"]);
+    assert_eq!(4.5, reviews["summary"]["recentAverage"]);
+    assert_eq!(
+        json!([
+            { "stars": 1, "count": 0 },
+            { "stars": 2, "count": 0 },
+            { "stars": 3, "count": 0 },
+            { "stars": 4, "count": 1 },
+            { "stars": 5, "count": 1 },
+        ]),
+        reviews["summary"]["histogram"]
+    );
+}
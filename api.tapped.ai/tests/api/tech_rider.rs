@@ -0,0 +1,114 @@
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::user::UserModel;
+
+use crate::helpers::{spawn_app, TestApp};
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "club",
+        json!({
+            "venueInfo": {
+                "capacity": 300,
+                "productionInfo": "In-house engineer. Stage 6m x 4m. Backline: drum kit, bass amp.",
+                "frontOfHouse": "Midas M32, 32 channels",
+                "monitors": "6 wedges, 4 mixes, IEMs available",
+                "microphones": "SM58 x8",
+            }
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "bar",
+        json!({
+            "venueInfo": {
+                "capacity": 80,
+                "frontOfHouse": "16ch analog desk",
+                "monitors": "2 wedges, 1 mix",
+            }
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "structured",
+        json!({
+            "venueInfo": {
+                "capacity": 500,
+                "monitors": "ask us",
+                "techRider": { "channels": 48, "inEarMonitors": true, "backline": ["Drum kit"] },
+            }
+        }),
+    ));
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({ "performerInfo": {} }),
+    ));
+}
+
+async fn venue_ids(app: &TestApp, filters: &str) -> Vec<String> {
+    let body = app
+        .get(&format!("/v1/export/venues?fields=id&{filters}"))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .text()
+        .await
+        .unwrap();
+
+    body.lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .map(|venue| venue["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn venues_expose_a_tech_rider_parsed_from_free_text() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let body = app
+        .get("/v1/export/venues?fields=id,techRider")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let club: Value = body
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|venue| venue["id"] == "club")
+        .unwrap();
+
+    let rider = &club["techRider"];
+    assert_eq!("Midas M32", rider["mixingDesk"]);
+    assert_eq!(32, rider["channels"]);
+    assert_eq!(4, rider["monitorMixes"]);
+    assert_eq!(6, rider["wedges"]);
+    assert_eq!(true, rider["inEarMonitors"]);
+    assert_eq!(true, rider["soundEngineer"]);
+    assert_eq!(json!({ "width": 6.0, "depth": 4.0 }), rider["stage"]);
+    assert_eq!(json!(["drum kit", "bass amp"]), rider["backline"]);
+    assert_eq!(
+        json!([{ "name": "SM58", "quantity": 8 }]),
+        rider["microphones"]
+    );
+}
+
+#[tokio::test]
+async fn venues_can_be_filtered_by_tech_rider() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    assert_eq!(
+        vec!["club", "structured"],
+        venue_ids(&app, "inEarMonitors=true").await
+    );
+    assert_eq!(vec!["structured"], venue_ids(&app, "minChannels=40").await);
+    assert_eq!(
+        vec!["bar", "club", "structured"],
+        venue_ids(&app, "minChannels=16").await
+    );
+    assert_eq!(vec!["club"], venue_ids(&app, "minMonitorMixes=2").await);
+    assert_eq!(
+        vec!["club"],
+        venue_ids(&app, "backline=drum%20kit,bass").await
+    );
+}
@@ -0,0 +1,195 @@
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use tapped_api_rs::domain::models::{
+    booking::{Booking, BookingStatus},
+    review::{ModerationStatus, Review, ReviewType},
+    user::UserModel,
+};
+
+use crate::helpers::{spawn_app, TestApp};
+
+fn seed(app: &TestApp) {
+    app.database.insert_user(UserModel::fixture(
+        "performer",
+        json!({
+            "artistName": "The Performer",
+            "performerInfo": { "genres": ["rock"] },
+        }),
+    ));
+    for day in 1..=3 {
+        app.database.insert_booking(Booking {
+            id: format!("booking-{day}"),
+            requester_id: Some("venue".into()),
+            requestee_id: "performer".into(),
+            status: BookingStatus::Confirmed,
+            start_time: Utc.with_ymd_and_hms(2024, 6, day, 20, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2024, 6, day, 23, 0, 0).unwrap(),
+            ..Default::default()
+        });
+    }
+    app.database.insert_review(Review {
+        id: "review".into(),
+        booker_id: "venue".into(),
+        performer_id: "performer".into(),
+        booking_id: "booking-1".into(),
+        timestamp: Utc.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap(),
+        overall_rating: 4.0,
+        overall_review: "solid".into(),
+        review_type: ReviewType::Performer,
+        moderation_status: ModerationStatus::Approved,
+    });
+}
+
+async fn get_json(app: &TestApp, path: &str) -> (u16, Value) {
+    let response = app
+        .get(path)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    let status = response.status().as_u16();
+
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn v1_keeps_its_shape() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let (status, body) = get_json(&app, "/v1/performer/performer").await;
+
+    assert_eq!(200, status);
+    assert_eq!(3, body["bookings"]["count"]);
+    assert!(body["bookings"]["items"].is_array());
+    assert_eq!(1, body["reviews"]["count"]);
+    assert_eq!(4.0, body["reviews"]["rating"]);
+}
+
+#[tokio::test]
+async fn v2_leaves_relations_out_unless_included() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let (status, body) = get_json(&app, "/v2/performer/performer").await;
+
+    assert_eq!(200, status);
+    assert_eq!("The Performer", body["displayName"]);
+    assert!(body.get("bookings").is_none());
+    assert!(body.get("reviews").is_none());
+    assert!(!app
+        .database
+        .calls()
+        .contains(&"get_bookings_by_performer_id"));
+}
+
+#[tokio::test]
+async fn v2_embeds_paginated_relations_with_typed_times() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let (status, body) = get_json(&app, "/v2/performer/performer?include=bookings,reviews").await;
+
+    assert_eq!(200, status);
+    let bookings = &body["bookings"];
+    assert_eq!(3, bookings["total"]);
+    assert_eq!(1, bookings["page"]);
+    assert_eq!(false, bookings["hasMore"]);
+    assert_eq!("booking-3", bookings["items"][0]["id"]);
+    assert_eq!("2024-06-03T20:00:00Z", bookings["items"][0]["startTime"]);
+    assert_eq!("confirmed", bookings["items"][0]["status"]);
+    assert_eq!(
+        "2024-06-02T12:00:00Z",
+        body["reviews"]["items"][0]["createdAt"]
+    );
+}
+
+#[tokio::test]
+async fn v2_relations_page_through_their_own_routes() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let (status, body) = get_json(&app, "/v2/performer/performer/bookings?page=2&pageSize=2").await;
+
+    assert_eq!(200, status);
+    assert_eq!(3, body["total"]);
+    assert_eq!(2, body["page"]);
+    assert_eq!(false, body["hasMore"]);
+    assert_eq!(vec!["booking-1"], ids(&body));
+
+    let (status, _) = get_json(&app, "/v2/performer/performer/bookings?page=0").await;
+    assert_eq!(400, status);
+    let (status, _) = get_json(&app, "/v2/performer/missing/reviews").await;
+    assert_eq!(404, status);
+}
+
+#[tokio::test]
+async fn v2_search_is_paginated() {
+    let app = spawn_app().await;
+    seed(&app);
+    app.database.insert_user(UserModel::fixture(
+        "performer-two",
+        json!({ "artistName": "Two" }),
+    ));
+
+    let (status, body) = get_json(&app, "/v2/performer/search?query=performer&pageSize=1").await;
+
+    assert_eq!(200, status);
+    assert_eq!(2, body["total"]);
+    assert_eq!(1, body["items"].as_array().unwrap().len());
+    assert_eq!(true, body["hasMore"]);
+}
+
+#[tokio::test]
+async fn v2_requires_an_api_key() {
+    let app = spawn_app().await;
+    seed(&app);
+
+    let response = app
+        .api_client
+        .get(format!("{}/v2/performer/performer", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn each_version_has_its_own_openapi_document() {
+    let app = spawn_app().await;
+
+    let v1: Value = app
+        .api_client
+        .get(format!("{}/docs/v1/openapi.json", app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let v2: Value = app
+        .api_client
+        .get(format!("{}/docs/v2/openapi.json", app.address))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("0.1.0", v1["info"]["version"]);
+    assert_eq!("2.0.0", v2["info"]["version"]);
+    assert_eq!("/v2", v2["servers"][0]["url"]);
+    assert!(v2["paths"]["/performer/{id}"]["get"].is_object());
+    assert!(v2["paths"]["/performer/{id}/bookings"]["get"].is_object());
+    assert!(v2["components"]["schemas"]["PerformerV2"].is_object());
+}
+
+fn ids(page: &Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["id"].as_str().unwrap())
+        .collect()
+}
@@ -0,0 +1,455 @@
+use std::{
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde_json::{json, Value};
+use tapped_api_rs::{
+    data::{change_feed::ChangeFeed, database::Database},
+    domain::{
+        models::{
+            booking::{Booking, BookingStatus},
+            review::{ModerationStatus, Review, ReviewType},
+            webhook::{WebhookDelivery, WebhookDeliveryStatus, WebhookEventType},
+        },
+        webhooks::dispatcher::{sign_payload, WebhookDispatcher, WebhookPolicy},
+    },
+};
+
+use crate::helpers::{spawn_app, TestApp, TEST_USER_ID};
+
+#[derive(Clone)]
+struct Receiver {
+    status: Arc<AtomicU16>,
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    receiver.requests.lock().unwrap().push((headers, body));
+    StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+}
+
+async fn spawn_receiver(status: u16) -> (String, Receiver) {
+    let receiver = Receiver {
+        status: Arc::new(AtomicU16::new(status)),
+        requests: Arc::new(Mutex::new(vec![])),
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    let app = Router::new()
+        .route("/hook", post(receive))
+        .with_state(receiver.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    (url, receiver)
+}
+
+async fn register(app: &TestApp, url: &str, event_types: Value) -> Value {
+    let response = app
+        .post("/v1/webhooks")
+        .json(&json!({ "url": url, "eventTypes": event_types }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(reqwest::StatusCode::CREATED, response.status());
+
+    response.json().await.unwrap()
+}
+
+async fn wait_for_deliveries(
+    app: &TestApp,
+    webhook_id: &str,
+    done: impl Fn(&[Value]) -> bool,
+) -> Vec<Value> {
+    for _ in 0..100 {
+        let deliveries: Vec<Value> = app
+            .get(&format!("/v1/webhooks/{webhook_id}/deliveries"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if done(&deliveries) {
+            return deliveries;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    panic!("Timed out waiting for webhook deliveries");
+}
+
+fn confirmed_booking() -> Booking {
+    Booking {
+        id: "booking-1".into(),
+        requester_id: Some("booker".into()),
+        requestee_id: "performer".into(),
+        status: BookingStatus::Confirmed,
+        ..Default::default()
+    }
+}
+
+/// A delivery stuck in `pending` that was due at `due`, as left behind by an
+/// instance that stopped mid-retry.
+async fn insert_pending_delivery(
+    app: &TestApp,
+    webhook: &Value,
+    id: &str,
+    due: DateTime<Utc>,
+) -> WebhookDelivery {
+    let delivery = WebhookDelivery {
+        id: id.into(),
+        webhook_id: webhook["id"].as_str().unwrap().into(),
+        owner_id: TEST_USER_ID.into(),
+        event_id: format!("event-{id}"),
+        event_type: WebhookEventType::BookingConfirmed,
+        payload: json!({ "type": "booking.confirmed" }),
+        status: WebhookDeliveryStatus::Pending,
+        attempts: 1,
+        last_status_code: Some(500),
+        last_error: Some("endpoint responded with 500".into()),
+        next_attempt_at: Some(due),
+        timestamp: due,
+    };
+    app.database
+        .upsert_webhook_delivery(&delivery)
+        .await
+        .unwrap();
+
+    delivery
+}
+
+#[tokio::test]
+async fn webhooks_can_be_managed() {
+    let app = spawn_app().await;
+
+    let created = register(
+        &app,
+        "https://example.com/hook",
+        json!(["booking.confirmed"]),
+    )
+    .await;
+    let id = created["id"].as_str().unwrap();
+    assert!(created["secret"].as_str().unwrap().starts_with("whsec_"));
+    assert_eq!(true, created["active"]);
+
+    let listed: Vec<Value> = app
+        .get("/v1/webhooks")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, listed.len());
+    assert_eq!(id, listed[0]["id"]);
+    assert!(listed[0].get("secret").is_none());
+
+    let updated: Value = app
+        .request(Method::PATCH, &format!("/v1/webhooks/{id}"))
+        .json(&json!({ "active": false, "eventTypes": ["review.created"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(false, updated["active"]);
+    assert_eq!(json!(["review.created"]), updated["eventTypes"]);
+
+    let response = app
+        .request(Method::DELETE, &format!("/v1/webhooks/{id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::NO_CONTENT, response.status());
+
+    let response = app.get(&format!("/v1/webhooks/{id}")).send().await.unwrap();
+    assert_eq!(reqwest::StatusCode::NOT_FOUND, response.status());
+}
+
+#[tokio::test]
+async fn webhook_registration_is_validated() {
+    let app = spawn_app().await;
+
+    for body in [
+        json!({ "url": "ftp://example.com/hook", "eventTypes": ["booking.confirmed"] }),
+        json!({ "url": "https://example.com/hook", "eventTypes": [] }),
+    ] {
+        let response = app.post("/v1/webhooks").json(&body).send().await.unwrap();
+        assert_eq!(reqwest::StatusCode::BAD_REQUEST, response.status());
+    }
+}
+
+#[tokio::test]
+async fn booking_changes_are_delivered_signed() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(200).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    app.change_feed.publish(&confirmed_booking()).await.unwrap();
+
+    let deliveries = wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.first().map(|d| d["status"] == "delivered") == Some(true)
+    })
+    .await;
+    assert_eq!(1, deliveries[0]["attempts"]);
+    assert_eq!(200, deliveries[0]["lastStatusCode"]);
+
+    let requests = receiver.requests.lock().unwrap();
+    assert_eq!(1, requests.len());
+    let (headers, body) = &requests[0];
+    assert_eq!("booking.confirmed", headers["tapped-event"]);
+
+    let signature = headers["tapped-signature"].to_str().unwrap();
+    let (timestamp, signature) = signature
+        .strip_prefix("t=")
+        .and_then(|rest| rest.split_once(",v1="))
+        .unwrap();
+    assert_eq!(
+        sign_payload(
+            webhook["secret"].as_str().unwrap(),
+            timestamp.parse().unwrap(),
+            body
+        ),
+        signature
+    );
+
+    let payload: Value = serde_json::from_slice(body).unwrap();
+    assert_eq!("booking.confirmed", payload["type"]);
+    assert_eq!("booking-1", payload["data"]["id"]);
+    assert_eq!("performer", payload["data"]["performerId"]);
+}
+
+#[tokio::test]
+async fn every_instance_sees_the_change_but_it_is_delivered_once() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(200).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    // Another instance subscribed to the same changes.
+    WebhookDispatcher::new(
+        Arc::new(app.database.clone()),
+        WebhookPolicy {
+            require_https: false,
+            allow_private_hosts: true,
+            ..Default::default()
+        },
+    )
+    .start(Arc::new(app.change_feed.clone()))
+    .await
+    .unwrap();
+
+    app.change_feed.publish(&confirmed_booking()).await.unwrap();
+
+    let deliveries = wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.first().map(|d| d["status"] == "delivered") == Some(true)
+    })
+    .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(1, deliveries.len());
+    assert_eq!(1, receiver.requests.lock().unwrap().len());
+}
+
+#[tokio::test]
+async fn unchanged_booking_status_is_not_redelivered() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(200).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed", "booking.canceled"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    let mut booking = confirmed_booking();
+    app.change_feed.publish(&booking).await.unwrap();
+    app.change_feed.publish(&booking).await.unwrap();
+    booking.status = BookingStatus::Canceled;
+    app.change_feed.publish(&booking).await.unwrap();
+
+    wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.len() == 2 && deliveries.iter().all(|d| d["status"] == "delivered")
+    })
+    .await;
+
+    let events: Vec<String> = receiver
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(headers, _)| headers["tapped-event"].to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(2, events.len());
+    assert!(events.contains(&"booking.confirmed".to_string()));
+    assert!(events.contains(&"booking.canceled".to_string()));
+}
+
+#[tokio::test]
+async fn new_reviews_are_delivered() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(204).await;
+    let webhook = register(&app, &url, json!(["review.created"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    app.change_feed
+        .publish_review(&Review {
+            id: "review-1".into(),
+            booker_id: "booker".into(),
+            performer_id: "performer".into(),
+            booking_id: "booking-1".into(),
+            timestamp: chrono::Utc::now(),
+            overall_rating: 4.5,
+            overall_review: "great show".into(),
+            review_type: ReviewType::Performer,
+            moderation_status: ModerationStatus::Approved,
+        })
+        .await
+        .unwrap();
+
+    wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.first().map(|d| d["status"] == "delivered") == Some(true)
+    })
+    .await;
+
+    let requests = receiver.requests.lock().unwrap();
+    let payload: Value = serde_json::from_slice(&requests[0].1).unwrap();
+    assert_eq!("review.created", payload["type"]);
+    assert_eq!("review-1", payload["data"]["id"]);
+    assert_eq!(4.5, payload["data"]["rating"]);
+}
+
+#[tokio::test]
+async fn failing_deliveries_are_retried_then_dead_lettered() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(500).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    app.change_feed.publish(&confirmed_booking()).await.unwrap();
+
+    let deliveries = wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.first().map(|d| d["status"] == "deadLettered") == Some(true)
+    })
+    .await;
+    assert_eq!(3, deliveries[0]["attempts"]);
+    assert_eq!(500, deliveries[0]["lastStatusCode"]);
+    assert_eq!(3, receiver.requests.lock().unwrap().len());
+
+    let dead_letters: Vec<Value> = app
+        .get(&format!(
+            "/v1/webhooks/{webhook_id}/deliveries?status=deadLettered"
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, dead_letters.len());
+
+    receiver.status.store(200, Ordering::SeqCst);
+    let delivery_id = dead_letters[0]["id"].as_str().unwrap();
+    let response = app
+        .post(&format!(
+            "/v1/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver"
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::ACCEPTED, response.status());
+
+    wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.first().map(|d| d["status"] == "delivered") == Some(true)
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn stalled_deliveries_are_resumed_on_start() {
+    let app = spawn_app().await;
+    let (url, receiver) = spawn_receiver(200).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+    let an_hour = chrono::Duration::hours(1);
+    insert_pending_delivery(&app, &webhook, "stalled", Utc::now() - an_hour * 2).await;
+    insert_pending_delivery(&app, &webhook, "waiting", Utc::now() + an_hour).await;
+
+    // A fresh instance with the default one hour `max_delay`.
+    let dispatcher = WebhookDispatcher::new(
+        Arc::new(app.database.clone()),
+        WebhookPolicy {
+            require_https: false,
+            allow_private_hosts: true,
+            ..Default::default()
+        },
+    );
+    let resumed = dispatcher.resume_stalled().await.unwrap();
+    assert_eq!(
+        vec!["stalled"],
+        resumed.iter().map(|d| &d.id).collect::<Vec<_>>()
+    );
+    // Once claimed, it isn't stalled for anyone else.
+    assert!(dispatcher.resume_stalled().await.unwrap().is_empty());
+
+    let deliveries = wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries.iter().any(|d| d["status"] == "delivered")
+    })
+    .await;
+    let statuses: Vec<(&str, &str)> = deliveries
+        .iter()
+        .map(|d| (d["id"].as_str().unwrap(), d["status"].as_str().unwrap()))
+        .collect();
+    assert!(statuses.contains(&("stalled", "delivered")));
+    assert!(statuses.contains(&("waiting", "pending")));
+    assert_eq!(
+        2,
+        deliveries.iter().find(|d| d["id"] == "stalled").unwrap()["attempts"]
+    );
+    assert_eq!(1, receiver.requests.lock().unwrap().len());
+}
+
+#[tokio::test]
+async fn stalled_deliveries_can_be_redelivered() {
+    let app = spawn_app().await;
+    let (url, _receiver) = spawn_receiver(200).await;
+    let webhook = register(&app, &url, json!(["booking.confirmed"])).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+    let an_hour = chrono::Duration::hours(1);
+    insert_pending_delivery(&app, &webhook, "stalled", Utc::now() - an_hour).await;
+    insert_pending_delivery(&app, &webhook, "waiting", Utc::now() + an_hour).await;
+
+    let redeliver = |id: &str| {
+        app.post(&format!(
+            "/v1/webhooks/{webhook_id}/deliveries/{id}/redeliver"
+        ))
+        .send()
+    };
+
+    assert_eq!(
+        reqwest::StatusCode::CONFLICT,
+        redeliver("waiting").await.unwrap().status()
+    );
+    assert_eq!(
+        reqwest::StatusCode::ACCEPTED,
+        redeliver("stalled").await.unwrap().status()
+    );
+
+    wait_for_deliveries(&app, webhook_id, |deliveries| {
+        deliveries
+            .iter()
+            .any(|d| d["id"] == "stalled" && d["status"] == "delivered")
+    })
+    .await;
+}